@@ -0,0 +1 @@
+DROP TABLE IF EXISTS engineering_metrics.run_locks;
\ No newline at end of file
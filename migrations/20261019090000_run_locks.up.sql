@@ -0,0 +1,8 @@
+-- Lease rows that keep overlapping collector runs from importing the same group/org concurrently
+CREATE TABLE engineering_metrics.run_locks (
+    lock_key VARCHAR PRIMARY KEY,
+    holder_id UUID NOT NULL,
+    holder_name VARCHAR NOT NULL,
+    acquired_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS engineering_metrics.idx_projects_group_full_path;
+ALTER TABLE engineering_metrics.projects
+    DROP COLUMN IF EXISTS removed_at,
+    DROP COLUMN IF EXISTS last_synced_at,
+    DROP COLUMN IF EXISTS group_full_path;
\ No newline at end of file
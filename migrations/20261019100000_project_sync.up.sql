@@ -0,0 +1,6 @@
+-- Track which group a project was synced from so projects that disappear from it can be flagged
+ALTER TABLE engineering_metrics.projects
+    ADD COLUMN group_full_path VARCHAR NULL,
+    ADD COLUMN last_synced_at TIMESTAMPTZ NULL,
+    ADD COLUMN removed_at TIMESTAMPTZ NULL;
+CREATE INDEX idx_projects_group_full_path ON engineering_metrics.projects (group_full_path);
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS engineering_metrics.merge_request_dead_letters;
\ No newline at end of file
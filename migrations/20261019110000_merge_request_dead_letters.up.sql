@@ -0,0 +1,16 @@
+-- Merge requests that failed to import, retried at the start of subsequent runs
+CREATE TABLE engineering_metrics.merge_request_dead_letters (
+    id UUID PRIMARY KEY DEFAULT uuidv7(),
+    mr_id VARCHAR NOT NULL,
+    group_full_path VARCHAR NOT NULL,
+    stage VARCHAR NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'pending',
+    error_message TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    payload JSONB NOT NULL,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (mr_id)
+);
+CREATE INDEX idx_merge_request_dead_letters_group_status
+    ON engineering_metrics.merge_request_dead_letters (group_full_path, status);
@@ -0,0 +1,3 @@
+ALTER TABLE engineering_metrics.import_progress DROP COLUMN IF EXISTS run_id;
+DROP TABLE IF EXISTS engineering_metrics.run_history_group_stats;
+DROP TABLE IF EXISTS engineering_metrics.run_history;
\ No newline at end of file
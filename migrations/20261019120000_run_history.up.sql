@@ -0,0 +1,30 @@
+-- Every collector run, successful or not, with per-group statistics
+CREATE TABLE engineering_metrics.run_history (
+    id UUID PRIMARY KEY DEFAULT uuidv7(),
+    collector VARCHAR NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'running',
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ NULL,
+    error_summary TEXT NULL
+);
+CREATE INDEX idx_run_history_collector_started_at
+    ON engineering_metrics.run_history (collector, started_at DESC);
+
+CREATE TABLE engineering_metrics.run_history_group_stats (
+    run_id UUID NOT NULL REFERENCES engineering_metrics.run_history (id) ON DELETE CASCADE,
+    group_key VARCHAR NOT NULL,
+    import_type VARCHAR NOT NULL,
+    items_fetched BIGINT NOT NULL DEFAULT 0,
+    items_filtered BIGINT NOT NULL DEFAULT 0,
+    items_skipped_existing BIGINT NOT NULL DEFAULT 0,
+    items_summarized BIGINT NOT NULL DEFAULT 0,
+    items_persisted BIGINT NOT NULL DEFAULT 0,
+    items_failed BIGINT NOT NULL DEFAULT 0,
+    ai_time_ms BIGINT NOT NULL DEFAULT 0,
+    api_calls BIGINT NOT NULL DEFAULT 0,
+    error_summary TEXT NULL,
+    PRIMARY KEY (run_id, group_key, import_type)
+);
+
+ALTER TABLE engineering_metrics.import_progress
+    ADD COLUMN run_id UUID NULL REFERENCES engineering_metrics.run_history (id) ON DELETE SET NULL;
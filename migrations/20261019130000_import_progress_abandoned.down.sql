@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS engineering_metrics.idx_import_progress_last_activity_at;
+DELETE FROM engineering_metrics.import_progress WHERE status = 'abandoned';
+ALTER TABLE engineering_metrics.import_progress
+    ADD UNIQUE (group_full_path, import_type, updated_after, status);
\ No newline at end of file
@@ -0,0 +1,22 @@
+-- Abandoned imports are restarted with the same updated_after, which the original
+-- (group_full_path, import_type, updated_after, status) constraint would reject once an
+-- import is abandoned twice. Overlapping runs are prevented by run_locks instead.
+DO $$
+DECLARE
+    unique_constraint_name TEXT;
+BEGIN
+    SELECT conname INTO unique_constraint_name
+    FROM pg_constraint
+    WHERE conrelid = 'engineering_metrics.import_progress'::regclass
+      AND contype = 'u';
+
+    IF unique_constraint_name IS NOT NULL THEN
+        EXECUTE format(
+            'ALTER TABLE engineering_metrics.import_progress DROP CONSTRAINT %I',
+            unique_constraint_name
+        );
+    END IF;
+END $$;
+
+CREATE INDEX idx_import_progress_last_activity_at
+    ON engineering_metrics.import_progress (last_activity_at);
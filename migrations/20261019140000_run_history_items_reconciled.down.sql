@@ -0,0 +1,2 @@
+ALTER TABLE engineering_metrics.run_history_group_stats
+    DROP COLUMN items_reconciled;
@@ -0,0 +1,2 @@
+ALTER TABLE engineering_metrics.run_history_group_stats
+    ADD COLUMN items_reconciled BIGINT NOT NULL DEFAULT 0;
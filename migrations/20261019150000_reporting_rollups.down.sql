@@ -0,0 +1,8 @@
+DROP MATERIALIZED VIEW IF EXISTS engineering_metrics.copilot_daily_language_rollup;
+DROP MATERIALIZED VIEW IF EXISTS engineering_metrics.copilot_daily_ide_rollup;
+DROP MATERIALIZED VIEW IF EXISTS engineering_metrics.copilot_daily_org_rollup;
+DROP MATERIALIZED VIEW IF EXISTS engineering_metrics.mr_weekly_category_rollup;
+DROP MATERIALIZED VIEW IF EXISTS engineering_metrics.mr_weekly_size_rollup;
+DROP MATERIALIZED VIEW IF EXISTS engineering_metrics.mr_weekly_author_rollup;
+DROP MATERIALIZED VIEW IF EXISTS engineering_metrics.mr_weekly_project_rollup;
+DROP VIEW IF EXISTS engineering_metrics.merge_request_facts;
@@ -0,0 +1,156 @@
+-- Reporting views shared by the dashboards. The materialized rollups are refreshed after every
+-- collector run (see `refresh-rollups`); each has a unique index so it can be refreshed
+-- concurrently without blocking readers.
+
+-- Merged merge requests with their cycle time and size bucket
+CREATE VIEW engineering_metrics.merge_request_facts AS
+SELECT
+    mr_id,
+    project_id,
+    project_path,
+    created_by AS author,
+    created_at,
+    merged_at,
+    (date_trunc('week', merged_at AT TIME ZONE 'UTC'))::date AS merged_week,
+    EXTRACT(EPOCH FROM merged_at - created_at) / 3600.0 AS cycle_time_hours,
+    (diff_stats_summary ->> 'additions')::integer AS additions,
+    (diff_stats_summary ->> 'deletions')::integer AS deletions,
+    (diff_stats_summary ->> 'file_count')::integer AS file_count,
+    CASE
+        WHEN diff_stats_summary ->> 'additions' IS NULL THEN 'unknown'
+        WHEN (diff_stats_summary ->> 'additions')::integer
+            + (diff_stats_summary ->> 'deletions')::integer <= 10 THEN 'xs'
+        WHEN (diff_stats_summary ->> 'additions')::integer
+            + (diff_stats_summary ->> 'deletions')::integer <= 100 THEN 's'
+        WHEN (diff_stats_summary ->> 'additions')::integer
+            + (diff_stats_summary ->> 'deletions')::integer <= 500 THEN 'm'
+        WHEN (diff_stats_summary ->> 'additions')::integer
+            + (diff_stats_summary ->> 'deletions')::integer <= 1000 THEN 'l'
+        ELSE 'xl'
+    END AS size_bucket,
+    COALESCE(mr_ai_category, 'uncategorized') AS ai_category
+FROM engineering_metrics.merge_requests
+WHERE merged_at IS NOT NULL;
+
+-- Merged merge requests per week and project
+CREATE MATERIALIZED VIEW engineering_metrics.mr_weekly_project_rollup AS
+SELECT
+    merged_week,
+    project_id,
+    max(project_path) AS project_path,
+    count(*) AS merged_count,
+    count(DISTINCT author) AS author_count,
+    percentile_cont(0.5) WITHIN GROUP (ORDER BY cycle_time_hours) AS median_cycle_time_hours,
+    percentile_cont(0.9) WITHIN GROUP (ORDER BY cycle_time_hours) AS p90_cycle_time_hours,
+    COALESCE(sum(additions), 0) AS additions,
+    COALESCE(sum(deletions), 0) AS deletions
+FROM engineering_metrics.merge_request_facts
+GROUP BY merged_week, project_id;
+CREATE UNIQUE INDEX idx_mr_weekly_project_rollup
+    ON engineering_metrics.mr_weekly_project_rollup (merged_week, project_id);
+
+-- Merged merge requests per week and author
+CREATE MATERIALIZED VIEW engineering_metrics.mr_weekly_author_rollup AS
+SELECT
+    merged_week,
+    author,
+    count(*) AS merged_count,
+    count(DISTINCT project_id) AS project_count,
+    percentile_cont(0.5) WITHIN GROUP (ORDER BY cycle_time_hours) AS median_cycle_time_hours,
+    COALESCE(sum(additions), 0) AS additions,
+    COALESCE(sum(deletions), 0) AS deletions
+FROM engineering_metrics.merge_request_facts
+GROUP BY merged_week, author;
+CREATE UNIQUE INDEX idx_mr_weekly_author_rollup
+    ON engineering_metrics.mr_weekly_author_rollup (merged_week, author);
+
+-- Merged merge requests per week, project and size bucket
+CREATE MATERIALIZED VIEW engineering_metrics.mr_weekly_size_rollup AS
+SELECT
+    merged_week,
+    project_id,
+    size_bucket,
+    count(*) AS merged_count,
+    percentile_cont(0.5) WITHIN GROUP (ORDER BY cycle_time_hours) AS median_cycle_time_hours
+FROM engineering_metrics.merge_request_facts
+GROUP BY merged_week, project_id, size_bucket;
+CREATE UNIQUE INDEX idx_mr_weekly_size_rollup
+    ON engineering_metrics.mr_weekly_size_rollup (merged_week, project_id, size_bucket);
+
+-- Merged merge requests per week, project and AI category
+CREATE MATERIALIZED VIEW engineering_metrics.mr_weekly_category_rollup AS
+SELECT
+    merged_week,
+    project_id,
+    ai_category,
+    count(*) AS merged_count
+FROM engineering_metrics.merge_request_facts
+GROUP BY merged_week, project_id, ai_category;
+CREATE UNIQUE INDEX idx_mr_weekly_category_rollup
+    ON engineering_metrics.mr_weekly_category_rollup (merged_week, project_id, ai_category);
+
+-- Copilot usage per org and day
+CREATE MATERIALIZED VIEW engineering_metrics.copilot_daily_org_rollup AS
+SELECT
+    org_slug,
+    report_day,
+    count(*) FILTER (
+        WHERE user_initiated_interaction_count > 0
+            OR code_generation_activity_count > 0
+            OR used_agent OR used_chat OR used_cli
+    ) AS active_users,
+    count(*) FILTER (WHERE used_chat) AS chat_users,
+    count(*) FILTER (WHERE used_agent) AS agent_users,
+    sum(code_generation_activity_count) AS code_generation_count,
+    sum(code_acceptance_activity_count) AS code_acceptance_count,
+    round(
+        sum(code_acceptance_activity_count)::numeric
+            / NULLIF(sum(code_generation_activity_count), 0),
+        4
+    ) AS acceptance_rate,
+    sum(loc_suggested_to_add_sum) AS loc_suggested_to_add,
+    sum(loc_added_sum) AS loc_added
+FROM engineering_metrics.copilot_user_daily_metrics
+GROUP BY org_slug, report_day;
+CREATE UNIQUE INDEX idx_copilot_daily_org_rollup
+    ON engineering_metrics.copilot_daily_org_rollup (org_slug, report_day);
+
+-- Copilot usage per org, day and IDE
+CREATE MATERIALIZED VIEW engineering_metrics.copilot_daily_ide_rollup AS
+SELECT
+    daily.org_slug,
+    daily.report_day,
+    ide.ide,
+    count(DISTINCT daily.user_id) AS active_users,
+    sum(ide.code_generation_activity_count) AS code_generation_count,
+    sum(ide.code_acceptance_activity_count) AS code_acceptance_count,
+    round(
+        sum(ide.code_acceptance_activity_count)::numeric
+            / NULLIF(sum(ide.code_generation_activity_count), 0),
+        4
+    ) AS acceptance_rate
+FROM engineering_metrics.copilot_user_daily_ide_metrics ide
+JOIN engineering_metrics.copilot_user_daily_metrics daily ON daily.id = ide.user_metric_id
+GROUP BY daily.org_slug, daily.report_day, ide.ide;
+CREATE UNIQUE INDEX idx_copilot_daily_ide_rollup
+    ON engineering_metrics.copilot_daily_ide_rollup (org_slug, report_day, ide);
+
+-- Copilot code completions per org, day and language, summed over features
+CREATE MATERIALIZED VIEW engineering_metrics.copilot_daily_language_rollup AS
+SELECT
+    daily.org_slug,
+    daily.report_day,
+    lang.language,
+    count(DISTINCT daily.user_id) AS active_users,
+    sum(lang.code_generation_activity_count) AS code_generation_count,
+    sum(lang.code_acceptance_activity_count) AS code_acceptance_count,
+    round(
+        sum(lang.code_acceptance_activity_count)::numeric
+            / NULLIF(sum(lang.code_generation_activity_count), 0),
+        4
+    ) AS acceptance_rate
+FROM engineering_metrics.copilot_user_daily_language_feature_metrics lang
+JOIN engineering_metrics.copilot_user_daily_metrics daily ON daily.id = lang.user_metric_id
+GROUP BY daily.org_slug, daily.report_day, lang.language;
+CREATE UNIQUE INDEX idx_copilot_daily_language_rollup
+    ON engineering_metrics.copilot_daily_language_rollup (org_slug, report_day, language);
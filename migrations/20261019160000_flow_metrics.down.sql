@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS engineering_metrics.flow_metrics;
+DROP VIEW IF EXISTS engineering_metrics.merge_request_flow;
+ALTER TABLE engineering_metrics.merge_requests DROP COLUMN IF EXISTS first_approved_at;
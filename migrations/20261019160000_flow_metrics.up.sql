@@ -0,0 +1,47 @@
+-- First approval of a merge request, from the system note GitLab adds when it is approved
+ALTER TABLE engineering_metrics.merge_requests ADD COLUMN first_approved_at TIMESTAMPTZ NULL;
+
+-- Per merge request flow metrics of merged merge requests. Approval times are NULL for merge
+-- requests without approvals or imported before approval times were fetched.
+CREATE VIEW engineering_metrics.merge_request_flow AS
+SELECT
+    mr.mr_id,
+    mr.project_id,
+    COALESCE(p.p_full_path, mr.project_path) AS project_full_path,
+    COALESCE(p.group_full_path, 'unknown') AS group_full_path,
+    mr.created_by AS author,
+    CASE WHEN jsonb_typeof(mr.labels) = 'array' THEN mr.labels ELSE '[]'::jsonb END AS labels,
+    mr.created_at,
+    mr.first_approved_at,
+    mr.merged_at,
+    EXTRACT(EPOCH FROM mr.merged_at - mr.created_at)::double precision / 3600 AS lead_time_hours,
+    EXTRACT(EPOCH FROM mr.first_approved_at - mr.created_at)::double precision / 3600
+        AS time_to_first_approval_hours,
+    EXTRACT(EPOCH FROM mr.merged_at - mr.first_approved_at)::double precision / 3600
+        AS approval_to_merge_hours,
+    (mr.diff_stats_summary ->> 'additions')::integer
+        + (mr.diff_stats_summary ->> 'deletions')::integer AS size_lines
+FROM engineering_metrics.merge_requests mr
+LEFT JOIN engineering_metrics.projects p ON p.p_id = 'gid://gitlab/Project/' || mr.project_id
+WHERE mr.merged_at IS NOT NULL;
+
+-- Flow metric distributions per window, dimension and metric, computed by the collector.
+-- A window covers the `window_days` days before `window_end` (exclusive, UTC).
+CREATE TABLE engineering_metrics.flow_metrics (
+    window_days INTEGER NOT NULL,
+    window_start DATE NOT NULL,
+    window_end DATE NOT NULL,
+    dimension VARCHAR NOT NULL,
+    dimension_value VARCHAR NOT NULL,
+    metric VARCHAR NOT NULL,
+    sample_count BIGINT NOT NULL,
+    mean DOUBLE PRECISION NULL,
+    p50 DOUBLE PRECISION NULL,
+    p75 DOUBLE PRECISION NULL,
+    p90 DOUBLE PRECISION NULL,
+    p95 DOUBLE PRECISION NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (window_end, window_days, dimension, dimension_value, metric)
+);
+CREATE INDEX idx_flow_metrics_dimension
+    ON engineering_metrics.flow_metrics (dimension, dimension_value, metric, window_end DESC);
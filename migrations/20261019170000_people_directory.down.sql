@@ -0,0 +1,8 @@
+DROP VIEW IF EXISTS engineering_metrics.copilot_user_daily_teams;
+DROP VIEW IF EXISTS engineering_metrics.merge_request_teams;
+DROP VIEW IF EXISTS engineering_metrics.unmapped_identities;
+DROP TABLE IF EXISTS engineering_metrics.person_alias_candidates;
+DROP TABLE IF EXISTS engineering_metrics.team_memberships;
+DROP TABLE IF EXISTS engineering_metrics.teams;
+DROP TABLE IF EXISTS engineering_metrics.person_aliases;
+DROP TABLE IF EXISTS engineering_metrics.people;
@@ -0,0 +1,96 @@
+-- People of the organisation, keyed by email
+CREATE TABLE engineering_metrics.people (
+    person_id UUID PRIMARY KEY DEFAULT uuidv7(),
+    email VARCHAR NOT NULL UNIQUE,
+    display_name VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Lowercased GitLab usernames and GitHub logins of a person. `origin` is `directory` for
+-- aliases imported from a directory file and `match` for accepted match candidates.
+CREATE TABLE engineering_metrics.person_aliases (
+    source VARCHAR NOT NULL CHECK (source IN ('gitlab', 'github')),
+    alias VARCHAR NOT NULL,
+    person_id UUID NOT NULL REFERENCES engineering_metrics.people (person_id) ON DELETE CASCADE,
+    origin VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (source, alias)
+);
+CREATE INDEX idx_person_aliases_person ON engineering_metrics.person_aliases (person_id);
+
+CREATE TABLE engineering_metrics.teams (
+    team_id UUID PRIMARY KEY DEFAULT uuidv7(),
+    name VARCHAR NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Team membership with inclusive effective dates; NULL leaves that end open
+CREATE TABLE engineering_metrics.team_memberships (
+    id UUID PRIMARY KEY DEFAULT uuidv7(),
+    person_id UUID NOT NULL REFERENCES engineering_metrics.people (person_id) ON DELETE CASCADE,
+    team_id UUID NOT NULL REFERENCES engineering_metrics.teams (team_id) ON DELETE CASCADE,
+    valid_from DATE NULL,
+    valid_to DATE NULL,
+    CHECK (valid_to IS NULL OR valid_from IS NULL OR valid_to >= valid_from)
+);
+CREATE INDEX idx_team_memberships_person ON engineering_metrics.team_memberships (person_id);
+
+-- Proposed alias of a person found by automatic matching, waiting for review. Rejected
+-- candidates are kept so they are not proposed again.
+CREATE TABLE engineering_metrics.person_alias_candidates (
+    id UUID PRIMARY KEY DEFAULT uuidv7(),
+    source VARCHAR NOT NULL,
+    alias VARCHAR NOT NULL,
+    person_id UUID NOT NULL REFERENCES engineering_metrics.people (person_id) ON DELETE CASCADE,
+    reason VARCHAR NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_at TIMESTAMPTZ NULL,
+    UNIQUE (source, alias, person_id)
+);
+CREATE INDEX idx_person_alias_candidates_status
+    ON engineering_metrics.person_alias_candidates (status);
+
+-- GitLab authors and GitHub Copilot users that are not an alias of any person
+CREATE VIEW engineering_metrics.unmapped_identities AS
+SELECT identities.source, identities.alias
+FROM (
+    SELECT DISTINCT 'gitlab' AS source, lower(created_by) AS alias
+    FROM engineering_metrics.merge_requests
+    UNION
+    SELECT DISTINCT 'github', lower(user_login)
+    FROM engineering_metrics.copilot_user_daily_metrics
+) AS identities
+WHERE NOT EXISTS (
+    SELECT 1
+    FROM engineering_metrics.person_aliases a
+    WHERE a.source = identities.source AND a.alias = identities.alias
+);
+
+-- Merge requests of mapped authors with the teams the author belonged to on the merge day, or
+-- the creation day while unmerged. `team` is NULL when the author was in no team.
+CREATE VIEW engineering_metrics.merge_request_teams AS
+SELECT mr.mr_id, mr.created_by AS author, a.person_id, t.name AS team
+FROM engineering_metrics.merge_requests mr
+JOIN engineering_metrics.person_aliases a
+    ON a.source = 'gitlab' AND a.alias = lower(mr.created_by)
+LEFT JOIN engineering_metrics.team_memberships m
+    ON m.person_id = a.person_id
+    AND (m.valid_from IS NULL
+        OR m.valid_from <= (COALESCE(mr.merged_at, mr.created_at) AT TIME ZONE 'UTC')::date)
+    AND (m.valid_to IS NULL
+        OR m.valid_to >= (COALESCE(mr.merged_at, mr.created_at) AT TIME ZONE 'UTC')::date)
+LEFT JOIN engineering_metrics.teams t ON t.team_id = m.team_id;
+
+-- Copilot daily metrics of mapped users with the teams the user belonged to on the report day
+CREATE VIEW engineering_metrics.copilot_user_daily_teams AS
+SELECT c.id AS metrics_id, c.org_slug, c.report_day, c.user_login, a.person_id, t.name AS team
+FROM engineering_metrics.copilot_user_daily_metrics c
+JOIN engineering_metrics.person_aliases a
+    ON a.source = 'github' AND a.alias = lower(c.user_login)
+LEFT JOIN engineering_metrics.team_memberships m
+    ON m.person_id = a.person_id
+    AND (m.valid_from IS NULL OR m.valid_from <= c.report_day)
+    AND (m.valid_to IS NULL OR m.valid_to >= c.report_day)
+LEFT JOIN engineering_metrics.teams t ON t.team_id = m.team_id;
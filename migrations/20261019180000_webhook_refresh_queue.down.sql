@@ -0,0 +1 @@
+DROP TABLE IF EXISTS engineering_metrics.webhook_refresh_queue;
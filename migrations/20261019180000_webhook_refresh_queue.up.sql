@@ -0,0 +1,18 @@
+-- Merge requests and projects reported by GitLab webhooks, waiting to be re-imported.
+-- A row without `mr_iid` refreshes the recently updated merge requests of the project.
+-- Repeated events for the same target update the waiting row instead of adding another.
+CREATE TABLE engineering_metrics.webhook_refresh_queue (
+    id UUID PRIMARY KEY DEFAULT uuidv7(),
+    group_full_path VARCHAR NOT NULL,
+    project_id VARCHAR NOT NULL,
+    project_path VARCHAR NOT NULL,
+    mr_iid VARCHAR NULL,
+    event VARCHAR NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'parked')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE NULLS NOT DISTINCT (project_id, mr_iid)
+);
+CREATE INDEX idx_webhook_refresh_queue_status
+    ON engineering_metrics.webhook_refresh_queue (status, received_at);
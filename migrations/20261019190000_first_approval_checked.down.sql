@@ -0,0 +1 @@
+ALTER TABLE engineering_metrics.merge_requests DROP COLUMN IF EXISTS first_approval_checked_at;
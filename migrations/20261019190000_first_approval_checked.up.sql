@@ -0,0 +1,3 @@
+-- When the notes of a merge request were searched for its first approval, so merge requests
+-- without an approval note are not searched again on every sync
+ALTER TABLE engineering_metrics.merge_requests ADD COLUMN first_approval_checked_at TIMESTAMPTZ NULL;
@@ -0,0 +1,457 @@
+use std::sync::Arc;
+
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::Date;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+use crate::component::flow_metrics::{FlowMetricsError, FlowMetricsHandler, DIMENSIONS};
+use crate::component::query::{
+    CopilotDailyFilter, CopilotDailyRecord, MergeRequestFilter, MergeRequestRecord, Page, Paged,
+    ProjectRecord, QueryError, QueryHandler,
+};
+use crate::dates::parse_day;
+use crate::server::{json_response, timestamp};
+use crate::store::Store;
+
+/// Page size when `per_page` is not given
+pub const DEFAULT_PER_PAGE: u32 = 100;
+/// Largest accepted `per_page`
+pub const MAX_PER_PAGE: u32 = 1000;
+
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("Missing or invalid API key")]
+    Unauthorized,
+    #[error("Query error: {0}")]
+    QueryError(#[from] QueryError),
+    #[error("Flow metrics error: {0}")]
+    FlowMetricsError(#[from] FlowMetricsError),
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::BadRequest(rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::QueryError(_) | ApiError::FlowMetricsError(_) => {
+                tracing::error!(error = %self, "REST API request failed");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        json_response(status, &serde_json::json!({ "error": self.to_string() }))
+    }
+}
+
+/// State shared by the REST API endpoints
+#[derive(Debug, Clone)]
+pub struct ApiState {
+    pub store: Store,
+    /// Keys accepted in the `Authorization: Bearer` or `X-API-Key` header
+    pub keys: Arc<Vec<String>>,
+}
+
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/v1/projects", get(projects))
+        .route("/api/v1/merge-requests", get(merge_requests))
+        .route("/api/v1/copilot/daily-metrics", get(copilot_daily_metrics))
+        .route("/api/v1/flow-metrics", get(flow_metrics))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .with_state(state)
+}
+
+/// Serve the REST API on `listener` until `shutdown` is cancelled
+pub async fn serve(
+    listener: TcpListener,
+    state: ApiState,
+    shutdown: CancellationToken,
+) -> std::io::Result<()> {
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async move { shutdown.cancelled().await })
+        .await
+}
+
+async fn authenticate(
+    State(state): State<ApiState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let key = presented_key(request.headers()).ok_or(ApiError::Unauthorized)?;
+    if !state
+        .keys
+        .iter()
+        .any(|accepted| constant_time_eq(accepted, key))
+    {
+        return Err(ApiError::Unauthorized);
+    }
+
+    Ok(next.run(request).await)
+}
+
+fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    bearer
+        .or_else(|| {
+            headers
+                .get("x-api-key")
+                .and_then(|value| value.to_str().ok())
+        })
+        .map(str::trim)
+}
+
+/// Compare without returning early, so response times do not reveal how much of a key matched
+pub(crate) fn constant_time_eq(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+fn page(page: Option<u32>, per_page: Option<u32>) -> Result<Page, ApiError> {
+    let page = page.unwrap_or(1);
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE);
+    if page < 1 {
+        return Err(ApiError::BadRequest("page must be at least 1".to_string()));
+    }
+    if !(1..=MAX_PER_PAGE).contains(&per_page) {
+        return Err(ApiError::BadRequest(format!(
+            "per_page must be between 1 and {}",
+            MAX_PER_PAGE
+        )));
+    }
+
+    Ok(Page { page, per_page })
+}
+
+fn day(value: Option<&str>, name: &str) -> Result<Option<Date>, ApiError> {
+    value
+        .map(|value| {
+            parse_day(value).map_err(|e| {
+                ApiError::BadRequest(format!("{} must be a YYYY-MM-DD date: {}", name, e))
+            })
+        })
+        .transpose()
+}
+
+#[derive(Serialize)]
+struct PagedBody<T> {
+    items: Vec<T>,
+    page: u32,
+    per_page: u32,
+    next_page: Option<u32>,
+}
+
+fn paged_response<R, T>(page: Page, paged: Paged<R>, body: impl Fn(R) -> T) -> Response
+where
+    T: Serialize,
+{
+    json_response(
+        StatusCode::OK,
+        &PagedBody {
+            items: paged.items.into_iter().map(body).collect(),
+            page: page.page,
+            per_page: page.per_page,
+            next_page: paged.next_page,
+        },
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectParams {
+    group: Option<String>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct ProjectBody {
+    project_id: String,
+    name: String,
+    full_path: String,
+    web_url: String,
+    group_full_path: Option<String>,
+    topics: Vec<String>,
+    last_synced_at: Option<String>,
+    removed_at: Option<String>,
+}
+
+impl From<ProjectRecord> for ProjectBody {
+    fn from(project: ProjectRecord) -> Self {
+        ProjectBody {
+            project_id: project.project_id,
+            name: project.name,
+            full_path: project.full_path,
+            web_url: project.web_url,
+            group_full_path: project.group_full_path,
+            topics: project.topics,
+            last_synced_at: project.last_synced_at.map(timestamp),
+            removed_at: project.removed_at.map(timestamp),
+        }
+    }
+}
+
+async fn projects(
+    State(state): State<ApiState>,
+    params: Result<Query<ProjectParams>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(params) = params?;
+    let page = page(params.page, params.per_page)?;
+    let handler = QueryHandler { store: state.store };
+    let projects = handler.list_projects(params.group.as_deref(), page).await?;
+
+    Ok(paged_response(page, projects, ProjectBody::from))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MergeRequestParams {
+    group: Option<String>,
+    project: Option<String>,
+    author: Option<String>,
+    team: Option<String>,
+    category: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct MergeRequestBody {
+    mr_id: String,
+    mr_iid: String,
+    title: String,
+    web_url: String,
+    project_id: String,
+    project_full_path: String,
+    group_full_path: Option<String>,
+    author: String,
+    merged_by: Option<String>,
+    created_at: String,
+    merged_at: Option<String>,
+    first_approved_at: Option<String>,
+    approved: bool,
+    additions: Option<i64>,
+    deletions: Option<i64>,
+    labels: Vec<String>,
+    category: Option<String>,
+}
+
+impl From<MergeRequestRecord> for MergeRequestBody {
+    fn from(mr: MergeRequestRecord) -> Self {
+        MergeRequestBody {
+            mr_id: mr.mr_id,
+            mr_iid: mr.mr_iid,
+            title: mr.title,
+            web_url: mr.web_url,
+            project_id: mr.project_id,
+            project_full_path: mr.project_full_path,
+            group_full_path: mr.group_full_path,
+            author: mr.author,
+            merged_by: mr.merged_by,
+            created_at: timestamp(mr.created_at),
+            merged_at: mr.merged_at.map(timestamp),
+            first_approved_at: mr.first_approved_at.map(timestamp),
+            approved: mr.approved,
+            additions: mr.additions,
+            deletions: mr.deletions,
+            labels: mr.labels,
+            category: mr.category,
+        }
+    }
+}
+
+async fn merge_requests(
+    State(state): State<ApiState>,
+    params: Result<Query<MergeRequestParams>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(params) = params?;
+    let page = page(params.page, params.per_page)?;
+    let filter = MergeRequestFilter {
+        since: day(params.since.as_deref(), "since")?,
+        until: day(params.until.as_deref(), "until")?,
+        group: params.group,
+        project: params.project,
+        author: params.author,
+        team: params.team,
+        category: params.category,
+    };
+    let handler = QueryHandler { store: state.store };
+    let merge_requests = handler.list_merge_requests(&filter, page).await?;
+
+    Ok(paged_response(page, merge_requests, MergeRequestBody::from))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CopilotDailyParams {
+    org: Option<String>,
+    user: Option<String>,
+    team: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct CopilotDailyBody {
+    org_slug: String,
+    report_day: String,
+    user_login: String,
+    user_initiated_interaction_count: i64,
+    code_generation_activity_count: i64,
+    code_acceptance_activity_count: i64,
+    loc_suggested_to_add_sum: i64,
+    loc_suggested_to_delete_sum: i64,
+    loc_added_sum: i64,
+    loc_deleted_sum: i64,
+    used_agent: bool,
+    used_chat: bool,
+    used_cli: bool,
+}
+
+impl From<CopilotDailyRecord> for CopilotDailyBody {
+    fn from(day: CopilotDailyRecord) -> Self {
+        CopilotDailyBody {
+            org_slug: day.org_slug,
+            report_day: day.report_day.to_string(),
+            user_login: day.user_login,
+            user_initiated_interaction_count: day.user_initiated_interaction_count,
+            code_generation_activity_count: day.code_generation_activity_count,
+            code_acceptance_activity_count: day.code_acceptance_activity_count,
+            loc_suggested_to_add_sum: day.loc_suggested_to_add_sum,
+            loc_suggested_to_delete_sum: day.loc_suggested_to_delete_sum,
+            loc_added_sum: day.loc_added_sum,
+            loc_deleted_sum: day.loc_deleted_sum,
+            used_agent: day.used_agent,
+            used_chat: day.used_chat,
+            used_cli: day.used_cli,
+        }
+    }
+}
+
+async fn copilot_daily_metrics(
+    State(state): State<ApiState>,
+    params: Result<Query<CopilotDailyParams>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(params) = params?;
+    let page = page(params.page, params.per_page)?;
+    let filter = CopilotDailyFilter {
+        since: day(params.since.as_deref(), "since")?,
+        until: day(params.until.as_deref(), "until")?,
+        org: params.org,
+        user: params.user,
+        team: params.team,
+    };
+    let handler = QueryHandler { store: state.store };
+    let days = handler.list_copilot_daily(&filter, page).await?;
+
+    Ok(paged_response(page, days, CopilotDailyBody::from))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FlowMetricParams {
+    window_days: Option<i32>,
+    dimension: Option<String>,
+    dimension_value: Option<String>,
+    metric: Option<String>,
+}
+
+#[derive(Serialize)]
+struct FlowMetricBody {
+    window_days: i32,
+    window_start: String,
+    window_end: String,
+    dimension: String,
+    dimension_value: String,
+    metric: String,
+    sample_count: i64,
+    mean: Option<f64>,
+    p50: Option<f64>,
+    p75: Option<f64>,
+    p90: Option<f64>,
+    p95: Option<f64>,
+}
+
+/// Metrics of the most recently computed window, 30 days unless `window_days` is given
+async fn flow_metrics(
+    State(state): State<ApiState>,
+    params: Result<Query<FlowMetricParams>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(params) = params?;
+    let window_days = params.window_days.unwrap_or(30);
+    if window_days < 1 {
+        return Err(ApiError::BadRequest(
+            "window_days must be at least 1".to_string(),
+        ));
+    }
+    if let Some(dimension) = &params.dimension {
+        if !DIMENSIONS.contains(&dimension.as_str()) {
+            return Err(ApiError::BadRequest(format!(
+                "dimension must be one of {}",
+                DIMENSIONS.join(", ")
+            )));
+        }
+    }
+
+    let handler = FlowMetricsHandler { store: state.store };
+    let metrics: Vec<FlowMetricBody> = handler
+        .fetch_latest(window_days, params.dimension.as_deref())
+        .await?
+        .into_iter()
+        .filter(|metric| {
+            params
+                .dimension_value
+                .as_ref()
+                .is_none_or(|value| &metric.dimension_value == value)
+                && params
+                    .metric
+                    .as_ref()
+                    .is_none_or(|name| &metric.metric == name)
+        })
+        .map(|metric| FlowMetricBody {
+            window_days: metric.window_days,
+            window_start: metric.window_start.to_string(),
+            window_end: metric.window_end.to_string(),
+            dimension: metric.dimension,
+            dimension_value: metric.dimension_value,
+            metric: metric.metric,
+            sample_count: metric.sample_count,
+            mean: metric.mean,
+            p50: metric.p50,
+            p75: metric.p75,
+            p90: metric.p90,
+            p95: metric.p95,
+        })
+        .collect();
+
+    Ok(json_response(
+        StatusCode::OK,
+        &serde_json::json!({ "items": metrics }),
+    ))
+}
@@ -0,0 +1,134 @@
+use clap::Args;
+use tokio_util::sync::CancellationToken;
+use tracing::field::{display, Empty};
+use tracing::{info, instrument, warn, Span};
+
+use crate::cli::{finish_run, AiArgs, CliError, GitlabArgs};
+use crate::client::gitlab_graphql_client::GitlabGraphQLClient;
+use crate::client::gitlab_rest_client::GitlabRestClient;
+use crate::component::import_progress::ImportProgressPolicy;
+use crate::component::merge_request::{MergeRequestError, MergeRequestHandler, ReconcilePolicy};
+use crate::component::run_history::{RunGroupStats, RunHistoryHandler, RunStatus};
+use crate::config::{AiSettings, Config, GitlabApiSettings};
+use crate::context::GitlabContext;
+use crate::scheduler::WorkScheduler;
+use crate::store::Store;
+
+/// Import type under which backfill statistics are recorded in the run history
+const BACKFILL_IMPORT_TYPE: &str = "ai_backfill";
+
+#[derive(Args, Debug)]
+pub struct AiBackfillArgs {
+    #[command(flatten)]
+    pub gitlab: GitlabArgs,
+    #[command(flatten)]
+    pub ai: AiArgs,
+    /// Only backfill merge requests of projects in this group
+    #[arg(long)]
+    pub group: Option<String>,
+    /// Maximum number of merge requests to summarize in this run
+    #[arg(long, default_value_t = 500)]
+    pub limit: i64,
+}
+
+impl AiBackfillArgs {
+    pub fn apply(&self, config: &mut Config) {
+        self.gitlab.apply(config);
+        self.ai.apply(config);
+    }
+}
+
+/// Summarize up to `limit` stored merge requests, optionally only those of one group
+#[instrument(name = "run", skip_all, fields(collector = "ai", run_id = Empty))]
+pub async fn backfill(
+    store: Store,
+    gitlab: GitlabApiSettings,
+    ai: AiSettings,
+    group: Option<&str>,
+    limit: i64,
+    scheduler: WorkScheduler,
+    shutdown: CancellationToken,
+) -> Result<(), CliError> {
+    let run_history_handler = RunHistoryHandler {
+        store: store.clone(),
+    };
+    let run = run_history_handler.start_run("ai").await?;
+    Span::current().record("run_id", display(run.id));
+
+    let context = GitlabContext {
+        store: store.clone(),
+        gitlab_rest_client: GitlabRestClient::new(&gitlab.api_token, gitlab.rest_endpoint)?,
+        gitlab_graphql_client: GitlabGraphQLClient::new(
+            &gitlab.api_token,
+            gitlab.graphql_endpoint,
+        )?,
+        ai_base_url: ai.base_url,
+        ai_model: ai.model,
+        ai_api_key: ai.api_key,
+        ai_max_context_chars: ai.max_context_chars,
+        ai_summaries_enabled: true,
+        upsert_merge_requests: true,
+        // The backfill only writes summaries
+        fetch_approval_times: false,
+        reconcile_policy: ReconcilePolicy::default(),
+        dead_letter_max_attempts: 0,
+        run_id: Some(run.id),
+        import_progress_policy: ImportProgressPolicy::default(),
+        // Summaries are generated one at a time, oldest first
+        max_concurrent_merge_requests: 1,
+        scheduler,
+        shutdown: shutdown.clone(),
+    };
+    let merge_request_handler = MergeRequestHandler { context };
+
+    let mut stats = RunGroupStats::default();
+    let result = merge_request_handler
+        .backfill_ai_summaries(group, limit, &mut stats)
+        .await;
+
+    let group_key = group.unwrap_or("*");
+    if let Err(e) = run_history_handler
+        .persist_group_stats(run.id, group_key, BACKFILL_IMPORT_TYPE, &stats)
+        .await
+    {
+        warn!(error = %e, "Failed to persist AI backfill run statistics");
+    }
+
+    info!(
+        candidates = stats.items_fetched,
+        summarized = stats.items_summarized,
+        failed = stats.items_failed,
+        "AI backfill finished"
+    );
+
+    match result {
+        Ok(()) if stats.items_failed == 0 => {
+            run_history_handler
+                .complete_run(run.id, RunStatus::Succeeded, None)
+                .await?;
+            Ok(())
+        }
+        Ok(()) => {
+            run_history_handler
+                .complete_run(run.id, RunStatus::Failed, stats.error_summary().as_deref())
+                .await?;
+            Ok(())
+        }
+        Err(MergeRequestError::Interrupted) => {
+            run_history_handler
+                .complete_run(
+                    run.id,
+                    RunStatus::Interrupted,
+                    stats.error_summary().as_deref(),
+                )
+                .await?;
+            finish_run(RunStatus::Interrupted)
+        }
+        Err(e) => {
+            run_history_handler
+                .complete_run(run.id, RunStatus::Failed, Some(&e.to_string()))
+                .await?;
+            Err(e.into())
+        }
+    }
+}
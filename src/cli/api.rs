@@ -0,0 +1,39 @@
+use clap::Args;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tracing::info;
+
+use crate::api::{self, ApiState};
+use crate::cli::{shutdown_on_signal, CliError};
+use crate::config::{ApiSettings, Config};
+use crate::store::Store;
+
+#[derive(Args, Debug)]
+pub struct ApiArgs {
+    /// Address to serve the REST API on, e.g. 0.0.0.0:8080
+    #[arg(long)]
+    pub listen_address: Option<String>,
+}
+
+impl ApiArgs {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(listen_address) = &self.listen_address {
+            config.api.listen_address = listen_address.clone();
+        }
+    }
+}
+
+/// Serve the read-only REST API until SIGTERM or Ctrl-C
+pub async fn run(store: Store, settings: ApiSettings) -> Result<(), CliError> {
+    let listener = TcpListener::bind(settings.listen_address).await?;
+    info!(address = %settings.listen_address, "Serving the REST API on /api/v1");
+    let state = ApiState {
+        store,
+        keys: Arc::new(settings.keys),
+    };
+
+    api::serve(listener, state, shutdown_on_signal()).await?;
+    info!("REST API stopped");
+
+    Ok(())
+}
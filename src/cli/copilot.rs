@@ -0,0 +1,308 @@
+use clap::Args;
+use futures::stream::{self, StreamExt};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio_util::sync::CancellationToken;
+use tracing::field::{display, Empty};
+use tracing::{info, info_span, instrument, warn, Instrument, Span};
+use uuid::Uuid;
+
+use crate::cli::{
+    completed_run_status, finish_run, run_lock_holder_name, select_keys, CliError,
+    ImportProgressArgs, RunLockArgs,
+};
+use crate::client::copilot_usage_metrics_client::CopilotUsageMetricsClient;
+use crate::component::copilot_collector_runs::{CopilotCollectorRun, CopilotCollectorRunsHandler};
+use crate::component::copilot_metrics::{self, CopilotMetricsHandler};
+use crate::component::import_progress::ImportProgressHandler;
+use crate::component::run_history::{RunGroupStats, RunHistoryHandler};
+use crate::component::run_lock::RunLockHandler;
+use crate::config::{set_secret, split_list, Config, CopilotSettings};
+use crate::context::CopilotContext;
+use crate::metrics;
+use crate::scheduler::WorkScheduler;
+use crate::store::Store;
+
+type TaskError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Flags override the config file and the environment
+#[derive(Args, Debug)]
+pub struct CopilotSyncArgs {
+    #[arg(long)]
+    pub github_api_token: Option<String>,
+    #[arg(long)]
+    pub github_api_endpoint: Option<String>,
+    #[arg(long)]
+    pub github_api_version: Option<String>,
+    /// Comma-separated slugs of the orgs to import, replacing the configured orgs
+    #[arg(long)]
+    pub orgs: Option<String>,
+    /// Only sync this org (repeatable)
+    #[arg(long = "org")]
+    pub only_orgs: Vec<String>,
+    #[arg(long)]
+    pub report_lag_days: Option<i64>,
+    /// RFC 3339 timestamp to start from for orgs without a successful run
+    #[arg(long)]
+    pub initial_ingestion_date: Option<String>,
+    /// Number of orgs imported at the same time
+    #[arg(long)]
+    pub max_concurrent_orgs: Option<usize>,
+    #[command(flatten)]
+    pub import_progress: ImportProgressArgs,
+    #[command(flatten)]
+    pub run_lock: RunLockArgs,
+}
+
+impl CopilotSyncArgs {
+    pub fn apply(&self, config: &mut Config) {
+        self.import_progress.apply(config);
+        self.run_lock.apply(config);
+
+        let copilot = &mut config.copilot;
+        if let Some(api_token) = &self.github_api_token {
+            set_secret(
+                &mut copilot.api_token,
+                &mut copilot.api_token_file,
+                api_token.clone(),
+            );
+        }
+        if let Some(api_endpoint) = &self.github_api_endpoint {
+            copilot.api_endpoint = api_endpoint.clone();
+        }
+        if let Some(api_version) = &self.github_api_version {
+            copilot.api_version = api_version.clone();
+        }
+        if let Some(orgs) = &self.orgs {
+            copilot.orgs = split_list(orgs);
+        }
+        if let Some(report_lag_days) = self.report_lag_days {
+            copilot.report_lag_days = report_lag_days;
+        }
+        if let Some(initial_ingestion_date) = &self.initial_ingestion_date {
+            copilot.initial_ingestion_date = Some(initial_ingestion_date.clone());
+        }
+        if let Some(max_concurrent_orgs) = self.max_concurrent_orgs {
+            config.concurrency.max_concurrent_orgs = max_concurrent_orgs;
+        }
+    }
+}
+
+/// Sync the selected orgs, or every configured org when none are selected
+#[instrument(name = "run", skip_all, fields(collector = "copilot", run_id = Empty))]
+pub async fn sync(
+    store: Store,
+    config: &Config,
+    settings: CopilotSettings,
+    only_orgs: &[String],
+    scheduler: WorkScheduler,
+    shutdown: CancellationToken,
+) -> Result<(), CliError> {
+    let start_time = OffsetDateTime::now_utc();
+
+    // Copilot watermarks are tracked per org, so a subset of orgs can be synced safely
+    let org_slugs = select_keys(&settings.orgs, only_orgs);
+    if org_slugs.is_empty() {
+        return Err("No Copilot orgs to sync; configure copilot.orgs or pass --org".into());
+    }
+    let initial_ingestion_date = settings.initial_ingestion_date.clone();
+    let import_progress_policy = config.import_progress.policy();
+
+    // Every run is recorded, successful or not, so failed runs can be debugged afterwards
+    let run_history_handler = RunHistoryHandler {
+        store: store.clone(),
+    };
+    let run = run_history_handler.start_run("copilot").await?;
+    Span::current().record("run_id", display(run.id));
+
+    // Retention cleanup of finished imports
+    let import_progress_handler = ImportProgressHandler {
+        store: store.clone(),
+        run_id: Some(run.id),
+        policy: import_progress_policy.clone(),
+    };
+    match import_progress_handler
+        .cleanup_old_imports(import_progress_policy.retention_days)
+        .await
+    {
+        Ok(deleted) => info!(
+            deleted,
+            retention_days = import_progress_policy.retention_days,
+            "Deleted old import progress records"
+        ),
+        Err(e) => warn!(error = %e, "Failed to clean up old import progress records"),
+    }
+
+    let copilot_usage_metrics_client = CopilotUsageMetricsClient::new(
+        &settings.api_token,
+        settings.api_endpoint,
+        &settings.api_version,
+    )?;
+    let context = CopilotContext {
+        store: store.clone(),
+        copilot_usage_metrics_client,
+        github_api_version: settings.api_version,
+        report_lag_days: settings.report_lag_days,
+        run_id: Some(run.id),
+        import_progress_policy,
+        scheduler,
+        shutdown: shutdown.clone(),
+    };
+
+    let copilot_metrics_handler = CopilotMetricsHandler {
+        context: context.clone(),
+    };
+    let copilot_collector_runs_handler = CopilotCollectorRunsHandler {
+        store: store.clone(),
+    };
+    let run_lock_handler = RunLockHandler {
+        store: store.clone(),
+        holder_name: run_lock_holder_name(),
+        lease_seconds: config.run_lock.lease_seconds,
+    };
+
+    let mut tasks = Vec::new();
+    for org_slug in org_slugs {
+        let handler = copilot_metrics_handler.clone();
+        let runs_handler = copilot_collector_runs_handler.clone();
+        let run_lock_handler = run_lock_handler.clone();
+        let run_history_handler = run_history_handler.clone();
+        let run_id = run.id;
+        let initial_ingestion_date = initial_ingestion_date.clone();
+        let org_span = info_span!("org", org = %org_slug);
+
+        tasks.push(async move {
+            tokio::spawn(
+                async move {
+                    // Only one collector instance may import a given org at a time
+                    let lock_key = format!("copilot:{}", org_slug);
+                    let import = import_org_metrics(
+                        handler,
+                        runs_handler,
+                        run_history_handler,
+                        run_id,
+                        org_slug.clone(),
+                        initial_ingestion_date,
+                    );
+                    match run_lock_handler.run_exclusive(&lock_key, import).await? {
+                        Some(result) => result,
+                        None => Err(format!(
+                            "Copilot metrics import for org={} skipped: another instance holds the run lock",
+                            org_slug
+                        )
+                        .into()),
+                    }
+                }
+                .instrument(org_span),
+            )
+            .await
+        });
+    }
+
+    let mut run_errors: Vec<String> = Vec::new();
+
+    // Tasks are spawned lazily so that at most max_concurrent_orgs imports run at once
+    let task_results: Vec<_> = stream::iter(tasks)
+        .buffer_unordered(config.concurrency.max_concurrent_orgs.max(1))
+        .collect()
+        .await;
+    for task_result in task_results {
+        match task_result {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => {
+                warn!(error = %error, "Copilot metrics import failed");
+                run_errors.push(error.to_string());
+            }
+            Err(error) => {
+                warn!(error = %error, "Copilot metrics task failed to join");
+                run_errors.push(error.to_string());
+            }
+        }
+    }
+
+    let end_time = OffsetDateTime::now_utc();
+    let run_status = completed_run_status(run_errors.is_empty(), &shutdown);
+    if run_errors.is_empty() {
+        info!("All Copilot imports completed successfully.");
+        run_history_handler
+            .complete_run(run.id, run_status, None)
+            .await?;
+    } else {
+        warn!("Some Copilot imports failed or were interrupted.");
+        run_history_handler
+            .complete_run(run.id, run_status, Some(&run_errors.join("; ")))
+            .await?;
+    }
+    let elapsed = end_time - start_time;
+    info!(elapsed = %elapsed, status = run_status.as_str(), "Copilot sync finished");
+
+    finish_run(run_status)
+}
+
+async fn import_org_metrics(
+    handler: CopilotMetricsHandler,
+    runs_handler: CopilotCollectorRunsHandler,
+    run_history_handler: RunHistoryHandler,
+    run_id: Uuid,
+    org_slug: String,
+    initial_ingestion_date: Option<String>,
+) -> Result<(), TaskError> {
+    let run_started_at = OffsetDateTime::now_utc();
+    let last_successful_run = runs_handler
+        .fetch_last_successful_collector_run(&org_slug)
+        .await?;
+
+    let updated_after = match last_successful_run {
+        Some(run) => run.last_successful_run_completed_at.format(&Rfc3339)?,
+        None => match initial_ingestion_date {
+            Some(initial_ingestion_date) => initial_ingestion_date,
+            None => OffsetDateTime::now_utc().format(&Rfc3339)?,
+        },
+    };
+
+    info!(
+        org = %org_slug,
+        updated_after = %updated_after,
+        "Starting Copilot metrics import"
+    );
+
+    let mut stats = RunGroupStats::default();
+    let import_result = handler
+        .import_org_users_usage_metrics(&org_slug, &updated_after, &mut stats)
+        .await;
+    if let Err(e) = run_history_handler
+        .persist_group_stats(run_id, &org_slug, copilot_metrics::IMPORT_TYPE, &stats)
+        .await
+    {
+        warn!(org = %org_slug, error = %e, "Failed to persist run statistics");
+    }
+    let summary = import_result?;
+
+    if let Some(last_completed_report_day) = summary.last_completed_report_day {
+        let run_completed_at = OffsetDateTime::now_utc();
+        runs_handler
+            .persist_successful_run(&CopilotCollectorRun {
+                org_slug: org_slug.clone(),
+                last_successful_run_started_at: run_started_at,
+                last_successful_run_completed_at: run_completed_at,
+                last_completed_report_day,
+            })
+            .await?;
+
+        info!(
+            org = %org_slug,
+            days_advanced = summary.days_advanced,
+            records_persisted = summary.records_persisted,
+            last_completed_report_day = %last_completed_report_day,
+            "Completed Copilot metrics import"
+        );
+    } else {
+        info!(
+            org = %org_slug,
+            "No Copilot report days were advanced, leaving successful-run watermark unchanged"
+        );
+    }
+    metrics::record_success("copilot", &org_slug);
+
+    Ok(())
+}
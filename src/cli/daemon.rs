@@ -0,0 +1,293 @@
+use std::future::Future;
+
+use clap::Args;
+use futures::future::{join_all, LocalBoxFuture};
+use futures::FutureExt;
+use time::OffsetDateTime;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, info_span, warn, Instrument, Span};
+
+use crate::cli::{
+    ai, copilot, gitlab, rollups, shutdown_on_signal, AiArgs, CliError, GitlabArgs,
+    ImportProgressArgs, Interrupted, RunLockArgs,
+};
+use crate::config::{Config, DaemonSettings};
+use crate::schedule::CronSchedule;
+use crate::server::{self, ServerState, WebhookReceiver};
+use crate::store::Store;
+
+#[derive(Args, Debug)]
+pub struct DaemonArgs {
+    #[command(flatten)]
+    pub gitlab: GitlabArgs,
+    #[command(flatten)]
+    pub ai: AiArgs,
+    #[command(flatten)]
+    pub import_progress: ImportProgressArgs,
+    #[command(flatten)]
+    pub run_lock: RunLockArgs,
+    /// Cron expression of the GitLab sync, e.g. "*/30 * * * *"
+    #[arg(long)]
+    pub gitlab_sync_schedule: Option<String>,
+    /// Cron expression of the Copilot sync
+    #[arg(long)]
+    pub copilot_sync_schedule: Option<String>,
+    /// Cron expression of the AI summary backfill
+    #[arg(long)]
+    pub ai_backfill_schedule: Option<String>,
+    /// Maximum number of merge requests summarized by one scheduled AI backfill
+    #[arg(long)]
+    pub ai_backfill_limit: Option<i64>,
+    /// Hours without activity of a scheduled job after which `/readyz` fails
+    #[arg(long)]
+    pub max_run_age_hours: Option<i64>,
+    /// Address to serve `/metrics` and the health endpoints on; an empty value disables the HTTP server
+    #[arg(long)]
+    pub metrics_listen_address: Option<String>,
+}
+
+impl DaemonArgs {
+    pub fn apply(&self, config: &mut Config) {
+        self.gitlab.apply(config);
+        self.ai.apply(config);
+        self.import_progress.apply(config);
+        self.run_lock.apply(config);
+
+        let daemon = &mut config.daemon;
+        if let Some(schedule) = &self.gitlab_sync_schedule {
+            daemon.gitlab_sync = Some(schedule.clone());
+        }
+        if let Some(schedule) = &self.copilot_sync_schedule {
+            daemon.copilot_sync = Some(schedule.clone());
+        }
+        if let Some(schedule) = &self.ai_backfill_schedule {
+            daemon.ai_backfill = Some(schedule.clone());
+        }
+        if let Some(limit) = self.ai_backfill_limit {
+            daemon.ai_backfill_limit = limit;
+        }
+        if let Some(max_run_age_hours) = self.max_run_age_hours {
+            daemon.max_run_age_hours = max_run_age_hours;
+        }
+        if let Some(listen_address) = &self.metrics_listen_address {
+            config.metrics.listen_address = listen_address.clone();
+        }
+    }
+}
+
+/// Run the scheduled jobs until SIGTERM or Ctrl-C.
+///
+/// Every job runs in its own loop, so a job never overlaps with itself and fire times missed
+/// while it was running are skipped. On shutdown the running jobs stop after their current page,
+/// leaving their imports checkpointed for the next start.
+pub async fn run(store: Store, config: &Config, settings: DaemonSettings) -> Result<(), CliError> {
+    let shutdown = shutdown_on_signal();
+
+    // The server outlives the jobs, so their final metrics can still be scraped while they drain
+    let server_shutdown = CancellationToken::new();
+    let server = match settings.listen_address {
+        Some(listen_address) => {
+            let listener = TcpListener::bind(listen_address).await?;
+            info!(address = %listen_address, "Serving /metrics, /healthz, /readyz and /status");
+            let webhook = settings.webhook.as_ref().map(|webhook| {
+                info!(address = %listen_address, "Receiving GitLab webhooks on /webhooks/gitlab");
+                WebhookReceiver {
+                    secret_token: webhook.secret_token.clone(),
+                    groups: webhook
+                        .gitlab
+                        .groups
+                        .iter()
+                        .map(|group| group.full_path.clone())
+                        .collect(),
+                }
+            });
+            let state = ServerState {
+                store: store.clone(),
+                collectors: scheduled_collectors(&settings),
+                max_run_age: settings.max_run_age,
+                import_progress_policy: config.import_progress.policy(),
+                started_at: OffsetDateTime::now_utc(),
+                webhook,
+            };
+            Some(tokio::spawn(server::serve(
+                listener,
+                state,
+                server_shutdown.clone(),
+            )))
+        }
+        None => None,
+    };
+
+    // Jobs overlapping in time share the API request limits
+    let scheduler = config.concurrency.scheduler();
+    let mut jobs: Vec<LocalBoxFuture<'_, ()>> = Vec::new();
+    if let Some(job) = settings.gitlab_sync {
+        let (store, scheduler, shutdown) = (store.clone(), scheduler.clone(), shutdown.clone());
+        jobs.push(
+            run_scheduled("gitlab sync", job.schedule, shutdown.clone(), move || {
+                let (store, settings) = (store.clone(), job.settings.clone());
+                let (scheduler, shutdown) = (scheduler.clone(), shutdown.clone());
+                async move {
+                    let result =
+                        gitlab::sync(store.clone(), config, settings, &[], scheduler, shutdown)
+                            .await;
+                    rollups::refresh_after_run(&store, config, "gitlab", result).await
+                }
+            })
+            .boxed_local(),
+        );
+    }
+    if let Some(job) = settings.copilot_sync {
+        let (store, scheduler, shutdown) = (store.clone(), scheduler.clone(), shutdown.clone());
+        jobs.push(
+            run_scheduled("copilot sync", job.schedule, shutdown.clone(), move || {
+                let (store, settings) = (store.clone(), job.settings.clone());
+                let (scheduler, shutdown) = (scheduler.clone(), shutdown.clone());
+                async move {
+                    let result =
+                        copilot::sync(store.clone(), config, settings, &[], scheduler, shutdown)
+                            .await;
+                    rollups::refresh_after_run(&store, config, "copilot", result).await
+                }
+            })
+            .boxed_local(),
+        );
+    }
+    if let Some(job) = settings.ai_backfill {
+        let (store, scheduler, shutdown) = (store.clone(), scheduler.clone(), shutdown.clone());
+        let limit = settings.ai_backfill_limit;
+        jobs.push(
+            run_scheduled("ai backfill", job.schedule, shutdown.clone(), move || {
+                let (gitlab, ai) = job.settings.clone();
+                let (store, scheduler, shutdown) =
+                    (store.clone(), scheduler.clone(), shutdown.clone());
+                async move {
+                    let result =
+                        ai::backfill(store.clone(), gitlab, ai, None, limit, scheduler, shutdown)
+                            .await;
+                    rollups::refresh_after_run(&store, config, "ai", result).await
+                }
+            })
+            .boxed_local(),
+        );
+    }
+
+    if let Some(webhook) = settings.webhook {
+        let (store, scheduler, shutdown) = (store.clone(), scheduler.clone(), shutdown.clone());
+        jobs.push(
+            run_periodically(
+                "webhook refresh",
+                webhook.refresh_interval,
+                shutdown.clone(),
+                move || {
+                    let (store, scheduler, shutdown) =
+                        (store.clone(), scheduler.clone(), shutdown.clone());
+                    let settings = webhook.gitlab.clone();
+                    async move {
+                        gitlab::refresh_webhook_queue(store, config, &settings, scheduler, shutdown)
+                            .await
+                    }
+                },
+            )
+            .boxed_local(),
+        );
+    }
+
+    info!(jobs = jobs.len(), "Daemon started");
+    join_all(jobs).await;
+
+    server_shutdown.cancel();
+    if let Some(server) = server {
+        match server.await {
+            Ok(Err(e)) => error!(error = %e, "HTTP server failed"),
+            Err(e) => error!(error = %e, "HTTP server task failed to join"),
+            Ok(Ok(())) => {}
+        }
+    }
+    info!("Daemon stopped");
+
+    Ok(())
+}
+
+/// Collectors recorded in the run history by the scheduled jobs
+fn scheduled_collectors(settings: &DaemonSettings) -> Vec<String> {
+    [
+        (settings.gitlab_sync.is_some(), "gitlab"),
+        (settings.copilot_sync.is_some(), "copilot"),
+        (settings.ai_backfill.is_some(), "ai"),
+    ]
+    .into_iter()
+    .filter(|(scheduled, _)| *scheduled)
+    .map(|(_, collector)| collector.to_string())
+    .collect()
+}
+
+async fn run_scheduled<F, Fut>(
+    name: &str,
+    schedule: CronSchedule,
+    shutdown: CancellationToken,
+    mut job: F,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), CliError>>,
+{
+    loop {
+        let Some(next) = schedule.next_after(OffsetDateTime::now_utc()) else {
+            warn!(
+                job = name,
+                schedule = schedule.expression(),
+                "Schedule never fires again"
+            );
+            return;
+        };
+        info!(job = name, next = %next, "Next scheduled run");
+
+        let wait = (next - OffsetDateTime::now_utc()).max(time::Duration::ZERO);
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(wait.unsigned_abs()) => {}
+        }
+
+        let job_span = info_span!("job", job = name);
+        job_span.in_scope(|| info!("Starting scheduled job"));
+        log_job_result(&job_span, job().instrument(job_span.clone()).await);
+        if shutdown.is_cancelled() {
+            return;
+        }
+    }
+}
+
+/// Run `job` every `interval` until shutdown; unlike scheduled jobs, passes are only logged when they fail
+async fn run_periodically<F, Fut>(
+    name: &str,
+    interval: std::time::Duration,
+    shutdown: CancellationToken,
+    mut job: F,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), CliError>>,
+{
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(interval) => {}
+        }
+
+        let job_span = info_span!("job", job = name);
+        log_job_result(&job_span, job().instrument(job_span.clone()).await);
+        if shutdown.is_cancelled() {
+            return;
+        }
+    }
+}
+
+fn log_job_result(job_span: &Span, result: Result<(), CliError>) {
+    match result {
+        Ok(()) => {}
+        Err(e) if e.is::<Interrupted>() => {
+            job_span.in_scope(|| info!("Scheduled job interrupted by shutdown"))
+        }
+        Err(e) => job_span.in_scope(|| error!(error = %e, "Scheduled job failed")),
+    }
+}
@@ -0,0 +1,55 @@
+use clap::Subcommand;
+use std::str::FromStr;
+
+use crate::cli::CliError;
+use crate::component::dead_letter::{DeadLetterHandler, DeadLetterStatus};
+use crate::store::Store;
+
+#[derive(Subcommand, Debug)]
+pub enum DeadLetterCommand {
+    /// List dead letters, optionally only pending or parked ones
+    List {
+        #[arg(value_parser = DeadLetterStatus::from_str)]
+        status: Option<DeadLetterStatus>,
+    },
+    /// Requeue one merge request, or every parked dead letter
+    Requeue { mr_id: Option<String> },
+}
+
+pub async fn run(store: Store, command: DeadLetterCommand) -> Result<(), CliError> {
+    // Attempts are only counted by the sync, so the limit does not matter here
+    let dead_letter_handler = DeadLetterHandler {
+        store,
+        max_attempts: 0,
+    };
+
+    match command {
+        DeadLetterCommand::List { status } => {
+            let dead_letters = dead_letter_handler.list(status).await?;
+
+            for dead_letter in &dead_letters {
+                println!(
+                    "{}\tgroup={}\tstage={}\tstatus={}\tattempts={}\tfirst_seen_at={}\tlast_seen_at={}\terror={}",
+                    dead_letter.mr_id,
+                    dead_letter.group_full_path,
+                    dead_letter.stage.as_str(),
+                    dead_letter.status.as_str(),
+                    dead_letter.attempts,
+                    dead_letter.first_seen_at,
+                    dead_letter.last_seen_at,
+                    dead_letter.error_message
+                );
+            }
+            println!("{} dead letters", dead_letters.len());
+        }
+        DeadLetterCommand::Requeue { mr_id } => {
+            let requeued = dead_letter_handler.requeue(mr_id.as_deref()).await?;
+            println!(
+                "Requeued {} dead letters; they will be retried on the next collector run.",
+                requeued
+            );
+        }
+    }
+
+    Ok(())
+}
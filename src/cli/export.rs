@@ -0,0 +1,106 @@
+use clap::Args;
+use std::path::PathBuf;
+use std::str::FromStr;
+use time::Date;
+
+use crate::cli::CliError;
+use crate::component::export::{Dataset, ExportFilter, ExportFormat, ExportHandler, Pseudonymizer};
+use crate::config::Config;
+use crate::dates::parse_day;
+use crate::store::Store;
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Dataset to export, repeatable: a table name, copilot for the Copilot daily metrics with
+    /// their breakdowns, or all
+    #[arg(long = "dataset", default_value = "all")]
+    pub datasets: Vec<String>,
+    /// csv, jsonl or parquet
+    #[arg(long, default_value = "csv", value_parser = ExportFormat::from_str)]
+    pub format: ExportFormat,
+    /// Directory the files are written to, one `<dataset>.<format>` file per dataset
+    #[arg(long)]
+    pub output_dir: PathBuf,
+    /// First merge day, Copilot report day or flow metric window end (YYYY-MM-DD) to export
+    #[arg(long, value_parser = parse_day)]
+    pub since: Option<Date>,
+    /// Day (YYYY-MM-DD) the export stops before
+    #[arg(long, value_parser = parse_day)]
+    pub until: Option<Date>,
+    /// Only export merge requests and projects of this GitLab group, repeatable
+    #[arg(long = "group")]
+    pub groups: Vec<String>,
+    /// Only export Copilot metrics of this org, repeatable
+    #[arg(long = "org")]
+    pub orgs: Vec<String>,
+    /// Replace usernames by pseudonyms keyed by export.pseudonymization_key and leave user ids empty
+    #[arg(long)]
+    pub pseudonymize: bool,
+}
+
+impl ExportArgs {
+    /// Selected datasets in export order, each once
+    pub fn datasets(&self) -> Result<Vec<Dataset>, String> {
+        let mut selected = Vec::new();
+        for name in &self.datasets {
+            selected.extend(Dataset::select(name)?);
+        }
+        Ok(Dataset::ALL
+            .into_iter()
+            .filter(|dataset| selected.contains(dataset))
+            .collect())
+    }
+}
+
+/// Resolve what the export needs from the configuration before connecting
+pub fn prepare(config: &Config, args: &ExportArgs) -> Result<Option<Pseudonymizer>, CliError> {
+    args.datasets()?;
+    if let (Some(since), Some(until)) = (args.since, args.until) {
+        if since >= until {
+            return Err(format!("--since {} must be before --until {}", since, until).into());
+        }
+    }
+    if !args.pseudonymize {
+        return Ok(None);
+    }
+
+    Ok(Some(Pseudonymizer::new(
+        &config.export_pseudonymization_key()?,
+    )))
+}
+
+pub async fn run(
+    store: Store,
+    args: ExportArgs,
+    pseudonymizer: Option<Pseudonymizer>,
+) -> Result<(), CliError> {
+    std::fs::create_dir_all(&args.output_dir)
+        .map_err(|e| format!("Failed to create {}: {}", args.output_dir.display(), e))?;
+    let handler = ExportHandler { store };
+    let filter = ExportFilter {
+        since: args.since,
+        until: args.until,
+        groups: args.groups.clone(),
+        orgs: args.orgs.clone(),
+    };
+
+    for dataset in args.datasets()? {
+        let summary = handler
+            .export(
+                dataset,
+                args.format,
+                &filter,
+                pseudonymizer.as_ref(),
+                &args.output_dir,
+            )
+            .await?;
+        println!(
+            "Exported {} rows of {} to {}",
+            summary.rows,
+            dataset.name(),
+            summary.path.display()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use clap::Subcommand;
+use time::OffsetDateTime;
+
+use crate::cli::CliError;
+use crate::component::flow_metrics::{FlowMetricsHandler, DIMENSIONS};
+use crate::config::Config;
+use crate::store::Store;
+
+#[derive(Subcommand, Debug)]
+pub enum FlowMetricsCommand {
+    /// Compute the flow metrics of the windows ending today (UTC) and store them in `flow_metrics`
+    Compute {
+        /// Comma separated window lengths in days [default: reporting.flow_metric_windows_days]
+        #[arg(long, value_delimiter = ',')]
+        windows: Option<Vec<i32>>,
+    },
+    /// Show the most recently computed flow metrics of a window
+    Show {
+        #[arg(long, default_value_t = 30)]
+        window_days: i32,
+        /// Only one dimension: all, project, group, author or label
+        #[arg(long, value_parser = parse_dimension)]
+        dimension: Option<String>,
+    },
+}
+
+fn parse_dimension(value: &str) -> Result<String, String> {
+    if DIMENSIONS.contains(&value) {
+        Ok(value.to_string())
+    } else {
+        Err(format!("expected one of {}", DIMENSIONS.join(", ")))
+    }
+}
+
+pub async fn run(
+    store: Store,
+    config: &Config,
+    command: FlowMetricsCommand,
+) -> Result<(), CliError> {
+    let handler = FlowMetricsHandler { store };
+
+    match command {
+        FlowMetricsCommand::Compute { windows } => {
+            let windows =
+                windows.unwrap_or_else(|| config.reporting.flow_metric_windows_days.clone());
+            let window_end = OffsetDateTime::now_utc().date();
+            for window_days in windows {
+                let rows = handler.compute(window_days, window_end).await?;
+                println!(
+                    "Computed {} flow metrics over the {} days before {}",
+                    rows, window_days, window_end
+                );
+            }
+        }
+        FlowMetricsCommand::Show {
+            window_days,
+            dimension,
+        } => {
+            let metrics = handler
+                .fetch_latest(window_days, dimension.as_deref())
+                .await?;
+            let format = |value: Option<f64>| {
+                value.map_or_else(|| "-".to_string(), |value| format!("{:.1}", value))
+            };
+
+            for metric in &metrics {
+                println!(
+                    "{}={}\t{}\tn={}\tmean={}\tp50={}\tp75={}\tp90={}\tp95={}",
+                    metric.dimension,
+                    metric.dimension_value,
+                    metric.metric,
+                    metric.sample_count,
+                    format(metric.mean),
+                    format(metric.p50),
+                    format(metric.p75),
+                    format(metric.p90),
+                    format(metric.p95)
+                );
+            }
+            match metrics.first() {
+                Some(metric) => println!(
+                    "{} flow metrics of {} to {}",
+                    metrics.len(),
+                    metric.window_start,
+                    metric.window_end
+                ),
+                None => println!("No flow metrics computed for a {} day window", window_days),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,811 @@
+use std::collections::BTreeMap;
+
+use clap::{ArgAction, Args};
+use futures::stream::{self, StreamExt};
+use time::format_description::well_known::Rfc3339;
+use time::{Date, Duration, OffsetDateTime};
+use tokio_util::sync::CancellationToken;
+use tracing::field::{display, Empty};
+use tracing::{info, info_span, instrument, warn, Instrument, Span};
+use uuid::Uuid;
+
+use crate::cli::{
+    completed_run_status, finish_run, run_lock_holder_name, AiArgs, CliError, GitlabArgs,
+    ImportProgressArgs, Interrupted, RunLockArgs,
+};
+use crate::client::gitlab_graphql_client::GitlabGraphQLClient;
+use crate::client::gitlab_rest_client::GitlabRestClient;
+use crate::component::collector_runs;
+use crate::component::import_progress::{ImportProgressHandler, ImportProgressPolicy};
+use crate::component::merge_request::{self, MergeRequestHandler};
+use crate::component::project::{self, ProjectHandler};
+use crate::component::run_history::{RunGroupStats, RunHistoryHandler};
+use crate::component::run_lock::{LockedTask, RunLockHandler};
+use crate::component::webhook::{RefreshRequest, WebhookQueueHandler};
+use crate::config::{split_list, Config, GitlabSyncSettings, GroupConfig};
+use crate::context::GitlabContext;
+use crate::dates::parse_day;
+use crate::metrics;
+use crate::scheduler::WorkScheduler;
+use crate::store::Store;
+
+/// Flags override the config file and the environment
+#[derive(Args, Debug)]
+pub struct GitlabSyncArgs {
+    #[command(flatten)]
+    pub gitlab: GitlabArgs,
+    #[command(flatten)]
+    pub ai: AiArgs,
+    /// Comma-separated full paths of the groups to import, replacing the configured groups
+    #[arg(long)]
+    pub groups: Option<String>,
+    /// Only sync this configured group (repeatable). The watermark is not advanced by a partial sync
+    #[arg(long = "group")]
+    pub only_groups: Vec<String>,
+    #[arg(long, action = ArgAction::Set)]
+    pub upsert_merge_requests: Option<bool>,
+    /// RFC 3339 timestamp to start from when no successful run has been recorded yet
+    #[arg(long)]
+    pub initial_ingestion_date: Option<String>,
+    #[arg(long)]
+    pub dead_letter_max_attempts: Option<i32>,
+    /// Refresh labels and approvals of stored merge requests, including those merged before the watermark
+    #[arg(long, action = ArgAction::Set)]
+    pub reconcile: Option<bool>,
+    /// Hours before the watermark at which a reconciling sync starts
+    #[arg(long)]
+    pub reconcile_overlap_hours: Option<i64>,
+    /// Fetch when approved merge requests were first approved, one request each
+    #[arg(long, action = ArgAction::Set)]
+    pub fetch_approval_times: Option<bool>,
+    /// Number of groups imported at the same time
+    #[arg(long)]
+    pub max_concurrent_groups: Option<usize>,
+    #[command(flatten)]
+    pub import_progress: ImportProgressArgs,
+    #[command(flatten)]
+    pub run_lock: RunLockArgs,
+}
+
+impl GitlabSyncArgs {
+    pub fn apply(&self, config: &mut Config) {
+        self.gitlab.apply(config);
+        self.ai.apply(config);
+        self.import_progress.apply(config);
+        self.run_lock.apply(config);
+
+        let gitlab = &mut config.gitlab;
+        if let Some(groups) = &self.groups {
+            gitlab.set_group_paths(&split_list(groups));
+        }
+        if let Some(upsert_merge_requests) = self.upsert_merge_requests {
+            gitlab.upsert_merge_requests = upsert_merge_requests;
+        }
+        if let Some(initial_ingestion_date) = &self.initial_ingestion_date {
+            gitlab.initial_ingestion_date = Some(initial_ingestion_date.clone());
+        }
+        if let Some(dead_letter_max_attempts) = self.dead_letter_max_attempts {
+            gitlab.dead_letter_max_attempts = dead_letter_max_attempts;
+        }
+        if let Some(reconcile) = self.reconcile {
+            gitlab.reconcile = reconcile;
+        }
+        if let Some(reconcile_overlap_hours) = self.reconcile_overlap_hours {
+            gitlab.reconcile_overlap_hours = reconcile_overlap_hours;
+        }
+        if let Some(fetch_approval_times) = self.fetch_approval_times {
+            gitlab.fetch_approval_times = fetch_approval_times;
+        }
+        if let Some(max_concurrent_groups) = self.max_concurrent_groups {
+            config.concurrency.max_concurrent_groups = max_concurrent_groups;
+        }
+    }
+}
+
+/// Sync the selected groups, or every configured group when none are selected
+#[instrument(name = "run", skip_all, fields(collector = "gitlab", run_id = Empty))]
+pub async fn sync(
+    store: Store,
+    config: &Config,
+    settings: GitlabSyncSettings,
+    only_groups: &[String],
+    scheduler: WorkScheduler,
+    shutdown: CancellationToken,
+) -> Result<(), CliError> {
+    let start_time = OffsetDateTime::now_utc();
+
+    let groups = select_groups(&settings.groups, only_groups)?;
+    let partial_sync = !only_groups.is_empty();
+    let import_progress_policy = config.import_progress.policy();
+
+    // Every run is recorded, successful or not, so failed runs can be debugged afterwards
+    let run_history_handler = RunHistoryHandler {
+        store: store.clone(),
+    };
+    let run = run_history_handler.start_run("gitlab").await?;
+    Span::current().record("run_id", display(run.id));
+    let mut run_errors: Vec<String> = Vec::new();
+
+    // Retention cleanup of finished imports
+    let import_progress_handler = ImportProgressHandler {
+        store: store.clone(),
+        run_id: Some(run.id),
+        policy: import_progress_policy.clone(),
+    };
+    match import_progress_handler
+        .cleanup_old_imports(import_progress_policy.retention_days)
+        .await
+    {
+        Ok(deleted) => info!(
+            deleted,
+            retention_days = import_progress_policy.retention_days,
+            "Deleted old import progress records"
+        ),
+        Err(e) => warn!(error = %e, "Failed to clean up old import progress records"),
+    }
+
+    let collector_runs_handler = collector_runs::CollectorRunsHandler {
+        store: store.clone(),
+    };
+    let run_lock_handler = RunLockHandler {
+        store: store.clone(),
+        holder_name: run_lock_holder_name(),
+        lease_seconds: config.run_lock.lease_seconds,
+    };
+    let last_successful_collector_run = collector_runs_handler
+        .fetch_last_successfull_collector_run()
+        .await?;
+    let updated_after = match &last_successful_collector_run {
+        Some(run) => run.watermark().format(&Rfc3339)?,
+        None => match &settings.initial_ingestion_date {
+            Some(initial_ingestion_date) => initial_ingestion_date.clone(),
+            None => OffsetDateTime::now_utc().format(&Rfc3339)?,
+        },
+    };
+
+    info!(
+        last_successful_run = ?last_successful_collector_run,
+        updated_after = %updated_after,
+        "Fetching data updated after the last successful run"
+    );
+
+    let context = build_context(
+        store.clone(),
+        &settings,
+        Some(run.id),
+        import_progress_policy,
+        config.concurrency.max_concurrent_merge_requests,
+        scheduler,
+        shutdown.clone(),
+    )?;
+
+    let group_results: Vec<_> = stream::iter(groups)
+        .map(|group| {
+            // Per-group settings override the defaults of the [gitlab] and [ai] sections
+            let context = GitlabContext {
+                upsert_merge_requests: settings.upsert_merge_requests_for(&group),
+                ai_summaries_enabled: settings.ai_summaries_for(&group),
+                ..context.clone()
+            };
+            let run_lock_handler = &run_lock_handler;
+            let run_history_handler = &run_history_handler;
+            let updated_after = &updated_after;
+            let group_span = info_span!("group", group = %group.full_path);
+            async move {
+                info!("Processing group");
+
+                // Only one collector instance may import a given group at a time
+                let lock_key = format!("gitlab:{}", group.full_path);
+                let group_import = import_group(
+                    context,
+                    run_history_handler,
+                    run.id,
+                    &group.full_path,
+                    updated_after,
+                );
+                let result = run_lock_handler
+                    .run_exclusive(&lock_key, group_import)
+                    .await;
+                (group.full_path, result)
+            }
+            .instrument(group_span)
+        })
+        .buffer_unordered(config.concurrency.max_concurrent_groups.max(1))
+        .collect()
+        .await;
+
+    let mut all_imports_successful = true;
+    for (group_full_path, result) in group_results {
+        match result {
+            Ok(Some(true)) => metrics::record_success("gitlab", &group_full_path),
+            Ok(Some(false)) => {
+                run_errors.push(group_failure(&group_full_path, &shutdown));
+                all_imports_successful = false;
+            }
+            Ok(None) => {
+                // Another instance owns this group; it records its own collector run
+                run_errors.push(format!(
+                    "group {} skipped: run lock held by another instance",
+                    group_full_path
+                ));
+                all_imports_successful = false;
+            }
+            Err(e) => {
+                warn!(group = %group_full_path, error = %e, "Run lock failed");
+                run_errors.push(format!("group {} run lock failed: {}", group_full_path, e));
+                all_imports_successful = false;
+            }
+        }
+    }
+
+    let end_time = OffsetDateTime::now_utc();
+
+    // Only record a successful run if ALL configured groups completed successfully;
+    // the watermark is shared by all groups, so a partial sync must not advance it
+    if all_imports_successful && !partial_sync {
+        collector_runs_handler
+            .persist_successful_run(&collector_runs::CollectorRun {
+                last_successful_run_started_at: start_time,
+                last_successful_run_completed_at: end_time,
+            })
+            .await?;
+        info!("All imports completed successfully. Collector run recorded.");
+    } else if all_imports_successful {
+        info!(
+            "Selected groups imported successfully. Collector run NOT recorded for a partial sync."
+        );
+    } else {
+        warn!("Some imports failed or were interrupted. Collector run NOT recorded - will resume from previous checkpoint on next run.");
+    }
+
+    let run_status = completed_run_status(all_imports_successful, &shutdown);
+    let run_error_summary = (!all_imports_successful).then(|| run_errors.join("; "));
+    run_history_handler
+        .complete_run(run.id, run_status, run_error_summary.as_deref())
+        .await?;
+
+    let elapsed = end_time - start_time;
+    info!(elapsed = %elapsed, status = run_status.as_str(), "GitLab sync finished");
+
+    finish_run(run_status)
+}
+
+/// Flags override the config file and the environment
+#[derive(Args, Debug)]
+pub struct GitlabBackfillArgs {
+    #[command(flatten)]
+    pub gitlab: GitlabArgs,
+    #[command(flatten)]
+    pub ai: AiArgs,
+    /// First merge day (YYYY-MM-DD) to import
+    #[arg(long, value_parser = parse_day)]
+    pub since: Date,
+    /// Merge day (YYYY-MM-DD) the backfill stops before [default: today]
+    #[arg(long, value_parser = parse_day)]
+    pub until: Option<Date>,
+    /// Only backfill this configured group (repeatable)
+    #[arg(long = "group")]
+    pub only_groups: Vec<String>,
+    #[arg(long, action = ArgAction::Set)]
+    pub upsert_merge_requests: Option<bool>,
+    /// Number of groups backfilled at the same time
+    #[arg(long)]
+    pub max_concurrent_groups: Option<usize>,
+    #[command(flatten)]
+    pub import_progress: ImportProgressArgs,
+    #[command(flatten)]
+    pub run_lock: RunLockArgs,
+}
+
+impl GitlabBackfillArgs {
+    pub fn apply(&self, config: &mut Config) {
+        self.gitlab.apply(config);
+        self.ai.apply(config);
+        self.import_progress.apply(config);
+        self.run_lock.apply(config);
+
+        if let Some(upsert_merge_requests) = self.upsert_merge_requests {
+            config.gitlab.upsert_merge_requests = upsert_merge_requests;
+        }
+        if let Some(max_concurrent_groups) = self.max_concurrent_groups {
+            config.concurrency.max_concurrent_groups = max_concurrent_groups;
+        }
+    }
+}
+
+/// Import the merge requests merged in `[since, until)` for the selected groups.
+///
+/// The range is imported in monthly windows that are tracked in `import_progress`, so running
+/// the same backfill again resumes where it stopped. The sync watermark is left untouched.
+#[instrument(name = "run", skip_all, fields(collector = "gitlab_backfill", run_id = Empty))]
+pub async fn backfill(
+    store: Store,
+    config: &Config,
+    settings: GitlabSyncSettings,
+    args: GitlabBackfillArgs,
+    scheduler: WorkScheduler,
+    shutdown: CancellationToken,
+) -> Result<(), CliError> {
+    let start_time = OffsetDateTime::now_utc();
+
+    let since = args.since;
+    let until = args
+        .until
+        .unwrap_or_else(|| OffsetDateTime::now_utc().date());
+    if since >= until {
+        return Err(format!("--since {} must be before --until {}", since, until).into());
+    }
+    let groups = select_groups(&settings.groups, &args.only_groups)?;
+
+    let run_history_handler = RunHistoryHandler {
+        store: store.clone(),
+    };
+    let run = run_history_handler.start_run("gitlab_backfill").await?;
+    Span::current().record("run_id", display(run.id));
+    let run_lock_handler = RunLockHandler {
+        store: store.clone(),
+        holder_name: run_lock_holder_name(),
+        lease_seconds: config.run_lock.lease_seconds,
+    };
+    let context = build_context(
+        store.clone(),
+        &settings,
+        Some(run.id),
+        config.import_progress.policy(),
+        config.concurrency.max_concurrent_merge_requests,
+        scheduler,
+        shutdown.clone(),
+    )?;
+
+    info!(
+        since = %since,
+        until = %until,
+        groups = groups.len(),
+        "Backfilling merge requests"
+    );
+
+    let group_results: Vec<_> = stream::iter(groups)
+        .map(|group| {
+            let context = GitlabContext {
+                upsert_merge_requests: settings.upsert_merge_requests_for(&group),
+                ai_summaries_enabled: settings.ai_summaries_for(&group),
+                ..context.clone()
+            };
+            let run_lock_handler = &run_lock_handler;
+            let run_history_handler = &run_history_handler;
+            let group_span = info_span!("group", group = %group.full_path);
+            async move {
+                // Backfills take their own lock, so the incremental sync of the group keeps running
+                let lock_key = format!("gitlab-backfill:{}", group.full_path);
+                let group_backfill = backfill_group(
+                    context,
+                    run_history_handler,
+                    run.id,
+                    &group.full_path,
+                    since,
+                    until,
+                );
+                let result = run_lock_handler
+                    .run_exclusive(&lock_key, group_backfill)
+                    .await;
+                (group.full_path, result)
+            }
+            .instrument(group_span)
+        })
+        .buffer_unordered(config.concurrency.max_concurrent_groups.max(1))
+        .collect()
+        .await;
+
+    let mut run_errors: Vec<String> = Vec::new();
+    for (group_full_path, result) in group_results {
+        match result {
+            Ok(Some(true)) => {}
+            Ok(Some(false)) => run_errors.push(group_failure(&group_full_path, &shutdown)),
+            Ok(None) => run_errors.push(format!(
+                "group {} skipped: backfill lock held by another instance",
+                group_full_path
+            )),
+            Err(e) => {
+                warn!(group = %group_full_path, error = %e, "Run lock failed");
+                run_errors.push(format!("group {} run lock failed: {}", group_full_path, e));
+            }
+        }
+    }
+
+    let run_status = completed_run_status(run_errors.is_empty(), &shutdown);
+    if run_errors.is_empty() {
+        info!("Backfill completed successfully.");
+        run_history_handler
+            .complete_run(run.id, run_status, None)
+            .await?;
+    } else {
+        warn!("Some backfills failed or were interrupted. Run the same backfill again to resume them.");
+        run_history_handler
+            .complete_run(run.id, run_status, Some(&run_errors.join("; ")))
+            .await?;
+    }
+    let elapsed = OffsetDateTime::now_utc() - start_time;
+    info!(elapsed = %elapsed, status = run_status.as_str(), "GitLab backfill finished");
+
+    finish_run(run_status)
+}
+
+/// Backfill one group, returning whether every window was imported
+async fn backfill_group(
+    context: GitlabContext,
+    run_history_handler: &RunHistoryHandler,
+    run_id: Uuid,
+    group_full_path: &str,
+    since: Date,
+    until: Date,
+) -> bool {
+    let merge_request_handler = MergeRequestHandler { context };
+    let mut stats = RunGroupStats::default();
+    let result = merge_request_handler
+        .backfill_merge_requests(group_full_path, since, until, &mut stats)
+        .await;
+
+    if let Err(e) = run_history_handler
+        .persist_group_stats(
+            run_id,
+            group_full_path,
+            merge_request::BACKFILL_IMPORT_TYPE,
+            &stats,
+        )
+        .await
+    {
+        warn!(
+            group = group_full_path,
+            error = %e,
+            "Failed to persist backfill run statistics"
+        );
+    }
+
+    match result {
+        Ok(summary) => {
+            info!(
+                group = group_full_path,
+                windows = summary.windows_total,
+                skipped = summary.windows_skipped,
+                imported = summary.windows_imported,
+                merge_requests_persisted = stats.items_persisted,
+                "Backfill finished"
+            );
+            true
+        }
+        Err(e) => {
+            warn!(
+                group = group_full_path,
+                error = %e,
+                "Backfill failed. Progress has been saved and will resume on the next backfill."
+            );
+            false
+        }
+    }
+}
+
+fn build_context(
+    store: Store,
+    settings: &GitlabSyncSettings,
+    run_id: Option<Uuid>,
+    import_progress_policy: ImportProgressPolicy,
+    max_concurrent_merge_requests: usize,
+    scheduler: WorkScheduler,
+    shutdown: CancellationToken,
+) -> Result<GitlabContext, CliError> {
+    // AI settings are only resolved when at least one group generates summaries
+    let ai = settings.ai.clone().unwrap_or_default();
+
+    Ok(GitlabContext {
+        store,
+        gitlab_graphql_client: GitlabGraphQLClient::new(
+            &settings.api.api_token,
+            settings.api.graphql_endpoint.clone(),
+        )?,
+        gitlab_rest_client: GitlabRestClient::new(
+            &settings.api.api_token,
+            settings.api.rest_endpoint.clone(),
+        )?,
+        ai_base_url: ai.base_url,
+        ai_model: ai.model,
+        ai_api_key: ai.api_key,
+        ai_max_context_chars: ai.max_context_chars,
+        ai_summaries_enabled: settings.ai_enabled,
+        upsert_merge_requests: settings.upsert_merge_requests,
+        fetch_approval_times: settings.fetch_approval_times,
+        reconcile_policy: settings.reconcile_policy.clone(),
+        dead_letter_max_attempts: settings.dead_letter_max_attempts,
+        run_id,
+        import_progress_policy,
+        max_concurrent_merge_requests,
+        scheduler,
+        shutdown,
+    })
+}
+
+/// Queued webhook refreshes handled by one pass
+const WEBHOOK_REFRESH_BATCH: i64 = 100;
+/// A project-wide refresh re-imports the merge requests updated this long before its first event
+const PROJECT_REFRESH_LOOKBACK: Duration = Duration::hours(1);
+
+/// Re-import the merge requests and projects queued by GitLab webhooks.
+///
+/// Requests of the same project are refreshed with one query. A failed refresh stays queued for
+/// the next pass until it is parked; the scheduled sync still picks up whatever was missed.
+pub async fn refresh_webhook_queue(
+    store: Store,
+    config: &Config,
+    settings: &GitlabSyncSettings,
+    scheduler: WorkScheduler,
+    shutdown: CancellationToken,
+) -> Result<(), CliError> {
+    let queue = WebhookQueueHandler {
+        store: store.clone(),
+    };
+    let requests = queue.fetch_pending(WEBHOOK_REFRESH_BATCH).await?;
+    if requests.is_empty() {
+        return Ok(());
+    }
+
+    // Takes the group lease of the sync, so a refresh never overlaps a sync or another daemon's refresh
+    let run_lock_handler = RunLockHandler {
+        store: store.clone(),
+        holder_name: run_lock_holder_name(),
+        lease_seconds: config.run_lock.lease_seconds,
+    };
+    let context = build_context(
+        store,
+        settings,
+        None,
+        config.import_progress.policy(),
+        config.concurrency.max_concurrent_merge_requests,
+        scheduler,
+        shutdown,
+    )?;
+
+    let mut projects: BTreeMap<(String, String), Vec<RefreshRequest>> = BTreeMap::new();
+    for request in requests {
+        projects
+            .entry((request.group_full_path.clone(), request.project_id.clone()))
+            .or_default()
+            .push(request);
+    }
+
+    for ((group_full_path, project_id), requests) in projects {
+        let Some(group) = settings
+            .groups
+            .iter()
+            .find(|group| group.full_path == group_full_path)
+        else {
+            // The group was removed from the config after the events arrived
+            for request in &requests {
+                queue.complete(request).await?;
+            }
+            continue;
+        };
+        let handler = MergeRequestHandler {
+            context: GitlabContext {
+                upsert_merge_requests: settings.upsert_merge_requests_for(group),
+                ai_summaries_enabled: settings.ai_summaries_for(group),
+                ..context.clone()
+            },
+        };
+
+        // A project-wide request covers the merge requests queued for the same project
+        let iids: Vec<String> = if requests.iter().any(|request| request.mr_iid.is_none()) {
+            Vec::new()
+        } else {
+            requests
+                .iter()
+                .filter_map(|request| request.mr_iid.clone())
+                .collect()
+        };
+        let first_received_at = requests
+            .iter()
+            .map(|request| request.received_at)
+            .min()
+            .unwrap_or_else(OffsetDateTime::now_utc);
+        // The path of the latest event, in case the project was renamed in the meantime
+        let project_path = requests
+            .iter()
+            .max_by_key(|request| request.received_at)
+            .map(|request| request.project_path.clone())
+            .unwrap_or_default();
+
+        let mut stats = RunGroupStats::default();
+        let project_span = info_span!(
+            "webhook_refresh",
+            group = %group_full_path,
+            project_id = %project_id
+        );
+        let refresh = handler
+            .refresh_project_merge_requests(
+                &group_full_path,
+                &project_path,
+                &iids,
+                first_received_at - PROJECT_REFRESH_LOOKBACK,
+                &mut stats,
+            )
+            .instrument(project_span.clone());
+        let result = match run_lock_handler
+            .run_exclusive(&format!("gitlab:{}", group_full_path), refresh)
+            .await
+        {
+            Ok(Some(result)) => result,
+            Ok(None) => {
+                // The group is being imported elsewhere; the requests stay queued for the next pass
+                continue;
+            }
+            Err(e) => {
+                project_span.in_scope(
+                    || warn!(error = %e, "Run lock failed, webhook refresh stays queued"),
+                );
+                continue;
+            }
+        };
+        match result {
+            Ok(()) => {
+                project_span.in_scope(|| {
+                    info!(
+                        requests = requests.len(),
+                        persisted = stats.items_persisted,
+                        reconciled = stats.items_reconciled,
+                        failed = stats.items_failed,
+                        "Refreshed merge requests reported by webhooks"
+                    )
+                });
+                for request in &requests {
+                    queue.complete(request).await?;
+                }
+            }
+            Err(merge_request::MergeRequestError::Interrupted) => return Err(Interrupted.into()),
+            Err(e) => {
+                project_span.in_scope(|| warn!(error = %e, "Webhook refresh failed"));
+                for request in &requests {
+                    queue
+                        .record_failure(request, &e.to_string(), settings.dead_letter_max_attempts)
+                        .await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Import the projects and merge requests of one group, returning whether both imports succeeded
+async fn import_group(
+    context: GitlabContext,
+    run_history_handler: &RunHistoryHandler,
+    run_id: Uuid,
+    group_full_path: &str,
+    updated_after: &str,
+) -> bool {
+    // projects - spawn and track separately; a lost run lock aborts both imports
+    let project_handler = ProjectHandler {
+        context: context.clone(),
+    };
+    let gfp1 = group_full_path.to_owned();
+    // Spawned tasks do not inherit the current span, so the group span is attached explicitly
+    let project_task = LockedTask::spawn(
+        async move {
+            info!(group = %gfp1, "Starting projects import");
+            let mut stats = RunGroupStats::default();
+            let success = match project_handler.import_projects(&gfp1, &mut stats).await {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!(
+                        group = %gfp1,
+                        error = %e,
+                        "Project import failed. Progress has been saved and will resume on next run."
+                    );
+                    false
+                }
+            };
+            (success, stats)
+        }
+        .in_current_span(),
+    );
+
+    // merge requests
+    let merge_request_handler = MergeRequestHandler { context };
+    let gfp2 = group_full_path.to_owned();
+    let ua1 = updated_after.to_owned();
+    let mr_task = LockedTask::spawn(
+        async move {
+            info!(
+                group = %gfp2,
+                updated_after = %ua1,
+                "Starting merge requests import"
+            );
+            let mut stats = RunGroupStats::default();
+            let success = match merge_request_handler
+                .import_merge_requests(&gfp2, &ua1, &mut stats)
+                .await
+            {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!(
+                        group = %gfp2,
+                        error = %e,
+                        "Merge request import failed. Progress has been saved and will resume on next run."
+                    );
+                    false
+                }
+            };
+            (success, stats)
+        }
+        .in_current_span(),
+    );
+
+    // Wait for both tasks
+    let (project_result, mr_result) = tokio::join!(project_task, mr_task);
+    let mut group_successful = true;
+
+    for (import_type, result) in [
+        (project::IMPORT_TYPE, project_result),
+        (merge_request::IMPORT_TYPE, mr_result),
+    ] {
+        match result {
+            Err(e) => {
+                warn!(
+                    import_type,
+                    group = group_full_path,
+                    error = ?e,
+                    "Import task failed"
+                );
+                group_successful = false;
+            }
+            Ok((success, stats)) => {
+                if !success {
+                    group_successful = false;
+                }
+                if let Err(e) = run_history_handler
+                    .persist_group_stats(run_id, group_full_path, import_type, &stats)
+                    .await
+                {
+                    warn!(
+                        import_type,
+                        group = group_full_path,
+                        error = %e,
+                        "Failed to persist run statistics"
+                    );
+                }
+            }
+        }
+    }
+
+    group_successful
+}
+
+/// Run error of a group whose import did not complete
+fn group_failure(group_full_path: &str, shutdown: &CancellationToken) -> String {
+    if shutdown.is_cancelled() {
+        format!("group {} interrupted", group_full_path)
+    } else {
+        format!("group {} failed", group_full_path)
+    }
+}
+
+/// Pick the explicitly selected groups, or every configured group when none were selected
+fn select_groups(
+    configured: &[GroupConfig],
+    selected: &[String],
+) -> Result<Vec<GroupConfig>, CliError> {
+    if selected.is_empty() {
+        return Ok(configured.to_vec());
+    }
+
+    selected
+        .iter()
+        .map(|full_path| {
+            configured
+                .iter()
+                .find(|group| group.full_path == full_path.trim())
+                .cloned()
+                .ok_or_else(|| {
+                    format!("Group {} is not configured in gitlab.groups", full_path).into()
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,542 @@
+use std::path::PathBuf;
+
+use clap::{ArgAction, Args, Parser, Subcommand};
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::component::run_history::RunStatus;
+use crate::config::{set_secret, Config, ConfigError, LogFormat};
+use crate::store::Store;
+use crate::{metrics, telemetry};
+
+pub mod ai;
+pub mod api;
+pub mod copilot;
+pub mod daemon;
+pub mod dead_letters;
+pub mod export;
+pub mod flow_metrics;
+pub mod gitlab;
+pub mod people;
+pub mod report;
+pub mod rollups;
+pub mod status;
+pub mod watermark;
+
+pub type CliError = Box<dyn std::error::Error>;
+
+/// Exit code of a command that stopped early on SIGTERM or Ctrl-C (`EX_TEMPFAIL`)
+pub const EXIT_INTERRUPTED: i32 = 75;
+
+/// Returned by a sync that stopped on shutdown before every import finished
+#[derive(Error, Debug)]
+#[error("Interrupted by shutdown; the next run resumes from the saved checkpoints")]
+pub struct Interrupted;
+
+/// Collects GitLab and GitHub Copilot engineering metrics into PostgreSQL.
+///
+/// Settings are read from the config file, then the environment, then command line flags;
+/// each layer overrides the previous one.
+#[derive(Parser, Debug)]
+#[command(name = "engineering-metrics-data-collector", version, about)]
+pub struct Cli {
+    /// TOML config file [default: collector.toml when it exists]
+    #[arg(long, env = "COLLECTOR_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// PostgreSQL connection URL
+    #[arg(long, global = true)]
+    pub database_url: Option<String>,
+
+    /// Schema holding the collector's tables, e.g. one per tenant
+    #[arg(long, global = true)]
+    pub database_schema: Option<String>,
+
+    /// Write Prometheus metrics to this file when a one-shot command finishes
+    #[arg(long, global = true)]
+    pub metrics_textfile: Option<PathBuf>,
+
+    /// Log line format: text or json
+    #[arg(long, global = true)]
+    pub log_format: Option<LogFormat>,
+
+    /// Log level filter in RUST_LOG syntax, e.g. info or debug
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Import projects and merge requests from GitLab
+    Gitlab {
+        #[command(subcommand)]
+        command: GitlabCommand,
+    },
+    /// Import GitHub Copilot usage metrics
+    Copilot {
+        #[command(subcommand)]
+        command: CopilotCommand,
+    },
+    /// Maintain the AI summaries of stored merge requests
+    Ai {
+        #[command(subcommand)]
+        command: AiCommand,
+    },
+    /// Validate the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Run the GitLab sync, Copilot sync and AI backfill on their cron schedules until SIGTERM
+    Daemon(daemon::DaemonArgs),
+    /// Apply pending database migrations
+    Migrate,
+    /// Show watermarks, recent runs, unfinished imports and dead letters
+    Status(status::StatusArgs),
+    /// Move a collector watermark back so the next sync imports again from that point
+    ResetWatermark {
+        #[command(subcommand)]
+        target: watermark::ResetWatermarkTarget,
+    },
+    /// List and requeue merge requests that failed to import
+    DeadLetters {
+        #[command(subcommand)]
+        command: dead_letters::DeadLetterCommand,
+    },
+    /// Refresh the materialized reporting rollups
+    RefreshRollups,
+    /// Compute and show cycle-time and throughput metrics of merged merge requests
+    FlowMetrics {
+        #[command(subcommand)]
+        command: flow_metrics::FlowMetricsCommand,
+    },
+    /// Map GitLab usernames and GitHub logins to people and teams
+    People {
+        #[command(subcommand)]
+        command: people::PeopleCommand,
+    },
+    /// Generate report datasets
+    Report {
+        #[command(subcommand)]
+        command: report::ReportCommand,
+    },
+    /// Export stored and computed data to CSV, JSON Lines or Parquet files
+    Export(export::ExportArgs),
+    /// Serve the read-only REST API until SIGTERM
+    Api(api::ApiArgs),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Report every problem in the layered configuration without connecting to anything
+    Check,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GitlabCommand {
+    /// Import projects and merged merge requests of the configured groups
+    Sync(gitlab::GitlabSyncArgs),
+    /// Import the merge requests merged in a time range, without touching the sync watermark
+    Backfill(gitlab::GitlabBackfillArgs),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CopilotCommand {
+    /// Import the daily Copilot user usage reports of the configured orgs
+    Sync(copilot::CopilotSyncArgs),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AiCommand {
+    /// Generate AI summaries for stored merge requests that have none
+    Backfill(ai::AiBackfillArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct GitlabArgs {
+    #[arg(long)]
+    pub gitlab_rest_endpoint: Option<String>,
+    #[arg(long)]
+    pub gitlab_graphql_endpoint: Option<String>,
+    #[arg(long)]
+    pub gitlab_api_token: Option<String>,
+}
+
+impl GitlabArgs {
+    pub fn apply(&self, config: &mut Config) {
+        let gitlab = &mut config.gitlab;
+        if let Some(rest_endpoint) = &self.gitlab_rest_endpoint {
+            gitlab.rest_endpoint = Some(rest_endpoint.clone());
+        }
+        if let Some(graphql_endpoint) = &self.gitlab_graphql_endpoint {
+            gitlab.graphql_endpoint = Some(graphql_endpoint.clone());
+        }
+        if let Some(api_token) = &self.gitlab_api_token {
+            set_secret(
+                &mut gitlab.api_token,
+                &mut gitlab.api_token_file,
+                api_token.clone(),
+            );
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct AiArgs {
+    #[arg(long)]
+    pub ai_base_url: Option<String>,
+    #[arg(long)]
+    pub ai_model: Option<String>,
+    #[arg(long)]
+    pub ai_api_key: Option<String>,
+    #[arg(long)]
+    pub ai_max_context_chars: Option<usize>,
+}
+
+impl AiArgs {
+    pub fn apply(&self, config: &mut Config) {
+        let ai = &mut config.ai;
+        if let Some(base_url) = &self.ai_base_url {
+            ai.base_url = Some(base_url.clone());
+        }
+        if let Some(model) = &self.ai_model {
+            ai.model = Some(model.clone());
+        }
+        if let Some(api_key) = &self.ai_api_key {
+            set_secret(&mut ai.api_key, &mut ai.api_key_file, api_key.clone());
+        }
+        if let Some(max_context_chars) = self.ai_max_context_chars {
+            ai.max_context_chars = max_context_chars;
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ImportProgressArgs {
+    #[arg(long)]
+    pub import_progress_retention_days: Option<i32>,
+    #[arg(long)]
+    pub import_stale_after_hours: Option<i64>,
+    #[arg(long, action = ArgAction::Set)]
+    pub restart_on_rejected_cursor: Option<bool>,
+}
+
+impl ImportProgressArgs {
+    pub fn apply(&self, config: &mut Config) {
+        let import_progress = &mut config.import_progress;
+        if let Some(retention_days) = self.import_progress_retention_days {
+            import_progress.retention_days = retention_days;
+        }
+        if let Some(stale_after_hours) = self.import_stale_after_hours {
+            import_progress.stale_after_hours = stale_after_hours;
+        }
+        if let Some(restart_on_rejected_cursor) = self.restart_on_rejected_cursor {
+            import_progress.restart_on_rejected_cursor = restart_on_rejected_cursor;
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RunLockArgs {
+    #[arg(long)]
+    pub run_lock_lease_seconds: Option<i64>,
+}
+
+impl RunLockArgs {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(lease_seconds) = self.run_lock_lease_seconds {
+            config.run_lock.lease_seconds = lease_seconds;
+        }
+    }
+}
+
+pub async fn run(cli: Cli) -> Result<(), CliError> {
+    let mut config = Config::load(cli.config.as_deref())?;
+    if let Some(database_url) = cli.database_url {
+        set_secret(
+            &mut config.database.url,
+            &mut config.database.url_file,
+            database_url,
+        );
+    }
+    if let Some(schema) = cli.database_schema {
+        config.database.schema = schema;
+    }
+    if let Some(textfile) = cli.metrics_textfile {
+        config.metrics.textfile = Some(textfile);
+    }
+    if let Some(format) = cli.log_format {
+        config.logging.format = format;
+    }
+    if let Some(level) = cli.log_level {
+        config.logging.level = level;
+    }
+    let telemetry = telemetry::init(&config.logging)?;
+
+    // The daemon serves its metrics instead of writing them once
+    let textfile = match cli.command {
+        Command::Daemon(_) => None,
+        _ => config.metrics.textfile.clone(),
+    };
+    let result = run_command(cli.command, config).await;
+    if let Some(textfile) = textfile {
+        if let Err(e) = metrics::write_textfile(&textfile) {
+            warn!(error = %e, "Failed to write metrics textfile");
+        }
+    }
+    telemetry.shutdown().await;
+
+    result
+}
+
+async fn run_command(command: Command, mut config: Config) -> Result<(), CliError> {
+    // Each command applies its own flags before the sections it needs are validated
+    match command {
+        Command::Config {
+            command: ConfigCommand::Check,
+        } => check_config(&config),
+        Command::Gitlab {
+            command: GitlabCommand::Sync(args),
+        } => {
+            args.apply(&mut config);
+            let settings = config.gitlab_sync_settings()?;
+            let store = connect(&config).await?;
+            let result = gitlab::sync(
+                store.clone(),
+                &config,
+                settings,
+                &args.only_groups,
+                config.concurrency.scheduler(),
+                shutdown_on_signal(),
+            )
+            .await;
+            rollups::refresh_after_run(&store, &config, "gitlab", result).await
+        }
+        Command::Gitlab {
+            command: GitlabCommand::Backfill(args),
+        } => {
+            args.apply(&mut config);
+            let settings = config.gitlab_sync_settings()?;
+            let store = connect(&config).await?;
+            let result = gitlab::backfill(
+                store.clone(),
+                &config,
+                settings,
+                args,
+                config.concurrency.scheduler(),
+                shutdown_on_signal(),
+            )
+            .await;
+            rollups::refresh_after_run(&store, &config, "gitlab", result).await
+        }
+        Command::Copilot {
+            command: CopilotCommand::Sync(args),
+        } => {
+            args.apply(&mut config);
+            let settings = config.copilot_settings()?;
+            let store = connect(&config).await?;
+            let result = copilot::sync(
+                store.clone(),
+                &config,
+                settings,
+                &args.only_orgs,
+                config.concurrency.scheduler(),
+                shutdown_on_signal(),
+            )
+            .await;
+            rollups::refresh_after_run(&store, &config, "copilot", result).await
+        }
+        Command::Ai {
+            command: AiCommand::Backfill(args),
+        } => {
+            args.apply(&mut config);
+            let (gitlab, ai) = config.ai_backfill_settings()?;
+            let store = connect(&config).await?;
+            let result = ai::backfill(
+                store.clone(),
+                gitlab,
+                ai,
+                args.group.as_deref(),
+                args.limit,
+                config.concurrency.scheduler(),
+                shutdown_on_signal(),
+            )
+            .await;
+            rollups::refresh_after_run(&store, &config, "ai", result).await
+        }
+        Command::Daemon(args) => {
+            args.apply(&mut config);
+            let settings = config.daemon_settings()?;
+            daemon::run(connect(&config).await?, &config, settings).await
+        }
+        Command::Migrate => {
+            connect(&config).await?;
+            info!("Database migrations are up to date");
+            Ok(())
+        }
+        Command::Status(args) => {
+            args.apply(&mut config);
+            status::status(connect_read_only(&config).await?, &config, args).await
+        }
+        Command::ResetWatermark { target } => {
+            watermark::reset(connect(&config).await?, target).await
+        }
+        Command::DeadLetters { command } => {
+            dead_letters::run(connect(&config).await?, command).await
+        }
+        Command::RefreshRollups => rollups::refresh(connect(&config).await?).await,
+        Command::FlowMetrics { command } => {
+            flow_metrics::run(connect(&config).await?, &config, command).await
+        }
+        Command::People { command } => people::run(connect(&config).await?, command).await,
+        Command::Report { command } => {
+            report::run(connect_read_only(&config).await?, command).await
+        }
+        Command::Export(args) => {
+            let pseudonymizer = export::prepare(&config, &args)?;
+            export::run(connect_read_only(&config).await?, args, pseudonymizer).await
+        }
+        Command::Api(args) => {
+            args.apply(&mut config);
+            let settings = config.api_settings()?;
+            api::run(connect_read_only(&config).await?, settings).await
+        }
+    }
+}
+
+/// Connect to the configured database and apply pending migrations
+async fn connect(config: &Config) -> Result<Store, CliError> {
+    let database = config.database_settings()?;
+    let store = Store::connect(&database.url, &database.options).await?;
+    store.migrate().await?;
+    Ok(store)
+}
+
+/// Connect to the configured database for a command that only reads, failing when migrations
+/// are pending instead of applying them
+async fn connect_read_only(config: &Config) -> Result<Store, CliError> {
+    let database = config.database_settings()?;
+    let store = Store::connect(&database.url, &database.options).await?;
+    store.check_migrated().await?;
+    Ok(store)
+}
+
+fn check_config(config: &Config) -> Result<(), CliError> {
+    let problems = config.check();
+    if !problems.is_empty() {
+        return Err(ConfigError::Invalid(problems).into());
+    }
+
+    println!("Configuration is valid.");
+    if !config.gitlab.groups.is_empty() {
+        println!("GitLab groups:");
+        for group in &config.gitlab.groups {
+            println!(
+                "  {}	upsert_merge_requests={}	ai_summaries={}",
+                group.full_path,
+                group
+                    .upsert_merge_requests
+                    .unwrap_or(config.gitlab.upsert_merge_requests),
+                group.ai_summaries.unwrap_or(config.ai.enabled)
+            );
+        }
+    }
+    if !config.copilot.orgs.is_empty() {
+        println!("Copilot orgs: {}", config.copilot.orgs.join(", "));
+    }
+    for (job, schedule) in [
+        ("gitlab sync", &config.daemon.gitlab_sync),
+        ("copilot sync", &config.daemon.copilot_sync),
+        ("ai backfill", &config.daemon.ai_backfill),
+    ] {
+        if let Some(schedule) = schedule {
+            println!("Daemon {}: {}", job, schedule);
+        }
+    }
+
+    Ok(())
+}
+
+/// Status of a finished run; imports that stopped on shutdown make it interrupted rather than failed
+pub fn completed_run_status(successful: bool, shutdown: &CancellationToken) -> RunStatus {
+    match (successful, shutdown.is_cancelled()) {
+        (true, _) => RunStatus::Succeeded,
+        (false, true) => RunStatus::Interrupted,
+        (false, false) => RunStatus::Failed,
+    }
+}
+
+/// Result of a command whose run finished with `status`; an interrupted run exits with `EXIT_INTERRUPTED`
+pub fn finish_run(status: RunStatus) -> Result<(), CliError> {
+    match status {
+        RunStatus::Interrupted => Err(Interrupted.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Token that is cancelled on SIGTERM or Ctrl-C.
+///
+/// Running imports stop after checkpointing their current page or day. A second signal exits
+/// immediately, for when finishing the current unit takes too long.
+pub fn shutdown_on_signal() -> CancellationToken {
+    let shutdown = CancellationToken::new();
+    tokio::spawn(cancel_on_signal(shutdown.clone()));
+    shutdown
+}
+
+async fn cancel_on_signal(shutdown: CancellationToken) {
+    wait_for_signal().await;
+    info!("Shutdown requested, finishing the current page of every running import");
+    shutdown.cancel();
+
+    wait_for_signal().await;
+    warn!("Shutdown requested again, exiting without waiting for running imports");
+    std::process::exit(EXIT_INTERRUPTED);
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = terminate.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to listen for SIGTERM");
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Name recorded as the holder of run locks taken by this process
+pub fn run_lock_holder_name() -> String {
+    format!("{}:{}", env!("CARGO_PKG_NAME"), std::process::id())
+}
+
+/// Pick the explicitly selected keys, or every configured key when none were selected
+pub fn select_keys(configured: &[String], selected: &[String]) -> Vec<String> {
+    let keys = if selected.is_empty() {
+        configured
+    } else {
+        selected
+    };
+
+    keys.iter()
+        .map(|key| key.trim())
+        .filter(|key| !key.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
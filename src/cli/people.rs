@@ -0,0 +1,90 @@
+use clap::Subcommand;
+use std::path::PathBuf;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::cli::CliError;
+use crate::component::people::{parse_directory, CandidateStatus, DirectoryFormat, PeopleHandler};
+use crate::store::Store;
+
+#[derive(Subcommand, Debug)]
+pub enum PeopleCommand {
+    /// Import people, their GitLab and GitHub aliases and team memberships from a YAML or CSV file
+    Import {
+        path: PathBuf,
+        /// yaml or csv [default: by the file extension]
+        #[arg(long, value_parser = DirectoryFormat::from_str)]
+        format: Option<DirectoryFormat>,
+    },
+    /// Propose people for unmapped GitLab authors and Copilot users by email, alias and name
+    Match,
+    /// List match candidates, optionally only pending, accepted or rejected ones
+    Candidates {
+        #[arg(value_parser = CandidateStatus::from_str)]
+        status: Option<CandidateStatus>,
+    },
+    /// Map the identity of a pending candidate to its person
+    Accept { id: Uuid },
+    /// Reject a pending candidate so it is not proposed again
+    Reject { id: Uuid },
+}
+
+pub async fn run(store: Store, command: PeopleCommand) -> Result<(), CliError> {
+    let handler = PeopleHandler { store };
+
+    match command {
+        PeopleCommand::Import { path, format } => {
+            let format = format
+                .or_else(|| DirectoryFormat::from_path(&path))
+                .ok_or_else(|| {
+                    format!(
+                        "Cannot tell the format of {}; pass --format yaml or --format csv",
+                        path.display()
+                    )
+                })?;
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let people = parse_directory(&content, format)?;
+            let summary = handler.import(&people).await?;
+            println!(
+                "Imported {} people with {} aliases and {} team memberships",
+                summary.people, summary.aliases, summary.memberships
+            );
+        }
+        PeopleCommand::Match => {
+            let queued = handler.match_identities().await?;
+            println!(
+                "Queued {} match candidates; review them with `people candidates pending`",
+                queued
+            );
+        }
+        PeopleCommand::Candidates { status } => {
+            let candidates = handler.list_candidates(status).await?;
+
+            for candidate in &candidates {
+                println!(
+                    "{}\t{}={}\tperson={} <{}>\treason={}\tstatus={}\tcreated_at={}",
+                    candidate.id,
+                    candidate.source,
+                    candidate.alias,
+                    candidate.display_name,
+                    candidate.email,
+                    candidate.reason,
+                    candidate.status,
+                    candidate.created_at
+                );
+            }
+            println!("{} match candidates", candidates.len());
+        }
+        PeopleCommand::Accept { id } => {
+            handler.accept(id).await?;
+            println!("Accepted match candidate {}", id);
+        }
+        PeopleCommand::Reject { id } => {
+            handler.reject(id).await?;
+            println!("Rejected match candidate {}", id);
+        }
+    }
+
+    Ok(())
+}
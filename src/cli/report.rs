@@ -0,0 +1,183 @@
+use clap::{Args, Subcommand};
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use time::{Date, OffsetDateTime};
+
+use crate::cli::CliError;
+use crate::component::copilot_delivery::{
+    compare_cohorts, mark_adoption, parse_identity_mapping, CopilotDeliveryHandler, PersonWeek,
+};
+use crate::dates::parse_day;
+use crate::store::Store;
+
+#[derive(Subcommand, Debug)]
+pub enum ReportCommand {
+    /// Compare weekly merge request delivery with Copilot engagement of mapped people
+    CopilotDelivery(CopilotDeliveryArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct CopilotDeliveryArgs {
+    /// First day (YYYY-MM-DD) of the report, rounded down to its Monday
+    #[arg(long, value_parser = parse_day)]
+    pub since: Date,
+    /// Day (YYYY-MM-DD) the report stops before, rounded down to its Monday [default: today]
+    #[arg(long, value_parser = parse_day)]
+    pub until: Option<Date>,
+    /// CSV file with `gitlab` and `github` columns [default: the people directory]
+    #[arg(long)]
+    pub mapping: Option<PathBuf>,
+    /// Copilot active days in a week that make a person an adopter from that week on
+    #[arg(long, default_value_t = 2)]
+    pub adoption_min_active_days: i64,
+    /// weekly (one row per person and week) or cohorts (adopters vs non-adopters, before vs after adoption)
+    #[arg(long, default_value = "weekly", value_parser = ReportDataset::from_str)]
+    pub dataset: ReportDataset,
+    /// csv or json
+    #[arg(long, default_value = "csv", value_parser = ReportFormat::from_str)]
+    pub format: ReportFormat,
+    /// File to write the dataset to [default: stdout]
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportDataset {
+    Weekly,
+    Cohorts,
+}
+
+impl FromStr for ReportDataset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "weekly" => Ok(ReportDataset::Weekly),
+            "cohorts" => Ok(ReportDataset::Cohorts),
+            _ => Err(format!("Unknown report dataset: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ReportFormat::Csv),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!("Unknown report format: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PersonWeekRow<'a> {
+    person: &'a str,
+    week: String,
+    merged_mrs: i64,
+    median_cycle_time_hours: Option<f64>,
+    median_size_lines: Option<f64>,
+    copilot_active_days: i64,
+    code_generations: i64,
+    code_acceptances: i64,
+    loc_added: i64,
+    agent_days: i64,
+    cli_days: i64,
+    adopted: bool,
+}
+
+impl<'a> From<&'a PersonWeek> for PersonWeekRow<'a> {
+    fn from(week: &'a PersonWeek) -> Self {
+        PersonWeekRow {
+            person: &week.person,
+            week: week.week.to_string(),
+            merged_mrs: week.merged_mrs,
+            median_cycle_time_hours: week.median_cycle_time_hours,
+            median_size_lines: week.median_size_lines,
+            copilot_active_days: week.copilot_active_days,
+            code_generations: week.code_generations,
+            code_acceptances: week.code_acceptances,
+            loc_added: week.loc_added,
+            agent_days: week.agent_days,
+            cli_days: week.cli_days,
+            adopted: week.adopted,
+        }
+    }
+}
+
+pub async fn run(store: Store, command: ReportCommand) -> Result<(), CliError> {
+    match command {
+        ReportCommand::CopilotDelivery(args) => copilot_delivery(store, args).await,
+    }
+}
+
+async fn copilot_delivery(store: Store, args: CopilotDeliveryArgs) -> Result<(), CliError> {
+    let handler = CopilotDeliveryHandler { store };
+    let mappings = match &args.mapping {
+        Some(path) => {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            parse_identity_mapping(&content)?
+        }
+        None => handler.mappings_from_directory().await?,
+    };
+    if mappings.is_empty() {
+        return Err(
+            "No mapped people to report on; import the people directory or pass --mapping".into(),
+        );
+    }
+
+    let until = args
+        .until
+        .unwrap_or_else(|| OffsetDateTime::now_utc().date());
+    let mut weeks = handler.fetch_weeks(&mappings, args.since, until).await?;
+    mark_adoption(&mut weeks, args.adoption_min_active_days);
+
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            std::fs::File::create(path)
+                .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?,
+        ),
+        None => Box::new(std::io::stdout()),
+    };
+    match args.dataset {
+        ReportDataset::Weekly => {
+            let rows: Vec<PersonWeekRow> = weeks.iter().map(PersonWeekRow::from).collect();
+            write_rows(output, args.format, &rows)
+        }
+        ReportDataset::Cohorts => write_rows(output, args.format, &compare_cohorts(&weeks)),
+    }
+}
+
+/// Write the rows as CSV with a header line, or as a JSON array
+fn write_rows<T: Serialize>(
+    mut output: Box<dyn Write>,
+    format: ReportFormat,
+    rows: &[T],
+) -> Result<(), CliError> {
+    match format {
+        ReportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(output);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        ReportFormat::Json => {
+            serde_json::to_writer_pretty(&mut output, rows)?;
+            writeln!(output)?;
+            output.flush()?;
+        }
+    }
+
+    Ok(())
+}
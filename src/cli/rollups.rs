@@ -0,0 +1,56 @@
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::cli::{CliError, Interrupted};
+use crate::component::flow_metrics::FlowMetricsHandler;
+use crate::component::rollups::RollupHandler;
+use crate::config::Config;
+use crate::store::Store;
+
+pub async fn refresh(store: Store) -> Result<(), CliError> {
+    let refreshed = RollupHandler { store }.refresh_all().await?;
+
+    for (rollup, elapsed) in &refreshed {
+        println!("{}\t{} ms", rollup, elapsed.as_millis());
+    }
+    println!("Refreshed {} rollups", refreshed.len());
+
+    Ok(())
+}
+
+/// Refresh the rollups fed by `collector` once its run finished, and recompute the flow metrics
+/// after GitLab and AI runs, unless disabled or the run was interrupted by shutdown. Returns the
+/// run's own result: a failed refresh is only logged, as the run's data is stored and the next
+/// run refreshes again.
+pub async fn refresh_after_run(
+    store: &Store,
+    config: &Config,
+    collector: &str,
+    result: Result<(), CliError>,
+) -> Result<(), CliError> {
+    let interrupted = matches!(&result, Err(e) if e.is::<Interrupted>());
+    if !config.reporting.refresh_after_runs || interrupted {
+        return result;
+    }
+
+    let handler = RollupHandler {
+        store: store.clone(),
+    };
+    if let Err(e) = handler.refresh(RollupHandler::rollups_of(collector)).await {
+        warn!(collector, error = %e, "Failed to refresh rollups");
+    }
+
+    if collector == "gitlab" || collector == "ai" {
+        let handler = FlowMetricsHandler {
+            store: store.clone(),
+        };
+        let window_end = OffsetDateTime::now_utc().date();
+        for &window_days in &config.reporting.flow_metric_windows_days {
+            if let Err(e) = handler.compute(window_days, window_end).await {
+                warn!(window_days, error = %e, "Failed to compute flow metrics");
+            }
+        }
+    }
+
+    result
+}
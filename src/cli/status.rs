@@ -0,0 +1,129 @@
+use clap::Args;
+use time::OffsetDateTime;
+
+use crate::cli::CliError;
+use crate::component::collector_runs::CollectorRunsHandler;
+use crate::component::copilot_collector_runs::CopilotCollectorRunsHandler;
+use crate::component::dead_letter::{DeadLetterHandler, DeadLetterStatus};
+use crate::component::import_progress::{ImportProgressHandler, ImportStatus};
+use crate::component::run_history::RunHistoryHandler;
+use crate::config::Config;
+use crate::store::Store;
+
+#[derive(Args, Debug)]
+pub struct StatusArgs {
+    /// Number of recent runs to show
+    #[arg(long, default_value_t = 10)]
+    pub runs: i64,
+    /// Hours without activity after which an unfinished import is flagged as stale
+    #[arg(long)]
+    pub import_stale_after_hours: Option<i64>,
+}
+
+impl StatusArgs {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(stale_after_hours) = self.import_stale_after_hours {
+            config.import_progress.stale_after_hours = stale_after_hours;
+        }
+    }
+}
+
+pub async fn status(store: Store, config: &Config, args: StatusArgs) -> Result<(), CliError> {
+    println!("Watermarks:");
+    let gitlab_run = CollectorRunsHandler {
+        store: store.clone(),
+    }
+    .fetch_last_successfull_collector_run()
+    .await?;
+    match gitlab_run {
+        Some(run) => println!(
+            "  gitlab\tlast_successful_run_completed_at={}",
+            run.last_successful_run_completed_at
+        ),
+        None => println!("  gitlab\tno successful run"),
+    }
+    let copilot_runs = CopilotCollectorRunsHandler {
+        store: store.clone(),
+    }
+    .fetch_latest_successful_runs()
+    .await?;
+    for run in &copilot_runs {
+        println!(
+            "  copilot:{}\tlast_completed_report_day={}\tlast_successful_run_completed_at={}",
+            run.org_slug, run.last_completed_report_day, run.last_successful_run_completed_at
+        );
+    }
+
+    println!("Recent runs:");
+    let runs = RunHistoryHandler {
+        store: store.clone(),
+    }
+    .fetch_recent_runs(None, args.runs)
+    .await?;
+    for run in &runs {
+        println!(
+            "  {}\tcollector={}\tstatus={}\tstarted_at={}\tcompleted_at={}\terror={}",
+            run.id,
+            run.collector,
+            run.status.as_str(),
+            run.started_at,
+            run.completed_at
+                .map(|completed_at| completed_at.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            run.error_summary.as_deref().unwrap_or("-")
+        );
+    }
+
+    println!("Unfinished imports:");
+    let import_progress_handler = ImportProgressHandler {
+        store: store.clone(),
+        run_id: None,
+        policy: config.import_progress.policy(),
+    };
+    let imports = import_progress_handler
+        .list_imports(&[
+            ImportStatus::InProgress,
+            ImportStatus::Interrupted,
+            ImportStatus::Failed,
+        ])
+        .await?;
+    let now = OffsetDateTime::now_utc();
+    for import in &imports {
+        let stale = if import_progress_handler.is_stale(import, now) {
+            "\tSTALE"
+        } else {
+            ""
+        };
+        println!(
+            "  {}\tgroup={}\ttype={}\tstatus={}\tprocessed={}\tcursor={}\tstarted_at={}\tlast_activity_at={}\terror={}{}",
+            import.id,
+            import.group_full_path,
+            import.import_type,
+            import.status.as_str(),
+            import.total_processed,
+            import.last_cursor.as_deref().unwrap_or("-"),
+            import.started_at,
+            import.last_activity_at,
+            import.error_message.as_deref().unwrap_or("-"),
+            stale
+        );
+    }
+
+    let dead_letters = DeadLetterHandler {
+        store: store.clone(),
+        max_attempts: 0,
+    }
+    .list(None)
+    .await?;
+    let pending = dead_letters
+        .iter()
+        .filter(|dead_letter| dead_letter.status == DeadLetterStatus::Pending)
+        .count();
+    println!(
+        "Dead letters: pending={}, parked={}",
+        pending,
+        dead_letters.len() - pending
+    );
+
+    Ok(())
+}
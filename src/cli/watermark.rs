@@ -0,0 +1,80 @@
+use clap::Subcommand;
+use time::format_description::well_known::Rfc3339;
+use time::{Date, OffsetDateTime};
+
+use crate::cli::CliError;
+use crate::component::collector_runs::CollectorRunsHandler;
+use crate::component::copilot_collector_runs::CopilotCollectorRunsHandler;
+use crate::component::copilot_metrics::{self, CopilotMetricsHandler};
+use crate::component::import_progress::{ImportProgressHandler, ImportProgressPolicy};
+use crate::component::merge_request;
+use crate::dates::parse_day;
+use crate::store::Store;
+
+#[derive(Subcommand, Debug)]
+pub enum ResetWatermarkTarget {
+    /// Reset the GitLab watermark shared by all groups
+    Gitlab {
+        /// RFC 3339 timestamp; the next sync fetches merge requests updated after it
+        #[arg(long, value_parser = parse_timestamp)]
+        to: OffsetDateTime,
+    },
+    /// Reset the watermark of one Copilot org
+    Copilot {
+        #[arg(long)]
+        org: String,
+        /// First report day (YYYY-MM-DD) the next sync imports
+        #[arg(long, value_parser = parse_day)]
+        from: Date,
+    },
+}
+
+fn parse_timestamp(value: &str) -> Result<OffsetDateTime, String> {
+    OffsetDateTime::parse(value, &Rfc3339).map_err(|e| e.to_string())
+}
+
+pub async fn reset(store: Store, target: ResetWatermarkTarget) -> Result<(), CliError> {
+    let import_progress_handler = ImportProgressHandler {
+        store: store.clone(),
+        run_id: None,
+        policy: ImportProgressPolicy::default(),
+    };
+
+    match target {
+        ResetWatermarkTarget::Gitlab { to } => {
+            let deleted = CollectorRunsHandler {
+                store: store.clone(),
+            }
+            .reset_watermark(to)
+            .await?;
+            // Unfinished imports still carry the old watermark as their updated_after
+            let abandoned = import_progress_handler
+                .abandon_resumable_imports(None, merge_request::IMPORT_TYPE, "watermark reset")
+                .await?;
+            println!(
+                "GitLab watermark reset to {}: removed {} later successful runs, abandoned {} unfinished imports",
+                to, deleted, abandoned
+            );
+        }
+        ResetWatermarkTarget::Copilot { org, from } => {
+            let deleted = CopilotCollectorRunsHandler {
+                store: store.clone(),
+            }
+            .reset_watermark(&org, from)
+            .await?;
+            let abandoned = import_progress_handler
+                .abandon_resumable_imports(
+                    Some(&CopilotMetricsHandler::import_progress_group_key(&org)),
+                    copilot_metrics::IMPORT_TYPE,
+                    "watermark reset",
+                )
+                .await?;
+            println!(
+                "Copilot watermark for org={} reset to start at {}: removed {} later successful runs, abandoned {} unfinished imports",
+                org, from, deleted, abandoned
+            );
+        }
+    }
+
+    Ok(())
+}
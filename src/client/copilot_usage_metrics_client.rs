@@ -1,7 +1,11 @@
+use std::time::Instant;
+
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::metrics;
+
 #[derive(Error, Debug)]
 pub enum CopilotUsageMetricsError {
     #[error("Failed to execute REST request: {0}")]
@@ -63,6 +67,10 @@ impl CopilotUsageMetricsClient {
         })
     }
 
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
     pub async fn fetch_org_users_usage_report_for_day(
         &self,
         org_slug: &str,
@@ -73,12 +81,20 @@ impl CopilotUsageMetricsClient {
             self.endpoint, org_slug
         );
 
-        let response = self
+        let started_at = Instant::now();
+        let result = self
             .api_client
             .get(&url)
             .query(&[("day", day)])
             .send()
-            .await?;
+            .await;
+        metrics::observe_api_request(
+            "github",
+            "copilot_users_usage_report",
+            &metrics::status_label(&result),
+            started_at.elapsed(),
+        );
+        let response = result?;
         let status = response.status();
 
         if status == StatusCode::NO_CONTENT {
@@ -97,7 +113,15 @@ impl CopilotUsageMetricsClient {
         &self,
         download_url: &str,
     ) -> Result<Vec<CopilotDailyUserMetricsRecord>, CopilotUsageMetricsError> {
-        let response = self.download_client.get(download_url).send().await?;
+        let started_at = Instant::now();
+        let result = self.download_client.get(download_url).send().await;
+        metrics::observe_api_request(
+            "github",
+            "copilot_users_usage_report_download",
+            &metrics::status_label(&result),
+            started_at.elapsed(),
+        );
+        let response = result?;
         let status = response.status();
 
         if !status.is_success() {
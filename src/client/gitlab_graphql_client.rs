@@ -1,7 +1,12 @@
+use std::time::Instant;
+
 use graphql_client::GraphQLQuery;
-use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::metrics;
+
 /// Custom post_graphql helper that works with any reqwest version.
 /// This decouples us from graphql_client's reqwest dependency.
 async fn post_graphql<Q: GraphQLQuery>(
@@ -9,11 +14,31 @@ async fn post_graphql<Q: GraphQLQuery>(
     url: &str,
     variables: Q::Variables,
 ) -> Result<graphql_client::Response<Q::ResponseData>, reqwest::Error>
+where
+    Q::Variables: Serialize,
+{
+    post_graphql_as::<Q, Q::ResponseData>(client, url, variables).await
+}
+
+/// Like [`post_graphql`], but reads the data into `R` instead of the generated response type
+async fn post_graphql_as<Q: GraphQLQuery, R: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+    variables: Q::Variables,
+) -> Result<graphql_client::Response<R>, reqwest::Error>
 where
     Q::Variables: Serialize,
 {
     let body = Q::build_query(variables);
-    client.post(url).json(&body).send().await?.json().await
+    let started_at = Instant::now();
+    let result = client.post(url).json(&body).send().await;
+    metrics::observe_api_request(
+        "gitlab_graphql",
+        body.operation_name,
+        &metrics::status_label(&result),
+        started_at.elapsed(),
+    );
+    result?.json().await
 }
 
 #[derive(Error, Debug)]
@@ -28,10 +53,38 @@ pub enum GitlabGraphQLError {
     MissingData,
     #[error("Group not found: {0}")]
     GroupNotFound(String),
+    #[error("Project not found: {0}")]
+    ProjectNotFound(String),
     #[error("Invalid authorization header: {0}")]
     InvalidHeader(String),
 }
 
+impl GitlabGraphQLError {
+    /// Whether GitLab rejected the `after` cursor of the request, e.g. "Please provide a valid
+    /// cursor"; other GraphQL errors say nothing about the cursor and are not retried from scratch
+    pub fn is_rejected_cursor(&self) -> bool {
+        match self {
+            GitlabGraphQLError::GraphQLErrors(errors) => errors
+                .iter()
+                .any(|message| message.to_lowercase().contains("cursor")),
+            _ => false,
+        }
+    }
+}
+
+/// RFC 3339 filters of the group merge requests query; filters that are not set are not applied
+#[derive(Debug, Clone, Default)]
+pub struct MergeRequestFilter {
+    pub updated_after: Option<String>,
+    pub merged_after: Option<String>,
+    pub merged_before: Option<String>,
+    /// Merge request iids; iids are per project, so results of other projects must be dropped
+    pub iids: Option<Vec<String>>,
+}
+
+/// Page of merge requests, as returned by both the group and the project query
+pub type MergeRequestConnection = group_merge_reqs::GroupMergeReqsGroupMergeRequests;
+
 #[derive(Debug, Clone)]
 pub struct GitlabGraphQLClient {
     client: reqwest::Client,
@@ -52,15 +105,36 @@ impl GitlabGraphQLClient {
         Ok(GitlabGraphQLClient { client, url })
     }
 
+    pub fn endpoint(&self) -> &str {
+        &self.url
+    }
+
     pub async fn fetch_group_merge_requests(
         &self,
         group_full_path: &str,
         updated_after: &str,
         after_pointer_token: Option<String>,
+    ) -> Result<group_merge_reqs::GroupMergeReqsGroup, GitlabGraphQLError> {
+        let filter = MergeRequestFilter {
+            updated_after: Some(updated_after.to_string()),
+            ..MergeRequestFilter::default()
+        };
+        self.fetch_filtered_group_merge_requests(group_full_path, &filter, after_pointer_token)
+            .await
+    }
+
+    pub async fn fetch_filtered_group_merge_requests(
+        &self,
+        group_full_path: &str,
+        filter: &MergeRequestFilter,
+        after_pointer_token: Option<String>,
     ) -> Result<group_merge_reqs::GroupMergeReqsGroup, GitlabGraphQLError> {
         let variables = group_merge_reqs::Variables {
             group_full_path: group_full_path.to_string(),
-            updated_after: updated_after.to_string(),
+            updated_after: filter.updated_after.clone(),
+            merged_after: filter.merged_after.clone(),
+            merged_before: filter.merged_before.clone(),
+            iids: filter.iids.clone(),
             after: after_pointer_token,
         };
 
@@ -83,6 +157,44 @@ impl GitlabGraphQLClient {
             .ok_or_else(|| GitlabGraphQLError::GroupNotFound(group_full_path.to_string()))
     }
 
+    /// Merge requests of one project, with the same filters and fields as the group query
+    pub async fn fetch_filtered_project_merge_requests(
+        &self,
+        project_full_path: &str,
+        filter: &MergeRequestFilter,
+        after_pointer_token: Option<String>,
+    ) -> Result<MergeRequestConnection, GitlabGraphQLError> {
+        let variables = project_merge_reqs::Variables {
+            project_full_path: project_full_path.to_string(),
+            updated_after: filter.updated_after.clone(),
+            merged_after: filter.merged_after.clone(),
+            merged_before: filter.merged_before.clone(),
+            iids: filter.iids.clone(),
+            after: after_pointer_token,
+        };
+
+        let response = post_graphql_as::<ProjectMergeReqs, ProjectMergeReqsData>(
+            &self.client,
+            &self.url,
+            variables,
+        )
+        .await?;
+
+        if let Some(errors) = response.errors {
+            if !errors.is_empty() {
+                return Err(GitlabGraphQLError::GraphQLErrors(
+                    errors.iter().map(|e| e.message.clone()).collect(),
+                ));
+            }
+        }
+
+        let response_data = response.data.ok_or(GitlabGraphQLError::MissingData)?;
+        response_data
+            .project
+            .map(|project| project.merge_requests)
+            .ok_or_else(|| GitlabGraphQLError::ProjectNotFound(project_full_path.to_string()))
+    }
+
     pub async fn fetch_group_projects(
         &self,
         group_full_path: &str,
@@ -130,3 +242,24 @@ struct GroupMergeReqs;
     response_derives = "Debug"
 )]
 struct GroupProjects;
+
+#[derive(GraphQLQuery, Clone)]
+#[graphql(
+    schema_path = "src/client/gitlab_group_mrs_schema.graphql",
+    query_path = "src/client/gitlab_project_mrs_query.graphql",
+    response_derives = "Debug"
+)]
+struct ProjectMergeReqs;
+
+/// Data of the project merge requests query; it selects the same merge request fields as the
+/// group query, so its connection is read into the type generated for the group query
+#[derive(Debug, Deserialize)]
+struct ProjectMergeReqsData {
+    project: Option<ProjectMergeReqsProject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectMergeReqsProject {
+    #[serde(rename = "mergeRequests")]
+    merge_requests: MergeRequestConnection,
+}
@@ -1,5 +1,12 @@
+use std::time::Instant;
+
 use serde::Deserialize;
+use serde_with::serde_as;
 use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::metrics;
 
 #[derive(Error, Debug)]
 pub enum GitlabRestError {
@@ -31,6 +38,10 @@ impl GitlabRestClient {
         Ok(GitlabRestClient { client, endpoint })
     }
 
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
     pub async fn fetch_merge_request_changes(
         &self,
         project_id: &str,
@@ -40,11 +51,79 @@ impl GitlabRestClient {
             "{}/projects/{}/merge_requests/{}/changes",
             self.endpoint, project_id, merge_request_iid
         );
-        let res = self.client.get(&url).send().await?;
+        let started_at = Instant::now();
+        let result = self.client.get(&url).send().await;
+        metrics::observe_api_request(
+            "gitlab_rest",
+            "merge_request_changes",
+            &metrics::status_label(&result),
+            started_at.elapsed(),
+        );
+        let res = result?;
         let text = res.text().await?;
         let mr_with_changes: MergeRequestResponseWithChanges = serde_json::from_str(&text)?;
         Ok(mr_with_changes.changes.unwrap_or_default())
     }
+
+    /// One page of the notes of a merge request, read oldest first, with the time of the system
+    /// note GitLab adds when it is approved if the page holds it.
+    pub async fn fetch_approval_note_page(
+        &self,
+        project_id: &str,
+        merge_request_iid: &str,
+        page: &str,
+    ) -> Result<ApprovalNotePage, GitlabRestError> {
+        let url = format!(
+            "{}/projects/{}/merge_requests/{}/notes?sort=asc&order_by=created_at&per_page=100&page={}",
+            self.endpoint, project_id, merge_request_iid, page
+        );
+        let started_at = Instant::now();
+        let result = self.client.get(&url).send().await;
+        metrics::observe_api_request(
+            "gitlab_rest",
+            "merge_request_notes",
+            &metrics::status_label(&result),
+            started_at.elapsed(),
+        );
+        let res = result?.error_for_status()?;
+        // Empty on the last page
+        let next_page = res
+            .headers()
+            .get("x-next-page")
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string);
+        let text = res.text().await?;
+        let notes: Vec<Note> = serde_json::from_str(&text)?;
+        let first_approved_at = notes
+            .into_iter()
+            .find(|note| note.system && note.body == APPROVAL_NOTE)
+            .map(|note| note.created_at);
+        Ok(ApprovalNotePage {
+            first_approved_at,
+            next_page,
+        })
+    }
+}
+
+/// Body of the system note recording an approval
+const APPROVAL_NOTE: &str = "approved this merge request";
+
+#[derive(Debug)]
+pub struct ApprovalNotePage {
+    pub first_approved_at: Option<OffsetDateTime>,
+    /// The page to request next, `None` on the last page
+    pub next_page: Option<String>,
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize)]
+pub struct Note {
+    pub body: String,
+    pub system: bool,
+    #[serde_as(as = "Rfc3339")]
+    pub created_at: OffsetDateTime,
 }
 
 #[derive(Debug, Deserialize)]
@@ -21,19 +21,27 @@ pub struct CollectorRun {
     pub last_successful_run_completed_at: OffsetDateTime,
 }
 
+impl CollectorRun {
+    /// Where the next sync starts: the start of this run, so merge requests merged or updated
+    /// while it was running are imported by the next one
+    pub fn watermark(&self) -> OffsetDateTime {
+        self.last_successful_run_started_at
+    }
+}
+
 impl CollectorRunsHandler {
     pub async fn fetch_last_successfull_collector_run(
         &self,
     ) -> Result<Option<CollectorRun>, CollectorRunsError> {
         let mut conn = self.store.conn_pool.acquire().await?;
-        let row = sqlx::query(
+        let row = sqlx::query(&self.store.sql(
             r#"
             SELECT last_successful_run_started_at, last_successful_run_completed_at
             FROM engineering_metrics.collector_runs
             ORDER BY last_successful_run_started_at DESC
             LIMIT 1
             "#,
-        )
+        ))
         .fetch_optional(&mut *conn)
         .await?;
 
@@ -53,10 +61,10 @@ impl CollectorRunsHandler {
     ) -> Result<(), CollectorRunsError> {
         let mut conn = self.store.conn_pool.acquire().await?;
         sqlx::query(
-            r#"
+            &self.store.sql(r#"
             INSERT INTO engineering_metrics.collector_runs (last_successful_run_started_at, last_successful_run_completed_at)
             VALUES ($1, $2)
-            "#)
+            "#))
             .bind(run.last_successful_run_started_at)
             .bind(run.last_successful_run_completed_at)
         .execute(&mut *conn)
@@ -64,4 +72,35 @@ impl CollectorRunsHandler {
 
         Ok(())
     }
+
+    /// Move the watermark back to `to` by dropping every later successful run.
+    ///
+    /// The next run fetches data updated after `to`.
+    pub async fn reset_watermark(&self, to: OffsetDateTime) -> Result<u64, CollectorRunsError> {
+        let mut tx = self.store.conn_pool.begin().await?;
+
+        let deleted = sqlx::query(&self.store.sql(
+            r#"
+            DELETE FROM engineering_metrics.collector_runs
+            WHERE last_successful_run_completed_at >= $1
+            "#,
+        ))
+        .bind(to)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            &self.store.sql(r#"
+            INSERT INTO engineering_metrics.collector_runs (last_successful_run_started_at, last_successful_run_completed_at)
+            VALUES ($1, $1)
+            "#),
+        )
+        .bind(to)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(deleted.rows_affected())
+    }
 }
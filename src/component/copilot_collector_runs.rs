@@ -1,6 +1,6 @@
 use sqlx::Row;
 use thiserror::Error;
-use time::{Date, OffsetDateTime};
+use time::{Date, Duration, OffsetDateTime};
 
 use crate::store::Store;
 
@@ -30,13 +30,13 @@ impl CopilotCollectorRunsHandler {
     ) -> Result<Option<CopilotCollectorRun>, CopilotCollectorRunsError> {
         let mut conn = self.store.conn_pool.acquire().await?;
         let row = sqlx::query(
-            r#"
+            &self.store.sql(r#"
             SELECT org_slug, last_successful_run_started_at, last_successful_run_completed_at, last_completed_report_day
             FROM engineering_metrics.copilot_collector_runs
             WHERE org_slug = $1
             ORDER BY last_successful_run_started_at DESC
             LIMIT 1
-            "#,
+            "#),
         )
         .bind(org_slug)
         .fetch_optional(&mut *conn)
@@ -50,12 +50,83 @@ impl CopilotCollectorRunsHandler {
         }))
     }
 
+    /// Fetch the most recent successful run of every org
+    pub async fn fetch_latest_successful_runs(
+        &self,
+    ) -> Result<Vec<CopilotCollectorRun>, CopilotCollectorRunsError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(
+            &self.store.sql(r#"
+            SELECT DISTINCT ON (org_slug)
+                org_slug, last_successful_run_started_at, last_successful_run_completed_at, last_completed_report_day
+            FROM engineering_metrics.copilot_collector_runs
+            ORDER BY org_slug, last_successful_run_started_at DESC
+            "#),
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| CopilotCollectorRun {
+                org_slug: row.get(0),
+                last_successful_run_started_at: row.get(1),
+                last_successful_run_completed_at: row.get(2),
+                last_completed_report_day: row.get(3),
+            })
+            .collect())
+    }
+
+    /// Move the watermark of an org back so the next run imports report days starting at `from_day`
+    pub async fn reset_watermark(
+        &self,
+        org_slug: &str,
+        from_day: Date,
+    ) -> Result<u64, CopilotCollectorRunsError> {
+        let last_completed_report_day = from_day - Duration::days(1);
+        let now = OffsetDateTime::now_utc();
+        let mut tx = self.store.conn_pool.begin().await?;
+
+        let deleted = sqlx::query(&self.store.sql(
+            r#"
+            DELETE FROM engineering_metrics.copilot_collector_runs
+            WHERE org_slug = $1
+              AND last_completed_report_day >= $2
+            "#,
+        ))
+        .bind(org_slug)
+        .bind(last_completed_report_day)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(&self.store.sql(
+            r#"
+            INSERT INTO engineering_metrics.copilot_collector_runs (
+                org_slug,
+                last_successful_run_started_at,
+                last_successful_run_completed_at,
+                last_completed_report_day
+            )
+            VALUES ($1, $2, $2, $3)
+            "#,
+        ))
+        .bind(org_slug)
+        .bind(now)
+        .bind(last_completed_report_day)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(deleted.rows_affected())
+    }
+
     pub async fn persist_successful_run(
         &self,
         run: &CopilotCollectorRun,
     ) -> Result<(), CopilotCollectorRunsError> {
         let mut conn = self.store.conn_pool.acquire().await?;
-        sqlx::query(
+        sqlx::query(&self.store.sql(
             r#"
             INSERT INTO engineering_metrics.copilot_collector_runs (
                 org_slug,
@@ -65,7 +136,7 @@ impl CopilotCollectorRunsHandler {
             )
             VALUES ($1, $2, $3, $4)
             "#,
-        )
+        ))
         .bind(&run.org_slug)
         .bind(run.last_successful_run_started_at)
         .bind(run.last_successful_run_completed_at)
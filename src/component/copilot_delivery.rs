@@ -0,0 +1,367 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+use time::{Date, Duration};
+
+use crate::component::people::{IdentitySource, PeopleError, PeopleHandler};
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum CopilotDeliveryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("People directory error: {0}")]
+    PeopleError(#[from] PeopleError),
+    #[error("Failed to parse the identity mapping: {0}")]
+    ParseError(String),
+    #[error("The report range {from} to {to} is empty")]
+    EmptyRange { from: Date, to: Date },
+}
+
+/// GitLab usernames and GitHub logins of one person of the report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PersonMapping {
+    /// Email from the people directory, or the GitLab username from a mapping file
+    pub person: String,
+    pub gitlab: Vec<String>,
+    pub github: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MappingRow {
+    gitlab: String,
+    #[serde(default)]
+    github: String,
+}
+
+/// Parse a CSV mapping file with `gitlab` and `github` columns. A person is keyed by GitLab
+/// username; rows repeating a username add further logins, and an empty login maps none.
+pub fn parse_identity_mapping(content: &str) -> Result<Vec<PersonMapping>, CopilotDeliveryError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    let mut people: Vec<PersonMapping> = Vec::new();
+    let mut by_username: HashMap<String, usize> = HashMap::new();
+
+    for row in reader.deserialize::<MappingRow>() {
+        let row = row.map_err(|e| CopilotDeliveryError::ParseError(e.to_string()))?;
+        let username = row.gitlab.to_lowercase();
+        if username.is_empty() {
+            continue;
+        }
+        let index = *by_username.entry(username.clone()).or_insert_with(|| {
+            people.push(PersonMapping {
+                person: username.clone(),
+                gitlab: vec![username.clone()],
+                github: Vec::new(),
+            });
+            people.len() - 1
+        });
+        let login = row.github.to_lowercase();
+        if !login.is_empty() && !people[index].github.contains(&login) {
+            people[index].github.push(login);
+        }
+    }
+
+    Ok(people)
+}
+
+/// Delivery and Copilot engagement of one person in one week
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersonWeek {
+    pub person: String,
+    /// Monday of the week
+    pub week: Date,
+    pub merged_mrs: i64,
+    /// Median hours from creation to merge of the week's merged merge requests
+    pub median_cycle_time_hours: Option<f64>,
+    /// Median added plus deleted lines of the week's merged merge requests with diff stats
+    pub median_size_lines: Option<f64>,
+    pub copilot_active_days: i64,
+    pub code_generations: i64,
+    pub code_acceptances: i64,
+    pub loc_added: i64,
+    pub agent_days: i64,
+    pub cli_days: i64,
+    /// Whether the person adopted Copilot in this week or before
+    pub adopted: bool,
+}
+
+/// Aggregate of the person-weeks of one cohort
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CohortSummary {
+    pub cohort: &'static str,
+    pub people: usize,
+    pub person_weeks: usize,
+    pub merged_mrs: i64,
+    pub mean_weekly_merged_mrs: Option<f64>,
+    /// Median over the person-weeks with merges of their median cycle time
+    pub median_weekly_cycle_time_hours: Option<f64>,
+    pub median_weekly_size_lines: Option<f64>,
+    pub mean_weekly_active_days: Option<f64>,
+    pub code_acceptances: i64,
+    pub loc_added: i64,
+    pub agent_days: i64,
+    pub cli_days: i64,
+}
+
+/// Cohorts compared by the report, in output order
+pub const COHORTS: [&str; 4] = [
+    "adopters",
+    "non_adopters",
+    "adopters_before_adoption",
+    "adopters_after_adoption",
+];
+
+/// Monday of the week of `day`
+pub fn week_start(day: Date) -> Date {
+    day - Duration::days(day.weekday().number_days_from_monday().into())
+}
+
+/// Mark the weeks from each person's adoption on: the first week with at least
+/// `min_active_days` Copilot active days. `weeks` must be ordered by person and week.
+pub fn mark_adoption(weeks: &mut [PersonWeek], min_active_days: i64) {
+    let mut adopted_person: Option<String> = None;
+    for week in weeks.iter_mut() {
+        if adopted_person.as_deref() != Some(week.person.as_str()) {
+            adopted_person = None;
+        }
+        if adopted_person.is_none() && week.copilot_active_days >= min_active_days {
+            adopted_person = Some(week.person.clone());
+        }
+        week.adopted = adopted_person.is_some();
+    }
+}
+
+/// Compare adopters with non-adopters over the whole range, and the adopters' weeks before
+/// their adoption with those from it on. Adopters are the people with any adopted week.
+pub fn compare_cohorts(weeks: &[PersonWeek]) -> Vec<CohortSummary> {
+    let adopters: HashSet<&str> = weeks
+        .iter()
+        .filter(|week| week.adopted)
+        .map(|week| week.person.as_str())
+        .collect();
+    let is_adopter = |week: &PersonWeek| adopters.contains(&week.person.as_str());
+
+    COHORTS
+        .iter()
+        .map(|&cohort| {
+            let members: Vec<&PersonWeek> = weeks
+                .iter()
+                .filter(|week| match cohort {
+                    "adopters" => is_adopter(week),
+                    "non_adopters" => !is_adopter(week),
+                    "adopters_before_adoption" => is_adopter(week) && !week.adopted,
+                    _ => week.adopted,
+                })
+                .collect();
+            summarize(cohort, &members)
+        })
+        .collect()
+}
+
+fn summarize(cohort: &'static str, weeks: &[&PersonWeek]) -> CohortSummary {
+    let mut people: Vec<&str> = weeks.iter().map(|week| week.person.as_str()).collect();
+    people.sort_unstable();
+    people.dedup();
+    let count = weeks.len() as f64;
+    let mean = |total: i64| (!weeks.is_empty()).then(|| total as f64 / count);
+    let total = |value: fn(&PersonWeek) -> i64| weeks.iter().map(|week| value(week)).sum::<i64>();
+
+    let merged_mrs = total(|week| week.merged_mrs);
+    CohortSummary {
+        cohort,
+        people: people.len(),
+        person_weeks: weeks.len(),
+        merged_mrs,
+        mean_weekly_merged_mrs: mean(merged_mrs),
+        median_weekly_cycle_time_hours: median(
+            weeks.iter().filter_map(|week| week.median_cycle_time_hours),
+        ),
+        median_weekly_size_lines: median(weeks.iter().filter_map(|week| week.median_size_lines)),
+        mean_weekly_active_days: mean(total(|week| week.copilot_active_days)),
+        code_acceptances: total(|week| week.code_acceptances),
+        loc_added: total(|week| week.loc_added),
+        agent_days: total(|week| week.agent_days),
+        cli_days: total(|week| week.cli_days),
+    }
+}
+
+fn median(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let mut values: Vec<f64> = values.collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    })
+}
+
+/// Reads the weekly delivery and Copilot engagement of mapped people
+#[derive(Debug, Clone)]
+pub struct CopilotDeliveryHandler {
+    pub store: Store,
+}
+
+impl CopilotDeliveryHandler {
+    /// People of the directory with a GitLab alias; only their merge requests can be compared
+    pub async fn mappings_from_directory(
+        &self,
+    ) -> Result<Vec<PersonMapping>, CopilotDeliveryError> {
+        let people = PeopleHandler {
+            store: self.store.clone(),
+        }
+        .fetch_people()
+        .await?;
+
+        Ok(people
+            .into_iter()
+            .map(|person| {
+                let aliases = |source: IdentitySource| {
+                    person
+                        .aliases
+                        .iter()
+                        .filter(|(alias_source, _)| *alias_source == source)
+                        .map(|(_, alias)| alias.clone())
+                        .collect::<Vec<String>>()
+                };
+                PersonMapping {
+                    gitlab: aliases(IdentitySource::Gitlab),
+                    github: aliases(IdentitySource::Github),
+                    person: person.email,
+                }
+            })
+            .filter(|mapping| !mapping.gitlab.is_empty())
+            .collect())
+    }
+
+    /// One row per person and week from the week of `from` until the week of `to`
+    /// (exclusive), ordered by person and week, with `adopted` left unset
+    pub async fn fetch_weeks(
+        &self,
+        mappings: &[PersonMapping],
+        from: Date,
+        to: Date,
+    ) -> Result<Vec<PersonWeek>, CopilotDeliveryError> {
+        let (from, to) = (week_start(from), week_start(to));
+        if to <= from {
+            return Err(CopilotDeliveryError::EmptyRange { from, to });
+        }
+
+        let mut people = Vec::new();
+        let mut sources = Vec::new();
+        let mut aliases = Vec::new();
+        for mapping in mappings {
+            let identities = mapping
+                .gitlab
+                .iter()
+                .map(|alias| (IdentitySource::Gitlab, alias))
+                .chain(
+                    mapping
+                        .github
+                        .iter()
+                        .map(|alias| (IdentitySource::Github, alias)),
+                );
+            for (source, alias) in identities {
+                people.push(mapping.person.as_str());
+                sources.push(source.as_str());
+                aliases.push(alias.to_lowercase());
+            }
+        }
+
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            WITH identities AS (
+                SELECT *
+                FROM UNNEST($1::VARCHAR[], $2::VARCHAR[], $3::VARCHAR[]) AS i (person, source, alias)
+            ),
+            weeks AS (
+                SELECT generate_series($4::date::timestamp, ($5::date - 7)::timestamp, INTERVAL '7 days')::date AS week
+            ),
+            deliveries AS (
+                SELECT
+                    i.person,
+                    date_trunc('week', mr.merged_at AT TIME ZONE 'UTC')::date AS week,
+                    count(*) AS merged_mrs,
+                    percentile_cont(0.5) WITHIN GROUP (
+                        ORDER BY EXTRACT(EPOCH FROM mr.merged_at - mr.created_at)::double precision / 3600
+                    ) AS median_cycle_time_hours,
+                    percentile_cont(0.5) WITHIN GROUP (
+                        ORDER BY (mr.diff_stats_summary ->> 'additions')::integer
+                            + (mr.diff_stats_summary ->> 'deletions')::integer
+                    ) AS median_size_lines
+                FROM engineering_metrics.merge_requests mr
+                JOIN identities i ON i.source = 'gitlab' AND i.alias = lower(mr.created_by)
+                WHERE mr.merged_at >= $4::date::timestamp AT TIME ZONE 'UTC'
+                  AND mr.merged_at < $5::date::timestamp AT TIME ZONE 'UTC'
+                GROUP BY 1, 2
+            ),
+            engagement AS (
+                SELECT
+                    i.person,
+                    date_trunc('week', c.report_day::timestamp)::date AS week,
+                    count(DISTINCT c.report_day) AS active_days,
+                    sum(c.code_generation_activity_count)::bigint AS code_generations,
+                    sum(c.code_acceptance_activity_count)::bigint AS code_acceptances,
+                    sum(c.loc_added_sum)::bigint AS loc_added,
+                    count(DISTINCT c.report_day) FILTER (
+                        WHERE c.used_agent OR c.used_copilot_coding_agent OR c.used_copilot_cloud_agent
+                    ) AS agent_days,
+                    count(DISTINCT c.report_day) FILTER (WHERE c.used_cli) AS cli_days
+                FROM engineering_metrics.copilot_user_daily_metrics c
+                JOIN identities i ON i.source = 'github' AND i.alias = lower(c.user_login)
+                WHERE c.report_day >= $4::date AND c.report_day < $5::date
+                GROUP BY 1, 2
+            )
+            SELECT
+                p.person,
+                w.week,
+                COALESCE(d.merged_mrs, 0),
+                d.median_cycle_time_hours,
+                d.median_size_lines,
+                COALESCE(e.active_days, 0),
+                COALESCE(e.code_generations, 0),
+                COALESCE(e.code_acceptances, 0),
+                COALESCE(e.loc_added, 0),
+                COALESCE(e.agent_days, 0),
+                COALESCE(e.cli_days, 0)
+            FROM (SELECT DISTINCT person FROM identities) p
+            CROSS JOIN weeks w
+            LEFT JOIN deliveries d ON d.person = p.person AND d.week = w.week
+            LEFT JOIN engagement e ON e.person = p.person AND e.week = w.week
+            ORDER BY p.person, w.week
+            "#,
+        ))
+        .bind(&people)
+        .bind(&sources)
+        .bind(&aliases)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| PersonWeek {
+                person: row.get(0),
+                week: row.get(1),
+                merged_mrs: row.get(2),
+                median_cycle_time_hours: row.get(3),
+                median_size_lines: row.get(4),
+                copilot_active_days: row.get(5),
+                code_generations: row.get(6),
+                code_acceptances: row.get(7),
+                loc_added: row.get(8),
+                agent_days: row.get(9),
+                cli_days: row.get(10),
+                adopted: false,
+            })
+            .collect())
+    }
+}
@@ -1,24 +1,29 @@
 use crate::client::copilot_usage_metrics_client::{
     CopilotDailyUserMetricsRecord, CopilotFeatureMetrics, CopilotIdeMetrics,
     CopilotLanguageFeatureMetrics, CopilotLanguageModelMetrics, CopilotModelFeatureMetrics,
-    CopilotUsageMetricsError,
+    CopilotUsageMetricsError, CopilotUsersUsageReportDay,
 };
 use crate::component::copilot_collector_runs::{
     CopilotCollectorRunsError, CopilotCollectorRunsHandler,
 };
 use crate::component::import_progress::{ImportProgressError, ImportProgressHandler};
+use crate::component::run_history::RunGroupStats;
 use crate::context::CopilotContext;
+use crate::metrics;
+use crate::scheduler::SchedulerError;
 use serde_json::Value;
 use sqlx::{Postgres, Transaction};
+use std::time::Instant;
 use thiserror::Error;
 use time::format_description::well_known::Rfc3339;
 use time::macros::format_description;
 use time::{Date, Duration, OffsetDateTime};
+use tracing::{info, info_span, instrument, warn, Instrument};
 use uuid::Uuid;
 
 const DAY_FORMAT: &[time::format_description::FormatItem<'static>] =
     format_description!("[year]-[month]-[day]");
-const IMPORT_TYPE: &str = "copilot_users_1_day";
+pub const IMPORT_TYPE: &str = "copilot_users_1_day";
 
 #[derive(Error, Debug)]
 pub enum CopilotMetricsError {
@@ -36,8 +41,12 @@ pub enum CopilotMetricsError {
     ImportProgressError(#[from] ImportProgressError),
     #[error("Copilot collector runs error: {0}")]
     CollectorRunsError(#[from] CopilotCollectorRunsError),
+    #[error("Scheduler error: {0}")]
+    SchedulerError(#[from] SchedulerError),
     #[error("Invalid state: {0}")]
     InvalidState(String),
+    #[error("Interrupted by shutdown")]
+    Interrupted,
 }
 
 #[derive(Debug, Clone)]
@@ -53,14 +62,44 @@ pub struct CopilotMetricsImportSummary {
 }
 
 impl CopilotMetricsHandler {
+    async fn fetch_report_for_day(
+        &self,
+        org_slug: &str,
+        day: &str,
+    ) -> Result<Option<CopilotUsersUsageReportDay>, CopilotMetricsError> {
+        let client = &self.context.copilot_usage_metrics_client;
+        let _permit = self.context.scheduler.acquire(client.endpoint()).await?;
+        Ok(client
+            .fetch_org_users_usage_report_for_day(org_slug, day)
+            .await?)
+    }
+
+    /// Download links point to a storage host, which gets its own share of the request limit
+    async fn download_report(
+        &self,
+        download_url: &str,
+    ) -> Result<Vec<CopilotDailyUserMetricsRecord>, CopilotMetricsError> {
+        let _permit = self.context.scheduler.acquire(download_url).await?;
+        Ok(self
+            .context
+            .copilot_usage_metrics_client
+            .download_users_usage_report(download_url)
+            .await?)
+    }
+
+    /// Import the daily user usage reports of an org, accumulating the run statistics into `stats`
+    #[instrument(name = "import", skip_all, fields(org = org_slug))]
     pub async fn import_org_users_usage_metrics(
         &self,
         org_slug: &str,
         updated_after: &str,
+        stats: &mut RunGroupStats,
     ) -> Result<CopilotMetricsImportSummary, CopilotMetricsError> {
         let updated_after_time = OffsetDateTime::parse(updated_after, &Rfc3339)?;
         let import_progress_handler = ImportProgressHandler {
             store: self.context.store.clone(),
+            run_id: self.context.run_id,
+            policy: self.context.import_progress_policy.clone(),
         };
         let group_key = Self::import_progress_group_key(org_slug);
         let import_progress = import_progress_handler
@@ -95,12 +134,29 @@ impl CopilotMetricsHandler {
         }
 
         while current_day <= end_day {
+            // Stop between report days on shutdown; the next run resumes after the last completed day
+            if self.context.shutdown.is_cancelled() {
+                if let Err(e) = import_progress_handler
+                    .mark_interrupted(import_progress.id)
+                    .await
+                {
+                    warn!(error = %e, "Failed to mark import as interrupted");
+                }
+                info!(
+                    org = org_slug,
+                    next_day = %current_day,
+                    "Copilot import interrupted by shutdown, progress saved at the last completed day"
+                );
+                return Err(CopilotMetricsError::Interrupted);
+            }
+
             let day_string = current_day.format(DAY_FORMAT)?;
+            let day_span = info_span!("day", day = %day_string, import_id = %import_progress.id);
 
+            stats.api_calls += 1;
             let report_for_day = match self
-                .context
-                .copilot_usage_metrics_client
-                .fetch_org_users_usage_report_for_day(org_slug, &day_string)
+                .fetch_report_for_day(org_slug, &day_string)
+                .instrument(day_span.clone())
                 .await
             {
                 Ok(report) => report,
@@ -108,7 +164,8 @@ impl CopilotMetricsHandler {
                     let _ = import_progress_handler
                         .mark_failed(import_progress.id, &error.to_string())
                         .await;
-                    return Err(error.into());
+                    stats.record_error(format!("report {} failed: {}", day_string, error));
+                    return Err(error);
                 }
             };
 
@@ -117,10 +174,10 @@ impl CopilotMetricsHandler {
                     let mut records = Vec::new();
 
                     for download_link in report.download_links {
+                        stats.api_calls += 1;
                         let mut partial_records = match self
-                            .context
-                            .copilot_usage_metrics_client
-                            .download_users_usage_report(&download_link)
+                            .download_report(&download_link)
+                            .instrument(day_span.clone())
                             .await
                         {
                             Ok(partial_records) => partial_records,
@@ -128,18 +185,27 @@ impl CopilotMetricsHandler {
                                 let _ = import_progress_handler
                                     .mark_failed(import_progress.id, &error.to_string())
                                     .await;
-                                return Err(error.into());
+                                stats.record_error(format!(
+                                    "download {} failed: {}",
+                                    day_string, error
+                                ));
+                                return Err(error);
                             }
                         };
 
                         records.append(&mut partial_records);
                     }
 
-                    match self
+                    stats.items_fetched += records.len() as i64;
+                    let write_started_at = Instant::now();
+                    let result = self
                         .persist_org_daily_metrics(org_slug, current_day, &records)
-                        .await
-                    {
+                        .instrument(day_span.clone())
+                        .await;
+                    metrics::observe_db_write("copilot_user_metrics", write_started_at.elapsed());
+                    match result {
                         Ok(persisted_records) => {
+                            stats.items_persisted += persisted_records as i64;
                             summary.records_persisted +=
                                 i64::try_from(persisted_records).map_err(|_| {
                                     CopilotMetricsError::InvalidState(
@@ -151,21 +217,25 @@ impl CopilotMetricsHandler {
                             let _ = import_progress_handler
                                 .mark_failed(import_progress.id, &error.to_string())
                                 .await;
+                            stats.items_failed += records.len() as i64;
+                            stats.record_error(format!("persist {} failed: {}", day_string, error));
                             return Err(error);
                         }
                     }
                 }
                 None if current_day == end_day => {
-                    println!(
-                        "No report content available yet for org={}, day={}; leaving cursor unchanged.",
-                        org_slug, day_string
+                    info!(
+                        org = org_slug,
+                        day = %day_string,
+                        "No report content available yet, leaving cursor unchanged"
                     );
                     break;
                 }
                 None => {
-                    println!(
-                        "No report content available for org={}, day={}; advancing cursor.",
-                        org_slug, day_string
+                    info!(
+                        org = org_slug,
+                        day = %day_string,
+                        "No report content available, advancing cursor"
                     );
                 }
             }
@@ -174,6 +244,7 @@ impl CopilotMetricsHandler {
                 .update_progress(import_progress.id, Some(&day_string), 1)
                 .await?;
             summary.days_advanced += 1;
+            metrics::count_copilot_day(org_slug);
             summary.last_completed_report_day = Some(current_day);
             current_day = next_day(current_day)?;
         }
@@ -254,7 +325,7 @@ impl CopilotMetricsHandler {
         let totals_by_cli = serde_json::to_value(&record.totals_by_cli)?;
         let raw_payload = serde_json::to_value(record)?;
 
-        Ok(sqlx::query_scalar(
+        Ok(sqlx::query_scalar(&self.context.store.sql(
             r#"
             INSERT INTO engineering_metrics.copilot_user_daily_metrics (
                 org_slug,
@@ -305,7 +376,7 @@ impl CopilotMetricsHandler {
                 updated_at = NOW()
             RETURNING id
             "#,
-        )
+        ))
         .bind(org_slug)
         .bind(report_day)
         .bind(record.user_id)
@@ -343,7 +414,7 @@ impl CopilotMetricsHandler {
             "engineering_metrics.copilot_user_daily_model_feature_metrics",
         ] {
             let delete_query = format!("DELETE FROM {} WHERE user_metric_id = $1", table_name);
-            sqlx::query(&delete_query)
+            sqlx::query(&self.context.store.sql(&delete_query))
                 .bind(user_metric_id)
                 .execute(&mut **transaction)
                 .await?;
@@ -359,7 +430,7 @@ impl CopilotMetricsHandler {
         metrics: &[CopilotFeatureMetrics],
     ) -> Result<(), CopilotMetricsError> {
         for metric in metrics {
-            sqlx::query(
+            sqlx::query(&self.context.store.sql(
                 r#"
                 INSERT INTO engineering_metrics.copilot_user_daily_feature_metrics (
                     user_metric_id,
@@ -374,7 +445,7 @@ impl CopilotMetricsHandler {
                 )
                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
                 "#,
-            )
+            ))
             .bind(user_metric_id)
             .bind(&metric.feature)
             .bind(metric.user_initiated_interaction_count)
@@ -398,7 +469,7 @@ impl CopilotMetricsHandler {
         metrics: &[CopilotIdeMetrics],
     ) -> Result<(), CopilotMetricsError> {
         for metric in metrics {
-            sqlx::query(
+            sqlx::query(&self.context.store.sql(
                 r#"
                 INSERT INTO engineering_metrics.copilot_user_daily_ide_metrics (
                     user_metric_id,
@@ -415,7 +486,7 @@ impl CopilotMetricsHandler {
                 )
                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
                 "#,
-            )
+            ))
             .bind(user_metric_id)
             .bind(&metric.ide)
             .bind(metric.user_initiated_interaction_count)
@@ -441,7 +512,7 @@ impl CopilotMetricsHandler {
         metrics: &[CopilotLanguageFeatureMetrics],
     ) -> Result<(), CopilotMetricsError> {
         for metric in metrics {
-            sqlx::query(
+            sqlx::query(&self.context.store.sql(
                 r#"
                 INSERT INTO engineering_metrics.copilot_user_daily_language_feature_metrics (
                     user_metric_id,
@@ -456,7 +527,7 @@ impl CopilotMetricsHandler {
                 )
                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
                 "#,
-            )
+            ))
             .bind(user_metric_id)
             .bind(&metric.language)
             .bind(&metric.feature)
@@ -480,7 +551,7 @@ impl CopilotMetricsHandler {
         metrics: &[CopilotLanguageModelMetrics],
     ) -> Result<(), CopilotMetricsError> {
         for metric in metrics {
-            sqlx::query(
+            sqlx::query(&self.context.store.sql(
                 r#"
                 INSERT INTO engineering_metrics.copilot_user_daily_language_model_metrics (
                     user_metric_id,
@@ -495,7 +566,7 @@ impl CopilotMetricsHandler {
                 )
                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
                 "#,
-            )
+            ))
             .bind(user_metric_id)
             .bind(&metric.language)
             .bind(&metric.model)
@@ -519,7 +590,7 @@ impl CopilotMetricsHandler {
         metrics: &[CopilotModelFeatureMetrics],
     ) -> Result<(), CopilotMetricsError> {
         for metric in metrics {
-            sqlx::query(
+            sqlx::query(&self.context.store.sql(
                 r#"
                 INSERT INTO engineering_metrics.copilot_user_daily_model_feature_metrics (
                     user_metric_id,
@@ -535,7 +606,7 @@ impl CopilotMetricsHandler {
                 )
                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
                 "#,
-            )
+            ))
             .bind(user_metric_id)
             .bind(&metric.model)
             .bind(&metric.feature)
@@ -553,7 +624,8 @@ impl CopilotMetricsHandler {
         Ok(())
     }
 
-    fn import_progress_group_key(org_slug: &str) -> String {
+    /// Key under which the imports of an org are tracked in `import_progress`
+    pub fn import_progress_group_key(org_slug: &str) -> String {
         format!("github_org:{}", org_slug)
     }
 }
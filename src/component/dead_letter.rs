@@ -0,0 +1,260 @@
+use serde_json::Value;
+use sqlx::Row;
+use std::str::FromStr;
+use thiserror::Error;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum DeadLetterError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Keeps merge requests that failed to import so they can be retried on later runs
+/// instead of being lost once the watermark moves past them.
+#[derive(Debug, Clone)]
+pub struct DeadLetterHandler {
+    pub store: Store,
+    /// Number of failed attempts after which a dead letter is parked and no longer retried automatically
+    pub max_attempts: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub id: Uuid,
+    pub mr_id: String,
+    pub group_full_path: String,
+    pub stage: DeadLetterStage,
+    pub status: DeadLetterStatus,
+    pub error_message: String,
+    pub attempts: i32,
+    pub payload: Value,
+    pub first_seen_at: OffsetDateTime,
+    pub last_seen_at: OffsetDateTime,
+}
+
+/// The step of the merge request import that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadLetterStage {
+    Fetch,
+    Ai,
+    Persist,
+}
+
+impl DeadLetterStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadLetterStage::Fetch => "fetch",
+            DeadLetterStage::Ai => "ai",
+            DeadLetterStage::Persist => "persist",
+        }
+    }
+}
+
+impl FromStr for DeadLetterStage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fetch" => Ok(DeadLetterStage::Fetch),
+            "ai" => Ok(DeadLetterStage::Ai),
+            "persist" => Ok(DeadLetterStage::Persist),
+            _ => Err(format!("Unknown dead letter stage: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadLetterStatus {
+    /// Retried at the start of every run
+    Pending,
+    /// Exceeded `max_attempts`; only retried again after being requeued
+    Parked,
+}
+
+impl DeadLetterStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadLetterStatus::Pending => "pending",
+            DeadLetterStatus::Parked => "parked",
+        }
+    }
+}
+
+impl FromStr for DeadLetterStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(DeadLetterStatus::Pending),
+            "parked" => Ok(DeadLetterStatus::Parked),
+            _ => Err(format!("Unknown dead letter status: {}", s)),
+        }
+    }
+}
+
+impl DeadLetterHandler {
+    /// Record a failed attempt for a merge request, creating the dead letter on first failure
+    pub async fn record_failure(
+        &self,
+        mr_id: &str,
+        group_full_path: &str,
+        stage: DeadLetterStage,
+        error_message: &str,
+        payload: &Value,
+    ) -> Result<(), DeadLetterError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let now = OffsetDateTime::now_utc();
+
+        sqlx::query(
+            &self.store.sql(r#"
+            INSERT INTO engineering_metrics.merge_request_dead_letters
+                (id, mr_id, group_full_path, stage, status, error_message, attempts, payload, first_seen_at, last_seen_at)
+            VALUES ($1, $2, $3, $4, CASE WHEN 1 >= $8 THEN 'parked' ELSE 'pending' END, $5, 1, $6, $7, $7)
+            ON CONFLICT (mr_id) DO
+            UPDATE SET
+                group_full_path = EXCLUDED.group_full_path,
+                stage = EXCLUDED.stage,
+                status = CASE
+                    WHEN engineering_metrics.merge_request_dead_letters.attempts + 1 >= $8 THEN 'parked'
+                    ELSE 'pending'
+                END,
+                error_message = EXCLUDED.error_message,
+                attempts = engineering_metrics.merge_request_dead_letters.attempts + 1,
+                payload = EXCLUDED.payload,
+                last_seen_at = EXCLUDED.last_seen_at
+            "#),
+        )
+        .bind(Uuid::now_v7())
+        .bind(mr_id)
+        .bind(group_full_path)
+        .bind(stage.as_str())
+        .bind(error_message)
+        .bind(payload)
+        .bind(now)
+        .bind(self.max_attempts)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetch the dead letters of a group that should be retried on this run
+    pub async fn fetch_pending(
+        &self,
+        group_full_path: &str,
+    ) -> Result<Vec<DeadLetter>, DeadLetterError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT id, mr_id, group_full_path, stage, status, error_message, attempts, payload,
+                   first_seen_at, last_seen_at
+            FROM engineering_metrics.merge_request_dead_letters
+            WHERE group_full_path = $1 AND status = 'pending'
+            ORDER BY first_seen_at
+            "#,
+        ))
+        .bind(group_full_path)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.iter().map(dead_letter_from_row).collect())
+    }
+
+    /// List dead letters, optionally restricted to one status
+    pub async fn list(
+        &self,
+        status: Option<DeadLetterStatus>,
+    ) -> Result<Vec<DeadLetter>, DeadLetterError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT id, mr_id, group_full_path, stage, status, error_message, attempts, payload,
+                   first_seen_at, last_seen_at
+            FROM engineering_metrics.merge_request_dead_letters
+            WHERE $1::VARCHAR IS NULL OR status = $1
+            ORDER BY group_full_path, first_seen_at
+            "#,
+        ))
+        .bind(status.map(|status| status.as_str()))
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.iter().map(dead_letter_from_row).collect())
+    }
+
+    /// Put dead letters back into the retry queue with a fresh attempt budget.
+    ///
+    /// Requeues a single merge request when `mr_id` is given, otherwise every parked dead letter.
+    pub async fn requeue(&self, mr_id: Option<&str>) -> Result<u64, DeadLetterError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let result = sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.merge_request_dead_letters
+            SET status = 'pending',
+                attempts = 0
+            WHERE ($1::VARCHAR IS NULL AND status = 'parked') OR mr_id = $1
+            "#,
+        ))
+        .bind(mr_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Remove the dead letter of a merge request that has since been imported successfully
+    pub async fn resolve(&self, mr_id: &str) -> Result<(), DeadLetterError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        sqlx::query(&self.store.sql(
+            r#"
+            DELETE FROM engineering_metrics.merge_request_dead_letters
+            WHERE mr_id = $1
+            "#,
+        ))
+        .bind(mr_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove the dead letters of merge requests that have now been imported, in one round trip
+    pub async fn resolve_all(&self, mr_ids: &[String]) -> Result<(), DeadLetterError> {
+        if mr_ids.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.store.conn_pool.acquire().await?;
+        sqlx::query(&self.store.sql(
+            r#"
+            DELETE FROM engineering_metrics.merge_request_dead_letters
+            WHERE mr_id = ANY($1)
+            "#,
+        ))
+        .bind(mr_ids)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn dead_letter_from_row(row: &sqlx::postgres::PgRow) -> DeadLetter {
+    let stage_str: String = row.get(3);
+    let status_str: String = row.get(4);
+    DeadLetter {
+        id: row.get(0),
+        mr_id: row.get(1),
+        group_full_path: row.get(2),
+        stage: DeadLetterStage::from_str(&stage_str).unwrap_or(DeadLetterStage::Persist),
+        status: DeadLetterStatus::from_str(&status_str).unwrap_or(DeadLetterStatus::Parked),
+        error_message: row.get(5),
+        attempts: row.get(6),
+        payload: row.get(7),
+        first_seen_at: row.get(8),
+        last_seen_at: row.get(9),
+    }
+}
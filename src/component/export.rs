@@ -0,0 +1,875 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Date32Array, Float64Array, Int64Array, RecordBatch, StringArray,
+    TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use futures::TryStreamExt;
+use hmac::{Hmac, Mac};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use sha2::Sha256;
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::{Date, OffsetDateTime};
+
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("Failed to write {path}: {source}")]
+    WriteError {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("CSV error: {0}")]
+    CsvError(#[from] csv::Error),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Parquet error: {0}")]
+    ParquetError(#[from] parquet::errors::ParquetError),
+    #[error("Arrow error: {0}")]
+    ArrowError(#[from] arrow_schema::ArrowError),
+    #[error("Failed to format a timestamp: {0}")]
+    FormatError(#[from] time::error::Format),
+    #[error("Invalid pseudonymization key: {0}")]
+    InvalidKey(String),
+}
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" => Ok(ExportFormat::Jsonl),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
+    }
+}
+
+/// Type of an exported column; every column is nullable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Text,
+    Int,
+    Float,
+    Bool,
+    /// UTC timestamp; RFC 3339 in CSV and JSON Lines, microseconds in Parquet
+    Timestamp,
+    /// `YYYY-MM-DD` in CSV and JSON Lines, `DATE` in Parquet
+    Date,
+}
+
+/// How a column is treated when usernames are pseudonymized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Privacy {
+    Public,
+    /// A GitLab username or GitHub login
+    Username,
+    /// A JSON array of usernames
+    Usernames,
+    /// A numeric user id, left empty as it cannot be pseudonymized without changing its type
+    UserId,
+    /// A flow metric dimension value, which is a username in the `author` dimension
+    DimensionValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column {
+    pub name: &'static str,
+    pub kind: ColumnKind,
+    pub privacy: Privacy,
+}
+
+const fn column(name: &'static str, kind: ColumnKind) -> Column {
+    Column {
+        name,
+        kind,
+        privacy: Privacy::Public,
+    }
+}
+
+const fn private(name: &'static str, kind: ColumnKind, privacy: Privacy) -> Column {
+    Column {
+        name,
+        kind,
+        privacy,
+    }
+}
+
+use ColumnKind::{Bool, Date as Day, Float, Int, Text, Timestamp};
+
+const MERGE_REQUEST_COLUMNS: &[Column] = &[
+    column("mr_id", Text),
+    column("mr_iid", Text),
+    column("project_id", Text),
+    column("project_full_path", Text),
+    column("group_full_path", Text),
+    column("title", Text),
+    column("web_url", Text),
+    column("created_at", Timestamp),
+    column("updated_at", Timestamp),
+    column("merged_at", Timestamp),
+    column("first_approved_at", Timestamp),
+    private("created_by", Text, Privacy::Username),
+    private("merged_by", Text, Privacy::Username),
+    column("approved", Bool),
+    private("approved_by", Text, Privacy::Usernames),
+    column("labels", Text),
+    column("additions", Int),
+    column("deletions", Int),
+    column("file_count", Int),
+    column("ai_title", Text),
+    column("ai_summary", Text),
+    column("ai_model", Text),
+    column("ai_category", Text),
+];
+
+const PROJECT_COLUMNS: &[Column] = &[
+    column("project_id", Text),
+    column("name", Text),
+    column("path", Text),
+    column("full_path", Text),
+    column("web_url", Text),
+    column("group_full_path", Text),
+    column("topics", Text),
+    column("last_synced_at", Timestamp),
+    column("removed_at", Timestamp),
+];
+
+const COPILOT_DAILY_COLUMNS: &[Column] = &[
+    column("id", Text),
+    column("org_slug", Text),
+    column("report_day", Day),
+    private("user_id", Int, Privacy::UserId),
+    private("user_login", Text, Privacy::Username),
+    column("organization_id", Text),
+    column("enterprise_id", Text),
+    column("user_initiated_interaction_count", Int),
+    column("code_generation_activity_count", Int),
+    column("code_acceptance_activity_count", Int),
+    column("loc_suggested_to_add_sum", Int),
+    column("loc_suggested_to_delete_sum", Int),
+    column("loc_added_sum", Int),
+    column("loc_deleted_sum", Int),
+    column("used_agent", Bool),
+    column("used_chat", Bool),
+    column("used_cli", Bool),
+    column("used_copilot_coding_agent", Bool),
+    column("used_copilot_cloud_agent", Bool),
+];
+
+const COPILOT_FEATURE_COLUMNS: &[Column] = &[
+    column("user_metric_id", Text),
+    column("org_slug", Text),
+    column("report_day", Day),
+    column("feature", Text),
+    column("user_initiated_interaction_count", Int),
+    column("code_generation_activity_count", Int),
+    column("code_acceptance_activity_count", Int),
+    column("loc_suggested_to_add_sum", Int),
+    column("loc_suggested_to_delete_sum", Int),
+    column("loc_added_sum", Int),
+    column("loc_deleted_sum", Int),
+];
+
+const COPILOT_IDE_COLUMNS: &[Column] = &[
+    column("user_metric_id", Text),
+    column("org_slug", Text),
+    column("report_day", Day),
+    column("ide", Text),
+    column("user_initiated_interaction_count", Int),
+    column("code_generation_activity_count", Int),
+    column("code_acceptance_activity_count", Int),
+    column("loc_suggested_to_add_sum", Int),
+    column("loc_suggested_to_delete_sum", Int),
+    column("loc_added_sum", Int),
+    column("loc_deleted_sum", Int),
+    column("last_known_plugin_version", Text),
+    column("last_known_ide_version", Text),
+];
+
+const COPILOT_LANGUAGE_FEATURE_COLUMNS: &[Column] = &[
+    column("user_metric_id", Text),
+    column("org_slug", Text),
+    column("report_day", Day),
+    column("language", Text),
+    column("feature", Text),
+    column("code_generation_activity_count", Int),
+    column("code_acceptance_activity_count", Int),
+    column("loc_suggested_to_add_sum", Int),
+    column("loc_suggested_to_delete_sum", Int),
+    column("loc_added_sum", Int),
+    column("loc_deleted_sum", Int),
+];
+
+const COPILOT_LANGUAGE_MODEL_COLUMNS: &[Column] = &[
+    column("user_metric_id", Text),
+    column("org_slug", Text),
+    column("report_day", Day),
+    column("language", Text),
+    column("model", Text),
+    column("code_generation_activity_count", Int),
+    column("code_acceptance_activity_count", Int),
+    column("loc_suggested_to_add_sum", Int),
+    column("loc_suggested_to_delete_sum", Int),
+    column("loc_added_sum", Int),
+    column("loc_deleted_sum", Int),
+];
+
+const COPILOT_MODEL_FEATURE_COLUMNS: &[Column] = &[
+    column("user_metric_id", Text),
+    column("org_slug", Text),
+    column("report_day", Day),
+    column("model", Text),
+    column("feature", Text),
+    column("user_initiated_interaction_count", Int),
+    column("code_generation_activity_count", Int),
+    column("code_acceptance_activity_count", Int),
+    column("loc_suggested_to_add_sum", Int),
+    column("loc_suggested_to_delete_sum", Int),
+    column("loc_added_sum", Int),
+    column("loc_deleted_sum", Int),
+];
+
+const FLOW_METRIC_COLUMNS: &[Column] = &[
+    column("window_days", Int),
+    column("window_start", Day),
+    column("window_end", Day),
+    column("dimension", Text),
+    private("dimension_value", Text, Privacy::DimensionValue),
+    column("metric", Text),
+    column("sample_count", Int),
+    column("mean", Float),
+    column("p50", Float),
+    column("p75", Float),
+    column("p90", Float),
+    column("p95", Float),
+    column("computed_at", Timestamp),
+];
+
+/// Copilot breakdowns: table and the columns that key its rows besides the user and day
+const COPILOT_BREAKDOWNS: [(&str, &str); 5] = [
+    ("copilot_user_daily_feature_metrics", "feature"),
+    ("copilot_user_daily_ide_metrics", "ide"),
+    (
+        "copilot_user_daily_language_feature_metrics",
+        "language, feature",
+    ),
+    (
+        "copilot_user_daily_language_model_metrics",
+        "language, model",
+    ),
+    ("copilot_user_daily_model_feature_metrics", "model, feature"),
+];
+
+/// A table that can be exported, with a column schema that only changes by adding columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dataset {
+    MergeRequests,
+    Projects,
+    CopilotDailyMetrics,
+    CopilotFeatureMetrics,
+    CopilotIdeMetrics,
+    CopilotLanguageFeatureMetrics,
+    CopilotLanguageModelMetrics,
+    CopilotModelFeatureMetrics,
+    FlowMetrics,
+}
+
+impl Dataset {
+    pub const ALL: [Dataset; 9] = [
+        Dataset::MergeRequests,
+        Dataset::Projects,
+        Dataset::CopilotDailyMetrics,
+        Dataset::CopilotFeatureMetrics,
+        Dataset::CopilotIdeMetrics,
+        Dataset::CopilotLanguageFeatureMetrics,
+        Dataset::CopilotLanguageModelMetrics,
+        Dataset::CopilotModelFeatureMetrics,
+        Dataset::FlowMetrics,
+    ];
+
+    /// Name of the dataset and of its export file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dataset::MergeRequests => "merge_requests",
+            Dataset::Projects => "projects",
+            Dataset::CopilotDailyMetrics => "copilot_user_daily_metrics",
+            Dataset::CopilotFeatureMetrics => COPILOT_BREAKDOWNS[0].0,
+            Dataset::CopilotIdeMetrics => COPILOT_BREAKDOWNS[1].0,
+            Dataset::CopilotLanguageFeatureMetrics => COPILOT_BREAKDOWNS[2].0,
+            Dataset::CopilotLanguageModelMetrics => COPILOT_BREAKDOWNS[3].0,
+            Dataset::CopilotModelFeatureMetrics => COPILOT_BREAKDOWNS[4].0,
+            Dataset::FlowMetrics => "flow_metrics",
+        }
+    }
+
+    pub fn columns(&self) -> &'static [Column] {
+        match self {
+            Dataset::MergeRequests => MERGE_REQUEST_COLUMNS,
+            Dataset::Projects => PROJECT_COLUMNS,
+            Dataset::CopilotDailyMetrics => COPILOT_DAILY_COLUMNS,
+            Dataset::CopilotFeatureMetrics => COPILOT_FEATURE_COLUMNS,
+            Dataset::CopilotIdeMetrics => COPILOT_IDE_COLUMNS,
+            Dataset::CopilotLanguageFeatureMetrics => COPILOT_LANGUAGE_FEATURE_COLUMNS,
+            Dataset::CopilotLanguageModelMetrics => COPILOT_LANGUAGE_MODEL_COLUMNS,
+            Dataset::CopilotModelFeatureMetrics => COPILOT_MODEL_FEATURE_COLUMNS,
+            Dataset::FlowMetrics => FLOW_METRIC_COLUMNS,
+        }
+    }
+
+    /// Datasets selected by a name, `copilot` for the daily metrics with every breakdown, or `all`
+    pub fn select(name: &str) -> Result<Vec<Dataset>, String> {
+        match name {
+            "all" => Ok(Dataset::ALL.to_vec()),
+            "copilot" => Ok(Dataset::ALL
+                .into_iter()
+                .filter(|dataset| dataset.name().starts_with("copilot_"))
+                .collect()),
+            _ => Dataset::ALL
+                .into_iter()
+                .find(|dataset| dataset.name() == name)
+                .map(|dataset| vec![dataset])
+                .ok_or_else(|| {
+                    let names: Vec<&str> = Dataset::ALL.iter().map(Dataset::name).collect();
+                    format!(
+                        "Unknown dataset {}; expected all, copilot or one of {}",
+                        name,
+                        names.join(", ")
+                    )
+                }),
+        }
+    }
+
+    /// Query returning the columns in order, filtered by `$1` first day, `$2` day before which it
+    /// stops, `$3` groups and `$4` orgs; NULL or empty filters match everything
+    fn query(&self) -> String {
+        match self {
+            Dataset::MergeRequests => r#"
+                SELECT
+                    mr.mr_id, mr.mr_iid, mr.project_id, COALESCE(p.p_full_path, mr.project_path),
+                    p.group_full_path, mr.mr_title, mr.mr_web_url, mr.created_at, mr.updated_at,
+                    mr.merged_at, mr.first_approved_at, mr.created_by, mr.merged_by, mr.approved,
+                    NULLIF(mr.approved_by, 'null'::jsonb)::text,
+                    NULLIF(mr.labels, 'null'::jsonb)::text,
+                    (mr.diff_stats_summary ->> 'additions')::bigint,
+                    (mr.diff_stats_summary ->> 'deletions')::bigint,
+                    (mr.diff_stats_summary ->> 'file_count')::bigint,
+                    mr.mr_ai_title, mr.mr_ai_summary, mr.mr_ai_model, mr.mr_ai_category
+                FROM engineering_metrics.merge_requests mr
+                LEFT JOIN engineering_metrics.projects p
+                    ON p.p_id = 'gid://gitlab/Project/' || mr.project_id
+                WHERE ($1::date IS NULL OR mr.merged_at >= $1::date::timestamp AT TIME ZONE 'UTC')
+                  AND ($2::date IS NULL OR mr.merged_at < $2::date::timestamp AT TIME ZONE 'UTC')
+                  AND (cardinality($3::varchar[]) = 0 OR p.group_full_path = ANY($3))
+                ORDER BY mr.merged_at NULLS LAST, mr.mr_id
+                "#
+            .to_string(),
+            Dataset::Projects => r#"
+                SELECT
+                    p_id, p_name, p_path, p_full_path, p_web_url, group_full_path,
+                    NULLIF(topics, 'null'::jsonb)::text, last_synced_at, removed_at
+                FROM engineering_metrics.projects
+                WHERE cardinality($3::varchar[]) = 0 OR group_full_path = ANY($3)
+                ORDER BY p_full_path
+                "#
+            .to_string(),
+            Dataset::CopilotDailyMetrics => r#"
+                SELECT
+                    id::text, org_slug, report_day, user_id, user_login, organization_id,
+                    enterprise_id, user_initiated_interaction_count, code_generation_activity_count,
+                    code_acceptance_activity_count, loc_suggested_to_add_sum,
+                    loc_suggested_to_delete_sum, loc_added_sum, loc_deleted_sum, used_agent,
+                    used_chat, used_cli, used_copilot_coding_agent, used_copilot_cloud_agent
+                FROM engineering_metrics.copilot_user_daily_metrics
+                WHERE ($1::date IS NULL OR report_day >= $1)
+                  AND ($2::date IS NULL OR report_day < $2)
+                  AND (cardinality($4::varchar[]) = 0 OR org_slug = ANY($4))
+                ORDER BY report_day, org_slug, user_id
+                "#
+            .to_string(),
+            Dataset::FlowMetrics => r#"
+                SELECT
+                    window_days::bigint, window_start, window_end, dimension, dimension_value,
+                    metric, sample_count, mean, p50, p75, p90, p95, computed_at
+                FROM engineering_metrics.flow_metrics
+                WHERE ($1::date IS NULL OR window_end >= $1)
+                  AND ($2::date IS NULL OR window_end < $2)
+                ORDER BY window_end, window_days, dimension, dimension_value, metric
+                "#
+            .to_string(),
+            _ => {
+                let (table, keys) = COPILOT_BREAKDOWNS
+                    .into_iter()
+                    .find(|(table, _)| *table == self.name())
+                    .unwrap_or(COPILOT_BREAKDOWNS[0]);
+                let measures: Vec<String> = self.columns()[3..]
+                    .iter()
+                    .map(|column| match column.name {
+                        "last_known_plugin_version" | "last_known_ide_version" => {
+                            format!("NULLIF(b.{0}, 'null'::jsonb)::text", column.name)
+                        }
+                        name => format!("b.{}", name),
+                    })
+                    .collect();
+                format!(
+                    r#"
+                    SELECT b.user_metric_id::text, m.org_slug, m.report_day, {measures}
+                    FROM engineering_metrics.{table} b
+                    JOIN engineering_metrics.copilot_user_daily_metrics m ON m.id = b.user_metric_id
+                    WHERE ($1::date IS NULL OR m.report_day >= $1)
+                      AND ($2::date IS NULL OR m.report_day < $2)
+                      AND (cardinality($4::varchar[]) = 0 OR m.org_slug = ANY($4))
+                    ORDER BY m.report_day, m.org_slug, m.user_id, {keys}
+                    "#,
+                    measures = measures.join(", "),
+                    table = table,
+                    keys = keys,
+                )
+            }
+        }
+    }
+}
+
+/// Restricts an export; filters that do not apply to a dataset are ignored
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// First merge day, Copilot report day or flow metric window end
+    pub since: Option<Date>,
+    /// Day the export stops before
+    pub until: Option<Date>,
+    /// GitLab groups of merge requests and projects
+    pub groups: Vec<String>,
+    /// Copilot orgs
+    pub orgs: Vec<String>,
+}
+
+/// A value of an exported cell
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportValue {
+    Null,
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Timestamp(OffsetDateTime),
+    Date(Date),
+}
+
+impl ExportValue {
+    fn from_row(row: &PgRow, index: usize, kind: ColumnKind) -> Result<Self, sqlx::Error> {
+        Ok(match kind {
+            Text => row
+                .try_get::<Option<String>, _>(index)?
+                .map_or(ExportValue::Null, ExportValue::Text),
+            Int => row
+                .try_get::<Option<i64>, _>(index)?
+                .map_or(ExportValue::Null, ExportValue::Int),
+            Float => row
+                .try_get::<Option<f64>, _>(index)?
+                .map_or(ExportValue::Null, ExportValue::Float),
+            Bool => row
+                .try_get::<Option<bool>, _>(index)?
+                .map_or(ExportValue::Null, ExportValue::Bool),
+            Timestamp => row
+                .try_get::<Option<OffsetDateTime>, _>(index)?
+                .map_or(ExportValue::Null, ExportValue::Timestamp),
+            Day => row
+                .try_get::<Option<Date>, _>(index)?
+                .map_or(ExportValue::Null, ExportValue::Date),
+        })
+    }
+
+    /// Text of the value as written to CSV; NULL is an empty field
+    fn to_text(&self) -> Result<String, ExportError> {
+        Ok(match self {
+            ExportValue::Null => String::new(),
+            ExportValue::Text(value) => value.clone(),
+            ExportValue::Int(value) => value.to_string(),
+            ExportValue::Float(value) => value.to_string(),
+            ExportValue::Bool(value) => value.to_string(),
+            ExportValue::Timestamp(value) => value.format(&Rfc3339)?,
+            ExportValue::Date(value) => value.to_string(),
+        })
+    }
+
+    fn to_json(&self) -> Result<serde_json::Value, ExportError> {
+        Ok(match self {
+            ExportValue::Null => serde_json::Value::Null,
+            ExportValue::Text(value) => value.clone().into(),
+            ExportValue::Int(value) => (*value).into(),
+            ExportValue::Float(value) => (*value).into(),
+            ExportValue::Bool(value) => (*value).into(),
+            ExportValue::Timestamp(_) | ExportValue::Date(_) => self.to_text()?.into(),
+        })
+    }
+}
+
+/// Replaces usernames by stable pseudonyms: the same username and key always give the same
+/// pseudonym, so exports can still be joined, but it cannot be reversed without the key
+#[derive(Clone)]
+pub struct Pseudonymizer {
+    key: Vec<u8>,
+}
+
+impl Pseudonymizer {
+    pub fn new(key: &str) -> Self {
+        Pseudonymizer {
+            key: key.as_bytes().to_vec(),
+        }
+    }
+
+    /// `user-` and 16 hex digits of the HMAC-SHA256 of the lowercased username
+    pub fn pseudonym(&self, username: &str) -> Result<String, ExportError> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key)
+            .map_err(|e| ExportError::InvalidKey(e.to_string()))?;
+        mac.update(username.to_lowercase().as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let hex: String = digest[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        Ok(format!("user-{}", hex))
+    }
+
+    /// Pseudonymize the private cells of a row of `columns`
+    pub fn apply(&self, columns: &[Column], row: &mut [ExportValue]) -> Result<(), ExportError> {
+        let is_author = columns
+            .iter()
+            .position(|column| column.name == "dimension")
+            .and_then(|index| row.get(index))
+            == Some(&ExportValue::Text("author".to_string()));
+
+        for (column, value) in columns.iter().zip(row.iter_mut()) {
+            let pseudonymize = match column.privacy {
+                Privacy::Public => false,
+                Privacy::DimensionValue => is_author,
+                Privacy::UserId => {
+                    *value = ExportValue::Null;
+                    false
+                }
+                Privacy::Username | Privacy::Usernames => true,
+            };
+            let ExportValue::Text(text) = value else {
+                continue;
+            };
+            if !pseudonymize {
+                continue;
+            }
+            *text = if column.privacy == Privacy::Usernames {
+                let usernames: Vec<String> = serde_json::from_str(text)?;
+                let pseudonyms = usernames
+                    .iter()
+                    .map(|username| self.pseudonym(username))
+                    .collect::<Result<Vec<String>, ExportError>>()?;
+                serde_json::to_string(&pseudonyms)?
+            } else {
+                self.pseudonym(text)?
+            };
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the rows of one dataset to a file in one format
+pub enum ExportWriter {
+    Csv(Box<csv::Writer<File>>),
+    Jsonl(BufWriter<File>, &'static [Column]),
+    Parquet(Box<ArrowWriter<File>>, Arc<Schema>, &'static [Column]),
+}
+
+impl ExportWriter {
+    pub fn create(
+        path: &Path,
+        format: ExportFormat,
+        columns: &'static [Column],
+    ) -> Result<Self, ExportError> {
+        let file = File::create(path).map_err(|source| ExportError::WriteError {
+            path: path.display().to_string(),
+            source,
+        })?;
+
+        Ok(match format {
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(file);
+                writer.write_record(columns.iter().map(|column| column.name))?;
+                ExportWriter::Csv(Box::new(writer))
+            }
+            ExportFormat::Jsonl => ExportWriter::Jsonl(BufWriter::new(file), columns),
+            ExportFormat::Parquet => {
+                let schema = Arc::new(arrow_schema(columns));
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+                ExportWriter::Parquet(Box::new(writer), schema, columns)
+            }
+        })
+    }
+
+    pub fn write(&mut self, rows: &[Vec<ExportValue>]) -> Result<(), ExportError> {
+        match self {
+            ExportWriter::Csv(writer) => {
+                for row in rows {
+                    let fields = row
+                        .iter()
+                        .map(ExportValue::to_text)
+                        .collect::<Result<Vec<String>, ExportError>>()?;
+                    writer.write_record(&fields)?;
+                }
+            }
+            ExportWriter::Jsonl(writer, columns) => {
+                for row in rows {
+                    // Built by hand so the keys keep the column order
+                    let mut line = String::from("{");
+                    for (index, (column, value)) in columns.iter().zip(row).enumerate() {
+                        if index > 0 {
+                            line.push(',');
+                        }
+                        line.push_str(&serde_json::to_string(column.name)?);
+                        line.push(':');
+                        line.push_str(&serde_json::to_string(&value.to_json()?)?);
+                    }
+                    line.push('}');
+                    writeln!(writer, "{}", line).map_err(|source| ExportError::WriteError {
+                        path: "JSON Lines export".to_string(),
+                        source,
+                    })?;
+                }
+            }
+            ExportWriter::Parquet(writer, schema, columns) => {
+                if rows.is_empty() {
+                    return Ok(());
+                }
+                let arrays = columns
+                    .iter()
+                    .enumerate()
+                    .map(|(index, column)| arrow_array(column.kind, rows, index))
+                    .collect();
+                writer.write(&RecordBatch::try_new(schema.clone(), arrays)?)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), ExportError> {
+        match self {
+            ExportWriter::Csv(mut writer) => {
+                writer.flush().map_err(|source| ExportError::WriteError {
+                    path: "CSV export".to_string(),
+                    source,
+                })?
+            }
+            ExportWriter::Jsonl(mut writer, _) => {
+                writer.flush().map_err(|source| ExportError::WriteError {
+                    path: "JSON Lines export".to_string(),
+                    source,
+                })?
+            }
+            ExportWriter::Parquet(writer, _, _) => {
+                writer.close()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parquet schema of a dataset
+pub fn arrow_schema(columns: &[Column]) -> Schema {
+    Schema::new(
+        columns
+            .iter()
+            .map(|column| {
+                let data_type = match column.kind {
+                    Text => DataType::Utf8,
+                    Int => DataType::Int64,
+                    Float => DataType::Float64,
+                    Bool => DataType::Boolean,
+                    Timestamp => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                    Day => DataType::Date32,
+                };
+                Field::new(column.name, data_type, true)
+            })
+            .collect::<Vec<Field>>(),
+    )
+}
+
+/// Julian day number of 1970-01-01, the day Arrow `Date32` values count from
+const UNIX_EPOCH_JULIAN_DAY: i32 = 2_440_588;
+
+fn arrow_array(kind: ColumnKind, rows: &[Vec<ExportValue>], index: usize) -> ArrayRef {
+    let values = rows.iter().map(|row| row.get(index));
+    match kind {
+        Text => Arc::new(StringArray::from(
+            values
+                .map(|value| match value {
+                    Some(ExportValue::Text(text)) => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<Option<&str>>>(),
+        )),
+        Int => Arc::new(Int64Array::from(
+            values
+                .map(|value| match value {
+                    Some(ExportValue::Int(number)) => Some(*number),
+                    _ => None,
+                })
+                .collect::<Vec<Option<i64>>>(),
+        )),
+        Float => Arc::new(Float64Array::from(
+            values
+                .map(|value| match value {
+                    Some(ExportValue::Float(number)) => Some(*number),
+                    _ => None,
+                })
+                .collect::<Vec<Option<f64>>>(),
+        )),
+        Bool => Arc::new(BooleanArray::from(
+            values
+                .map(|value| match value {
+                    Some(ExportValue::Bool(flag)) => Some(*flag),
+                    _ => None,
+                })
+                .collect::<Vec<Option<bool>>>(),
+        )),
+        Timestamp => Arc::new(
+            TimestampMicrosecondArray::from(
+                values
+                    .map(|value| match value {
+                        Some(ExportValue::Timestamp(at)) => {
+                            Some((at.unix_timestamp_nanos() / 1_000) as i64)
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<Option<i64>>>(),
+            )
+            .with_timezone("UTC"),
+        ),
+        Day => Arc::new(Date32Array::from(
+            values
+                .map(|value| match value {
+                    Some(ExportValue::Date(day)) => {
+                        Some(day.to_julian_day() - UNIX_EPOCH_JULIAN_DAY)
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<Option<i32>>>(),
+        )),
+    }
+}
+
+/// A written export file
+#[derive(Debug, Clone)]
+pub struct ExportSummary {
+    pub dataset: Dataset,
+    pub path: PathBuf,
+    pub rows: u64,
+}
+
+/// Rows read and written at a time
+const BATCH_SIZE: usize = 10_000;
+
+#[derive(Clone)]
+pub struct ExportHandler {
+    pub store: Store,
+}
+
+impl ExportHandler {
+    /// Export one dataset to `<directory>/<dataset>.<extension>`. The file is written under a
+    /// temporary name and renamed once complete, so readers never see a partial export.
+    pub async fn export(
+        &self,
+        dataset: Dataset,
+        format: ExportFormat,
+        filter: &ExportFilter,
+        pseudonymizer: Option<&Pseudonymizer>,
+        directory: &Path,
+    ) -> Result<ExportSummary, ExportError> {
+        let path = directory.join(format!("{}.{}", dataset.name(), format.extension()));
+        let partial_path = directory.join(format!(
+            ".{}.{}.partial",
+            dataset.name(),
+            format.extension()
+        ));
+        let columns = dataset.columns();
+        let mut writer = ExportWriter::create(&partial_path, format, columns)?;
+
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let query = dataset.query();
+        let query = self.store.sql(&query);
+        let mut rows = sqlx::query(&query)
+            .bind(filter.since)
+            .bind(filter.until)
+            .bind(&filter.groups)
+            .bind(&filter.orgs)
+            .fetch(&mut *conn);
+
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut written = 0;
+        while let Some(row) = rows.try_next().await? {
+            let mut values = columns
+                .iter()
+                .enumerate()
+                .map(|(index, column)| ExportValue::from_row(&row, index, column.kind))
+                .collect::<Result<Vec<ExportValue>, sqlx::Error>>()?;
+            if let Some(pseudonymizer) = pseudonymizer {
+                pseudonymizer.apply(columns, &mut values)?;
+            }
+            batch.push(values);
+            if batch.len() == BATCH_SIZE {
+                writer.write(&batch)?;
+                written += batch.len() as u64;
+                batch.clear();
+            }
+        }
+        writer.write(&batch)?;
+        written += batch.len() as u64;
+        writer.finish()?;
+
+        std::fs::rename(&partial_path, &path).map_err(|source| ExportError::WriteError {
+            path: path.display().to_string(),
+            source,
+        })?;
+
+        Ok(ExportSummary {
+            dataset,
+            path,
+            rows: written,
+        })
+    }
+}
@@ -0,0 +1,183 @@
+use sqlx::Row;
+use thiserror::Error;
+use time::Date;
+
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum FlowMetricsError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("A window must cover at least one day, got {0}")]
+    InvalidWindow(i32),
+}
+
+/// Dimensions the flow metrics are broken down by; `all` covers every merged merge request
+pub const DIMENSIONS: [&str; 5] = ["all", "project", "group", "author", "label"];
+
+/// Distribution of one flow metric of the merge requests merged in a window.
+///
+/// `throughput` has no percentiles: its `sample_count` is the number of merged merge requests
+/// and its `mean` the merged merge requests per week.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowMetric {
+    pub window_days: i32,
+    pub window_start: Date,
+    pub window_end: Date,
+    pub dimension: String,
+    pub dimension_value: String,
+    pub metric: String,
+    pub sample_count: i64,
+    pub mean: Option<f64>,
+    pub p50: Option<f64>,
+    pub p75: Option<f64>,
+    pub p90: Option<f64>,
+    pub p95: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FlowMetricsHandler {
+    pub store: Store,
+}
+
+impl FlowMetricsHandler {
+    /// Compute the flow metrics of the merge requests merged in the `window_days` days before
+    /// `window_end` and replace the stored metrics of that window. Returns the rows written.
+    pub async fn compute(
+        &self,
+        window_days: i32,
+        window_end: Date,
+    ) -> Result<u64, FlowMetricsError> {
+        if window_days < 1 {
+            return Err(FlowMetricsError::InvalidWindow(window_days));
+        }
+        let mut transaction = self.store.conn_pool.begin().await?;
+
+        sqlx::query(&self.store.sql(
+            r#"
+            DELETE FROM engineering_metrics.flow_metrics
+            WHERE window_days = $1 AND window_end = $2
+            "#,
+        ))
+        .bind(window_days)
+        .bind(window_end)
+        .execute(&mut *transaction)
+        .await?;
+
+        let result = sqlx::query(&self.store.sql(
+            r#"
+            WITH flow AS (
+                SELECT *
+                FROM engineering_metrics.merge_request_flow
+                WHERE merged_at >= ($2::date - $1::integer)::timestamp AT TIME ZONE 'UTC'
+                  AND merged_at < $2::date::timestamp AT TIME ZONE 'UTC'
+            ),
+            samples AS (
+                SELECT
+                    d.dimension,
+                    d.dimension_value,
+                    f.lead_time_hours,
+                    f.time_to_first_approval_hours,
+                    f.approval_to_merge_hours,
+                    f.size_lines::double precision AS size_lines
+                FROM flow f
+                CROSS JOIN LATERAL (
+                    VALUES
+                        ('all', 'all'),
+                        ('project', f.project_full_path),
+                        ('group', f.group_full_path),
+                        ('author', f.author)
+                    UNION ALL
+                    SELECT 'label', label FROM jsonb_array_elements_text(f.labels) AS label
+                ) AS d (dimension, dimension_value)
+            ),
+            measurements AS (
+                SELECT s.dimension, s.dimension_value, m.metric, m.value
+                FROM samples s
+                CROSS JOIN LATERAL (
+                    VALUES
+                        ('lead_time_hours', s.lead_time_hours),
+                        ('time_to_first_approval_hours', s.time_to_first_approval_hours),
+                        ('approval_to_merge_hours', s.approval_to_merge_hours),
+                        ('size_lines', s.size_lines)
+                ) AS m (metric, value)
+                WHERE m.value IS NOT NULL
+            )
+            INSERT INTO engineering_metrics.flow_metrics (
+                window_days, window_start, window_end, dimension, dimension_value, metric,
+                sample_count, mean, p50, p75, p90, p95
+            )
+            SELECT
+                $1::integer, $2::date - $1::integer, $2::date, dimension, dimension_value, metric,
+                count(*),
+                avg(value),
+                percentile_cont(0.5) WITHIN GROUP (ORDER BY value),
+                percentile_cont(0.75) WITHIN GROUP (ORDER BY value),
+                percentile_cont(0.9) WITHIN GROUP (ORDER BY value),
+                percentile_cont(0.95) WITHIN GROUP (ORDER BY value)
+            FROM measurements
+            GROUP BY dimension, dimension_value, metric
+            UNION ALL
+            SELECT
+                $1::integer, $2::date - $1::integer, $2::date, dimension, dimension_value,
+                'throughput', count(*), (count(*) * 7.0 / $1::integer)::double precision,
+                NULL, NULL, NULL, NULL
+            FROM samples
+            GROUP BY dimension, dimension_value
+            "#,
+        ))
+        .bind(window_days)
+        .bind(window_end)
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Metrics of the most recently computed `window_days` window, optionally of one dimension
+    pub async fn fetch_latest(
+        &self,
+        window_days: i32,
+        dimension: Option<&str>,
+    ) -> Result<Vec<FlowMetric>, FlowMetricsError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT window_days, window_start, window_end, dimension, dimension_value, metric,
+                sample_count, mean, p50, p75, p90, p95
+            FROM engineering_metrics.flow_metrics
+            WHERE window_days = $1
+              AND ($2::varchar IS NULL OR dimension = $2)
+              AND window_end = (
+                  SELECT max(window_end) FROM engineering_metrics.flow_metrics WHERE window_days = $1
+              )
+            ORDER BY dimension, dimension_value, metric
+            "#,
+        ))
+        .bind(window_days)
+        .bind(dimension)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| FlowMetric {
+                window_days: row.get(0),
+                window_start: row.get(1),
+                window_end: row.get(2),
+                dimension: row.get(3),
+                dimension_value: row.get(4),
+                metric: row.get(5),
+                sample_count: row.get(6),
+                mean: row.get(7),
+                p50: row.get(8),
+                p75: row.get(9),
+                p90: row.get(10),
+                p95: row.get(11),
+            })
+            .collect())
+    }
+}
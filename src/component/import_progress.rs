@@ -1,7 +1,8 @@
-use sqlx::Row;
+use sqlx::{PgConnection, Row};
 use std::str::FromStr;
 use thiserror::Error;
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
+use tracing::info;
 use uuid::Uuid;
 
 use crate::store::Store;
@@ -15,6 +16,32 @@ pub enum ImportProgressError {
 #[derive(Debug, Clone)]
 pub struct ImportProgressHandler {
     pub store: Store,
+    /// Run that creates or resumes imports, recorded on the import_progress rows it touches
+    pub run_id: Option<Uuid>,
+    pub policy: ImportProgressPolicy,
+}
+
+/// Controls how long import progress is kept and when an import is restarted instead of resumed
+#[derive(Debug, Clone)]
+pub struct ImportProgressPolicy {
+    /// Completed and abandoned imports older than this are deleted at the start of a run;
+    /// failed and interrupted ones are abandoned first
+    pub retention_days: i32,
+    /// Resumable imports without activity for longer than this are abandoned and restarted
+    /// from the first page, since their saved cursor has most likely expired
+    pub stale_after: Duration,
+    /// Restart an import from the first page when the API rejects its saved cursor
+    pub restart_on_rejected_cursor: bool,
+}
+
+impl Default for ImportProgressPolicy {
+    fn default() -> Self {
+        ImportProgressPolicy {
+            retention_days: 30,
+            stale_after: Duration::hours(72),
+            restart_on_rejected_cursor: true,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -30,6 +57,7 @@ pub struct ImportProgress {
     pub last_activity_at: OffsetDateTime,
     pub completed_at: Option<OffsetDateTime>,
     pub error_message: Option<String>,
+    pub run_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -37,6 +65,10 @@ pub enum ImportStatus {
     InProgress,
     Completed,
     Failed,
+    /// Stopped on shutdown after checkpointing its last unit; resumed by the next run
+    Interrupted,
+    /// Went stale or had its cursor rejected; superseded by a restarted import
+    Abandoned,
 }
 
 impl ImportStatus {
@@ -45,6 +77,8 @@ impl ImportStatus {
             ImportStatus::InProgress => "in_progress",
             ImportStatus::Completed => "completed",
             ImportStatus::Failed => "failed",
+            ImportStatus::Interrupted => "interrupted",
+            ImportStatus::Abandoned => "abandoned",
         }
     }
 }
@@ -57,6 +91,8 @@ impl FromStr for ImportStatus {
             "in_progress" => Ok(ImportStatus::InProgress),
             "completed" => Ok(ImportStatus::Completed),
             "failed" => Ok(ImportStatus::Failed),
+            "interrupted" => Ok(ImportStatus::Interrupted),
+            "abandoned" => Ok(ImportStatus::Abandoned),
             _ => Err(format!("Unknown import status: {}", s)),
         }
     }
@@ -75,18 +111,48 @@ impl ImportProgressHandler {
             .find_in_progress_import(group_full_path, import_type)
             .await?
         {
-            println!(
-                "Resuming existing import for group={}, type={}, cursor={:?}, processed={}, previous_status={:?}",
-                group_full_path, import_type, existing.last_cursor, existing.total_processed, existing.status
+            // A stale cursor would most likely be rejected, so start over from the first page
+            // while keeping the original updated_after to not skip any data
+            if self.is_stale(&existing, OffsetDateTime::now_utc()) {
+                info!(
+                    group = group_full_path,
+                    import_type,
+                    import_id = %existing.id,
+                    last_activity_at = %existing.last_activity_at,
+                    "Abandoning stale import, restarting from the first page"
+                );
+                self.mark_abandoned(
+                    existing.id,
+                    "stale: no activity within the configured threshold",
+                )
+                .await?;
+                return self
+                    .create_import(group_full_path, import_type, existing.updated_after)
+                    .await;
+            }
+
+            info!(
+                group = group_full_path,
+                import_type,
+                import_id = %existing.id,
+                cursor = ?existing.last_cursor,
+                processed = existing.total_processed,
+                previous_status = ?existing.status,
+                "Resuming existing import"
             );
 
-            // If the import was previously failed, reset it to in_progress
-            if existing.status == ImportStatus::Failed {
+            // If the import was previously failed or interrupted, reset it to in_progress
+            if existing.status != ImportStatus::InProgress {
                 self.reset_to_in_progress(existing.id).await?;
                 existing.status = ImportStatus::InProgress;
                 existing.error_message = None;
             }
 
+            if let Some(run_id) = self.run_id {
+                self.attach_run(existing.id, run_id).await?;
+                existing.run_id = Some(run_id);
+            }
+
             return Ok(existing);
         }
 
@@ -95,47 +161,89 @@ impl ImportProgressHandler {
             .await
     }
 
-    /// Find a resumable import (in_progress or failed) for the given group and type
+    /// Find a resumable import (in_progress, failed or interrupted) for the given group and type
     pub async fn find_in_progress_import(
         &self,
         group_full_path: &str,
         import_type: &str,
     ) -> Result<Option<ImportProgress>, ImportProgressError> {
         let mut conn = self.store.conn_pool.acquire().await?;
-        // Look for both in_progress and failed imports to allow resuming from failures
+        // Look for failed and interrupted imports as well to allow resuming from them
         let row = sqlx::query(
-            r#"
+            &self.store.sql(r#"
             SELECT id, group_full_path, import_type, updated_after, last_cursor, 
-                   total_processed, status, started_at, last_activity_at, completed_at, error_message
+                   total_processed, status, started_at, last_activity_at, completed_at, error_message, run_id
             FROM engineering_metrics.import_progress
             WHERE group_full_path = $1 
               AND import_type = $2 
-              AND status IN ('in_progress', 'failed')
+              AND status IN ('in_progress', 'failed', 'interrupted')
             ORDER BY started_at DESC
             LIMIT 1
-            "#,
+            "#),
         )
         .bind(group_full_path)
         .bind(import_type)
         .fetch_optional(&mut *conn)
         .await?;
 
-        Ok(row.map(|row| {
-            let status_str: String = row.get(6);
-            ImportProgress {
-                id: row.get(0),
-                group_full_path: row.get(1),
-                import_type: row.get(2),
-                updated_after: row.get(3),
-                last_cursor: row.get(4),
-                total_processed: row.get(5),
-                status: ImportStatus::from_str(&status_str).unwrap_or(ImportStatus::Failed),
-                started_at: row.get(7),
-                last_activity_at: row.get(8),
-                completed_at: row.get(9),
-                error_message: row.get(10),
-            }
-        }))
+        Ok(row.as_ref().map(import_progress_from_row))
+    }
+
+    /// List imports with any of the given statuses, most recently active first
+    pub async fn list_imports(
+        &self,
+        statuses: &[ImportStatus],
+    ) -> Result<Vec<ImportProgress>, ImportProgressError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let statuses: Vec<&str> = statuses.iter().map(ImportStatus::as_str).collect();
+
+        let rows = sqlx::query(
+            &self.store.sql(r#"
+            SELECT id, group_full_path, import_type, updated_after, last_cursor,
+                   total_processed, status, started_at, last_activity_at, completed_at, error_message, run_id
+            FROM engineering_metrics.import_progress
+            WHERE status = ANY($1)
+            ORDER BY last_activity_at DESC
+            "#),
+        )
+        .bind(&statuses)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.iter().map(import_progress_from_row).collect())
+    }
+
+    /// Most recently started import of any status for the given group and type
+    pub async fn find_latest_import(
+        &self,
+        group_full_path: &str,
+        import_type: &str,
+    ) -> Result<Option<ImportProgress>, ImportProgressError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let row = sqlx::query(
+            &self.store.sql(r#"
+            SELECT id, group_full_path, import_type, updated_after, last_cursor,
+                   total_processed, status, started_at, last_activity_at, completed_at, error_message, run_id
+            FROM engineering_metrics.import_progress
+            WHERE group_full_path = $1 AND import_type = $2
+            ORDER BY started_at DESC
+            LIMIT 1
+            "#),
+        )
+        .bind(group_full_path)
+        .bind(import_type)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(row.as_ref().map(import_progress_from_row))
+    }
+
+    /// Whether a resumable import has been inactive for longer than the policy allows
+    pub fn is_stale(&self, import: &ImportProgress, now: OffsetDateTime) -> bool {
+        matches!(
+            import.status,
+            ImportStatus::InProgress | ImportStatus::Failed | ImportStatus::Interrupted
+        ) && now - import.last_activity_at > self.policy.stale_after
     }
 
     /// Create a new import progress record
@@ -150,17 +258,18 @@ impl ImportProgressHandler {
         let id = Uuid::now_v7();
 
         sqlx::query(
-            r#"
+            &self.store.sql(r#"
             INSERT INTO engineering_metrics.import_progress 
-                (id, group_full_path, import_type, updated_after, status, started_at, last_activity_at)
-            VALUES ($1, $2, $3, $4, 'in_progress', $5, $5)
-            "#,
+                (id, group_full_path, import_type, updated_after, status, started_at, last_activity_at, run_id)
+            VALUES ($1, $2, $3, $4, 'in_progress', $5, $5, $6)
+            "#),
         )
         .bind(id)
         .bind(group_full_path)
         .bind(import_type)
         .bind(updated_after)
         .bind(now)
+        .bind(self.run_id)
         .execute(&mut *conn)
         .await?;
 
@@ -176,6 +285,7 @@ impl ImportProgressHandler {
             last_activity_at: now,
             completed_at: None,
             error_message: None,
+            run_id: self.run_id,
         })
     }
 
@@ -187,9 +297,22 @@ impl ImportProgressHandler {
         items_processed: i32,
     ) -> Result<(), ImportProgressError> {
         let mut conn = self.store.conn_pool.acquire().await?;
+        self.update_progress_in(&mut conn, import_id, last_cursor, items_processed)
+            .await
+    }
+
+    /// Same as `update_progress`, on the caller's connection so the checkpoint can share a
+    /// transaction with the data of the page it covers
+    pub async fn update_progress_in(
+        &self,
+        conn: &mut PgConnection,
+        import_id: Uuid,
+        last_cursor: Option<&str>,
+        items_processed: i32,
+    ) -> Result<(), ImportProgressError> {
         let now = OffsetDateTime::now_utc();
 
-        sqlx::query(
+        sqlx::query(&self.store.sql(
             r#"
             UPDATE engineering_metrics.import_progress
             SET last_cursor = $2,
@@ -197,23 +320,46 @@ impl ImportProgressHandler {
                 last_activity_at = $4
             WHERE id = $1
             "#,
-        )
+        ))
         .bind(import_id)
         .bind(last_cursor)
         .bind(items_processed)
         .bind(now)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Link a resumed import to the run that is now processing it
+    pub async fn attach_run(
+        &self,
+        import_id: Uuid,
+        run_id: Uuid,
+    ) -> Result<(), ImportProgressError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+
+        sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.import_progress
+            SET run_id = $2
+            WHERE id = $1
+            "#,
+        ))
+        .bind(import_id)
+        .bind(run_id)
         .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
-    /// Reset a failed import back to in_progress status for resumption
+    /// Reset a failed or interrupted import back to in_progress status for resumption
     pub async fn reset_to_in_progress(&self, import_id: Uuid) -> Result<(), ImportProgressError> {
         let mut conn = self.store.conn_pool.acquire().await?;
         let now = OffsetDateTime::now_utc();
 
-        sqlx::query(
+        sqlx::query(&self.store.sql(
             r#"
             UPDATE engineering_metrics.import_progress
             SET status = 'in_progress',
@@ -221,7 +367,7 @@ impl ImportProgressHandler {
                 last_activity_at = $2
             WHERE id = $1
             "#,
-        )
+        ))
         .bind(import_id)
         .bind(now)
         .execute(&mut *conn)
@@ -235,7 +381,7 @@ impl ImportProgressHandler {
         let mut conn = self.store.conn_pool.acquire().await?;
         let now = OffsetDateTime::now_utc();
 
-        sqlx::query(
+        sqlx::query(&self.store.sql(
             r#"
             UPDATE engineering_metrics.import_progress
             SET status = 'completed',
@@ -243,7 +389,114 @@ impl ImportProgressHandler {
                 last_activity_at = $2
             WHERE id = $1
             "#,
-        )
+        ))
+        .bind(import_id)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Drop the saved cursor so the import starts over from the first page
+    pub async fn restart_from_first_page(
+        &self,
+        import_id: Uuid,
+    ) -> Result<(), ImportProgressError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let now = OffsetDateTime::now_utc();
+
+        sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.import_progress
+            SET last_cursor = NULL,
+                total_processed = 0,
+                last_activity_at = $2
+            WHERE id = $1
+            "#,
+        ))
+        .bind(import_id)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Mark an import as abandoned so it is no longer resumed
+    pub async fn mark_abandoned(
+        &self,
+        import_id: Uuid,
+        reason: &str,
+    ) -> Result<(), ImportProgressError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let now = OffsetDateTime::now_utc();
+
+        sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.import_progress
+            SET status = 'abandoned',
+                error_message = $2,
+                last_activity_at = $3
+            WHERE id = $1
+            "#,
+        ))
+        .bind(import_id)
+        .bind(reason)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Abandon every resumable import of a type, optionally only for one group.
+    ///
+    /// Used when a watermark is reset, so the next run does not resume a cursor
+    /// that belongs to the old watermark.
+    pub async fn abandon_resumable_imports(
+        &self,
+        group_full_path: Option<&str>,
+        import_type: &str,
+        reason: &str,
+    ) -> Result<u64, ImportProgressError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let now = OffsetDateTime::now_utc();
+
+        let result = sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.import_progress
+            SET status = 'abandoned',
+                error_message = $3,
+                last_activity_at = $4
+            WHERE import_type = $2
+              AND status IN ('in_progress', 'failed', 'interrupted')
+              AND ($1::VARCHAR IS NULL OR group_full_path = $1)
+            "#,
+        ))
+        .bind(group_full_path)
+        .bind(import_type)
+        .bind(reason)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Mark an import as interrupted by shutdown, keeping its cursor for the next run
+    pub async fn mark_interrupted(&self, import_id: Uuid) -> Result<(), ImportProgressError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let now = OffsetDateTime::now_utc();
+
+        sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.import_progress
+            SET status = 'interrupted',
+                last_activity_at = $2
+            WHERE id = $1
+            "#,
+        ))
         .bind(import_id)
         .bind(now)
         .execute(&mut *conn)
@@ -261,7 +514,7 @@ impl ImportProgressHandler {
         let mut conn = self.store.conn_pool.acquire().await?;
         let now = OffsetDateTime::now_utc();
 
-        sqlx::query(
+        sqlx::query(&self.store.sql(
             r#"
             UPDATE engineering_metrics.import_progress
             SET status = 'failed',
@@ -269,7 +522,7 @@ impl ImportProgressHandler {
                 last_activity_at = $3
             WHERE id = $1
             "#,
-        )
+        ))
         .bind(import_id)
         .bind(error_message)
         .bind(now)
@@ -279,17 +532,43 @@ impl ImportProgressHandler {
         Ok(())
     }
 
-    /// Clean up old completed/failed imports (optional, for maintenance)
+    /// Clean up old completed and abandoned imports.
+    ///
+    /// Failed and interrupted imports are resumable, so instead of being deleted they are first
+    /// abandoned once they exceed the retention; they are deleted one retention period later.
     pub async fn cleanup_old_imports(&self, days_to_keep: i32) -> Result<u64, ImportProgressError> {
         let mut conn = self.store.conn_pool.acquire().await?;
+        let now = OffsetDateTime::now_utc();
+
+        let abandoned = sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.import_progress
+            SET status = 'abandoned',
+                error_message = $2,
+                last_activity_at = $3
+            WHERE status IN ('failed', 'interrupted')
+              AND last_activity_at < NOW() - INTERVAL '1 day' * $1
+            "#,
+        ))
+        .bind(days_to_keep)
+        .bind(format!("no activity for more than {} days", days_to_keep))
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+        if abandoned.rows_affected() > 0 {
+            info!(
+                count = abandoned.rows_affected(),
+                "Abandoned failed and interrupted imports older than the retention"
+            );
+        }
 
-        let result = sqlx::query(
+        let result = sqlx::query(&self.store.sql(
             r#"
             DELETE FROM engineering_metrics.import_progress
-            WHERE status IN ('completed', 'failed')
+            WHERE status IN ('completed', 'abandoned')
               AND last_activity_at < NOW() - INTERVAL '1 day' * $1
             "#,
-        )
+        ))
         .bind(days_to_keep)
         .execute(&mut *conn)
         .await?;
@@ -297,3 +576,21 @@ impl ImportProgressHandler {
         Ok(result.rows_affected())
     }
 }
+
+fn import_progress_from_row(row: &sqlx::postgres::PgRow) -> ImportProgress {
+    let status_str: String = row.get(6);
+    ImportProgress {
+        id: row.get(0),
+        group_full_path: row.get(1),
+        import_type: row.get(2),
+        updated_after: row.get(3),
+        last_cursor: row.get(4),
+        total_processed: row.get(5),
+        status: ImportStatus::from_str(&status_str).unwrap_or(ImportStatus::Failed),
+        started_at: row.get(7),
+        last_activity_at: row.get(8),
+        completed_at: row.get(9),
+        error_message: row.get(10),
+        run_id: row.get(11),
+    }
+}
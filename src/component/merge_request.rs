@@ -1,7 +1,16 @@
-use crate::client::gitlab_graphql_client::GitlabGraphQLError;
-use crate::client::gitlab_rest_client::GitlabRestError;
-use crate::component::import_progress::ImportProgressHandler;
+use crate::client::gitlab_graphql_client::{
+    GitlabGraphQLError, MergeRequestConnection, MergeRequestFilter,
+};
+use crate::client::gitlab_rest_client::{Change, GitlabRestError};
+use crate::component::dead_letter::{DeadLetterHandler, DeadLetterStage};
+use crate::component::import_progress::{
+    ImportProgress, ImportProgressError, ImportProgressHandler, ImportStatus,
+};
+use crate::component::run_history::RunGroupStats;
 use crate::context::GitlabContext;
+use crate::metrics;
+use crate::scheduler::SchedulerError;
+use futures::stream::{self, StreamExt};
 use genai::adapter::AdapterKind;
 use genai::chat::{ChatMessage, ChatRequest};
 use genai::resolver::{AuthData, Endpoint, ServiceTargetResolver};
@@ -9,9 +18,22 @@ use genai::{Client as GenAiClient, ModelIden, ServiceTarget};
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json;
+use serde_with::serde_as;
+use sqlx::Row;
+use sqlx::{Postgres, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
 use thiserror::Error;
 use time::format_description::well_known::Rfc3339;
-use time::OffsetDateTime;
+use time::{Date, Month, OffsetDateTime};
+use tracing::{info, info_span, instrument, warn, Instrument};
+use uuid::Uuid;
+
+pub const IMPORT_TYPE: &str = "merge_requests";
+/// Prefix of the import types of time-range backfill windows, followed by `:<start>..<end>`
+pub const BACKFILL_IMPORT_TYPE: &str = "merge_requests_backfill";
+/// Notes pages of 100 searched for the first approval of a merge request before giving up
+pub const MAX_APPROVAL_NOTE_PAGES: usize = 10;
 
 #[derive(Error, Debug)]
 pub enum MergeRequestError {
@@ -25,8 +47,16 @@ pub enum MergeRequestError {
     JsonError(#[from] serde_json::Error),
     #[error("Date parsing error: {0}")]
     DateParseError(#[from] time::error::Parse),
+    #[error("Date range error: {0}")]
+    DateRangeError(#[from] time::error::ComponentRange),
+    #[error("Import progress error: {0}")]
+    ImportProgressError(#[from] ImportProgressError),
+    #[error("Scheduler error: {0}")]
+    SchedulerError(#[from] SchedulerError),
     #[error("Missing data: {0}")]
     MissingData(String),
+    #[error("Interrupted by shutdown")]
+    Interrupted,
 }
 
 #[derive(Debug, Clone)]
@@ -34,7 +64,8 @@ pub struct MergeRequestHandler {
     pub context: GitlabContext,
 }
 
-#[derive(Debug)]
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct MergeRequest {
     pub mr_id: String,
     pub mr_iid: String,
@@ -44,8 +75,11 @@ pub struct MergeRequest {
     pub project_id: String,
     pub project_name: String,
     pub project_path: String,
+    #[serde_as(as = "Rfc3339")]
     pub created_at: OffsetDateTime,
+    #[serde_as(as = "Rfc3339")]
     pub updated_at: OffsetDateTime,
+    #[serde_as(as = "Option<Rfc3339>")]
     pub merged_at: Option<OffsetDateTime>,
     pub created_by: String,
     pub merged_by: Option<String>,
@@ -53,6 +87,14 @@ pub struct MergeRequest {
     pub approved_by: Option<Vec<String>>,
     pub diff_stats_summary: Option<DiffStatsSummary>,
     pub labels: Option<Vec<String>>,
+    /// Found by a separate request, so merge requests imported without it keep their stored value
+    #[serde_as(as = "Option<Rfc3339>")]
+    #[serde(default)]
+    pub first_approved_at: Option<OffsetDateTime>,
+    /// When the notes were searched for the first approval, whether or not it was found
+    #[serde_as(as = "Option<Rfc3339>")]
+    #[serde(default)]
+    pub first_approval_checked_at: Option<OffsetDateTime>,
     // AI fields
     pub mr_ai_title: Option<String>,
     pub mr_ai_summary: Option<String>,
@@ -81,6 +123,173 @@ pub struct PageInfo {
     pub has_next_page: bool,
 }
 
+/// Keeps stored merge requests in sync with changes made after they were imported
+#[derive(Debug, Clone, Default)]
+pub struct ReconcilePolicy {
+    /// Refresh the labels, approvals and other mutable fields of stored merge requests that
+    /// were updated since the last run, including those merged before it. AI fields are kept.
+    pub enabled: bool,
+    /// Incremental imports start this long before the watermark, so merge requests merged
+    /// while the previous run was going on are not missed
+    pub overlap: time::Duration,
+}
+
+/// Outcome of a time-range backfill of one group
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BackfillSummary {
+    pub windows_total: usize,
+    /// Windows completed by an earlier backfill
+    pub windows_skipped: usize,
+    pub windows_imported: usize,
+}
+
+/// Merge requests covered by one tracked import
+struct ImportWindow {
+    filter: MergeRequestFilter,
+    merged_from: OffsetDateTime,
+    /// Exclusive end of the window; open-ended for incremental imports
+    merged_until: Option<OffsetDateTime>,
+    /// Refresh stored merge requests instead of skipping them
+    reconcile: bool,
+}
+
+impl ImportWindow {
+    fn contains(&self, merged_at: Option<OffsetDateTime>) -> bool {
+        match merged_at {
+            Some(merged_at) => {
+                merged_at >= self.merged_from
+                    && self.merged_until.is_none_or(|until| merged_at < until)
+            }
+            None => false,
+        }
+    }
+}
+
+/// Split `[since, until)` into windows that each cover at most one calendar month
+pub fn monthly_windows(
+    since: Date,
+    until: Date,
+) -> Result<Vec<(Date, Date)>, time::error::ComponentRange> {
+    let mut windows = Vec::new();
+    let mut window_start = since;
+    while window_start < until {
+        let (year, month) = match window_start.month() {
+            Month::December => (window_start.year() + 1, Month::January),
+            month => (window_start.year(), month.next()),
+        };
+        let window_end = Date::from_calendar_date(year, month, 1)?.min(until);
+        windows.push((window_start, window_end));
+        window_start = window_end;
+    }
+
+    Ok(windows)
+}
+
+/// Import type under which one backfill window is tracked in `import_progress`
+pub fn backfill_import_type(window_start: Date, window_end: Date) -> String {
+    format!("{}:{}..{}", BACKFILL_IMPORT_TYPE, window_start, window_end)
+}
+
+fn format_timestamp(timestamp: OffsetDateTime) -> Result<String, MergeRequestError> {
+    timestamp
+        .format(&Rfc3339)
+        .map_err(|e| MergeRequestError::MissingData(format!("Failed to format date: {}", e)))
+}
+
+/// Map a page of the merge requests query, of a group or of a project
+fn merge_requests_from_connection(
+    connection: MergeRequestConnection,
+) -> Result<MergeRequestsWithPageInfo, MergeRequestError> {
+    let mut merge_requests: Vec<MergeRequest> = Vec::new();
+    let nodes = connection.nodes.ok_or_else(|| {
+        MergeRequestError::MissingData("GroupMergeReqsGroupMergeRequestsNodes is None".to_string())
+    })?;
+
+    for mr in nodes {
+        let mr_ref = mr
+            .as_ref()
+            .ok_or_else(|| MergeRequestError::MissingData("mr is None".to_string()))?;
+        merge_requests.push(MergeRequest {
+            mr_id: mr_ref.id.clone(),
+            mr_iid: mr_ref.iid.clone(),
+            mr_title: mr_ref.title.clone(),
+            mr_description: mr_ref.description.clone(),
+            mr_web_url: mr_ref.web_url.clone(),
+            project_id: mr_ref.project_id.clone().to_string(),
+            project_name: mr_ref.project.name.clone(),
+            project_path: mr_ref.project.path.clone(),
+            created_at: OffsetDateTime::parse(&mr_ref.created_at.clone(), &Rfc3339)?,
+            updated_at: OffsetDateTime::parse(&mr_ref.updated_at.clone(), &Rfc3339)?,
+            merged_at: mr_ref
+                .merged_at
+                .clone()
+                .map(|m_at| OffsetDateTime::parse(m_at.as_str(), &Rfc3339))
+                .transpose()?,
+            created_by: mr_ref.author.username.clone(),
+            merged_by: mr_ref.merge_user.as_ref().map(|m_by| m_by.username.clone()),
+            approved_by: mr_ref.approved_by.as_ref().map(|a_by| {
+                a_by.nodes
+                    .as_ref()
+                    .map(|nodes| {
+                        nodes
+                            .iter()
+                            .filter_map(|a_by_node| {
+                                a_by_node.as_ref().map(|node| node.username.clone())
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            }),
+            approved: mr_ref.approved,
+            diff_stats_summary: mr_ref
+                .diff_stats_summary
+                .as_ref()
+                .map(|diff_stats_summary| DiffStatsSummary {
+                    additions: diff_stats_summary.additions as i32,
+                    deletions: diff_stats_summary.deletions as i32,
+                    changes: diff_stats_summary.changes as i32,
+                    file_count: diff_stats_summary.file_count as i32,
+                }),
+            labels: mr_ref.labels.as_ref().map(|labels| {
+                labels
+                    .nodes
+                    .as_ref()
+                    .map(|nodes| {
+                        nodes
+                            .iter()
+                            .filter_map(|label| label.as_ref().map(|l| l.title.clone()))
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            }),
+            first_approved_at: None,
+            first_approval_checked_at: None,
+            mr_ai_title: None,
+            mr_ai_summary: None,
+            mr_ai_model: None,
+            mr_ai_category: None,
+        });
+    }
+
+    Ok(MergeRequestsWithPageInfo {
+        merge_requests,
+        page_info: PageInfo {
+            end_cursor: connection.page_info.end_cursor,
+            has_next_page: connection.page_info.has_next_page,
+        },
+        total_count: connection.count as i32,
+    })
+}
+
+/// Stored merge request that is still missing its AI summary
+struct SummaryCandidate {
+    mr_id: String,
+    mr_iid: String,
+    mr_title: String,
+    mr_web_url: String,
+    project_id: String,
+}
+
 #[derive(Deserialize)]
 struct AiResponse {
     category: String,
@@ -95,93 +304,60 @@ impl MergeRequestHandler {
         updated_after: &str,
         after_pointer_token: Option<String>,
     ) -> Result<MergeRequestsWithPageInfo, MergeRequestError> {
-        let group_data = self
-            .context
-            .gitlab_graphql_client
-            .fetch_group_merge_requests(group_full_path, updated_after, after_pointer_token)
-            .await?;
-
-        let mut merge_requests: Vec<MergeRequest> = Vec::new();
-        let nodes = group_data.merge_requests.nodes.ok_or_else(|| {
-            MergeRequestError::MissingData(
-                "GroupMergeReqsGroupMergeRequestsNodes is None".to_string(),
-            )
-        })?;
+        let filter = MergeRequestFilter {
+            updated_after: Some(updated_after.to_string()),
+            ..MergeRequestFilter::default()
+        };
+        self.fetch_filtered_group_merge_requests(group_full_path, &filter, after_pointer_token)
+            .await
+    }
 
-        for mr in nodes {
-            let mr_ref = mr
-                .as_ref()
-                .ok_or_else(|| MergeRequestError::MissingData("mr is None".to_string()))?;
-            merge_requests.push(MergeRequest {
-                mr_id: mr_ref.id.clone(),
-                mr_iid: mr_ref.iid.clone(),
-                mr_title: mr_ref.title.clone(),
-                mr_description: mr_ref.description.clone(),
-                mr_web_url: mr_ref.web_url.clone(),
-                project_id: mr_ref.project_id.clone().to_string(),
-                project_name: mr_ref.project.name.clone(),
-                project_path: mr_ref.project.path.clone(),
-                created_at: OffsetDateTime::parse(&mr_ref.created_at.clone(), &Rfc3339)?,
-                updated_at: OffsetDateTime::parse(&mr_ref.updated_at.clone(), &Rfc3339)?,
-                merged_at: mr_ref
-                    .merged_at
-                    .clone()
-                    .map(|m_at| OffsetDateTime::parse(m_at.as_str(), &Rfc3339))
-                    .transpose()?,
-                created_by: mr_ref.author.username.clone(),
-                merged_by: mr_ref.merge_user.as_ref().map(|m_by| m_by.username.clone()),
-                approved_by: mr_ref.approved_by.as_ref().map(|a_by| {
-                    a_by.nodes
-                        .as_ref()
-                        .map(|nodes| {
-                            nodes
-                                .iter()
-                                .filter_map(|a_by_node| {
-                                    a_by_node.as_ref().map(|node| node.username.clone())
-                                })
-                                .collect()
-                        })
-                        .unwrap_or_default()
-                }),
-                approved: mr_ref.approved,
-                diff_stats_summary: mr_ref
-                    .diff_stats_summary
-                    .as_ref()
-                    .map(|diff_stats_summary| DiffStatsSummary {
-                        additions: diff_stats_summary.additions as i32,
-                        deletions: diff_stats_summary.deletions as i32,
-                        changes: diff_stats_summary.changes as i32,
-                        file_count: diff_stats_summary.file_count as i32,
-                    }),
-                labels: mr_ref.labels.as_ref().map(|labels| {
-                    labels
-                        .nodes
-                        .as_ref()
-                        .map(|nodes| {
-                            nodes
-                                .iter()
-                                .filter_map(|label| label.as_ref().map(|l| l.title.clone()))
-                                .collect()
-                        })
-                        .unwrap_or_default()
-                }),
-                mr_ai_title: None,
-                mr_ai_summary: None,
-                mr_ai_model: None,
-                mr_ai_category: None,
-            });
-        }
+    pub async fn fetch_filtered_group_merge_requests(
+        &self,
+        group_full_path: &str,
+        filter: &MergeRequestFilter,
+        after_pointer_token: Option<String>,
+    ) -> Result<MergeRequestsWithPageInfo, MergeRequestError> {
+        let group_data = {
+            let _permit = self
+                .context
+                .scheduler
+                .acquire(self.context.gitlab_graphql_client.endpoint())
+                .await?;
+            self.context
+                .gitlab_graphql_client
+                .fetch_filtered_group_merge_requests(group_full_path, filter, after_pointer_token)
+                .await?
+        };
+        merge_requests_from_connection(group_data.merge_requests)
+    }
 
-        Ok(MergeRequestsWithPageInfo {
-            merge_requests,
-            page_info: PageInfo {
-                end_cursor: group_data.merge_requests.page_info.end_cursor,
-                has_next_page: group_data.merge_requests.page_info.has_next_page,
-            },
-            total_count: group_data.merge_requests.count as i32,
-        })
+    /// Merge requests of one project, e.g. to refresh it without paging through its whole group
+    pub async fn fetch_filtered_project_merge_requests(
+        &self,
+        project_full_path: &str,
+        filter: &MergeRequestFilter,
+        after_pointer_token: Option<String>,
+    ) -> Result<MergeRequestsWithPageInfo, MergeRequestError> {
+        let connection = {
+            let _permit = self
+                .context
+                .scheduler
+                .acquire(self.context.gitlab_graphql_client.endpoint())
+                .await?;
+            self.context
+                .gitlab_graphql_client
+                .fetch_filtered_project_merge_requests(
+                    project_full_path,
+                    filter,
+                    after_pointer_token,
+                )
+                .await?
+        };
+        merge_requests_from_connection(connection)
     }
 
+    /// Upsert a merge request, keeping a stored copy that is newer than `merge_request`
     pub async fn persist_merge_request(
         &self,
         merge_request: &MergeRequest,
@@ -189,11 +365,12 @@ impl MergeRequestHandler {
         let mut conn = self.context.store.conn_pool.acquire().await?;
 
         sqlx::query(
-            r#"
+            &self.context.store.sql(r#"
             INSERT INTO engineering_metrics.merge_requests (mr_id, mr_iid, mr_title, mr_web_url, project_id, project_name, project_path, 
                 created_at, updated_at, merged_at, 
-                created_by, merged_by, approved, approved_by, diff_stats_summary, labels, mr_ai_title, mr_ai_summary, mr_ai_model, mr_ai_category)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
+                created_by, merged_by, approved, approved_by, diff_stats_summary, labels, mr_ai_title, mr_ai_summary, mr_ai_model, mr_ai_category,
+                first_approved_at, first_approval_checked_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22)
             ON CONFLICT (mr_id) DO 
             UPDATE SET 
                 mr_iid = $2,
@@ -205,11 +382,14 @@ impl MergeRequestHandler {
                 approved_by = $14,
                 diff_stats_summary = $15,
                 labels = $16,
+                first_approved_at = COALESCE(EXCLUDED.first_approved_at, engineering_metrics.merge_requests.first_approved_at),
+                first_approval_checked_at = COALESCE(EXCLUDED.first_approval_checked_at, engineering_metrics.merge_requests.first_approval_checked_at),
                 mr_ai_title = COALESCE(EXCLUDED.mr_ai_title, engineering_metrics.merge_requests.mr_ai_title),
                 mr_ai_summary = COALESCE(EXCLUDED.mr_ai_summary, engineering_metrics.merge_requests.mr_ai_summary),
                 mr_ai_model = COALESCE(EXCLUDED.mr_ai_model, engineering_metrics.merge_requests.mr_ai_model),
                 mr_ai_category = COALESCE(EXCLUDED.mr_ai_category, engineering_metrics.merge_requests.mr_ai_category)
-            "#)
+            WHERE engineering_metrics.merge_requests.updated_at <= EXCLUDED.updated_at
+            "#))
             .bind(&merge_request.mr_id)
             .bind(&merge_request.mr_iid)
             .bind(&merge_request.mr_title)
@@ -230,236 +410,1305 @@ impl MergeRequestHandler {
             .bind(&merge_request.mr_ai_summary)
             .bind(&merge_request.mr_ai_model)
             .bind(&merge_request.mr_ai_category)
+            .bind(merge_request.first_approved_at)
+            .bind(merge_request.first_approval_checked_at)
         .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
-    pub async fn merge_request_exists(&self, mr_id: &str) -> Result<bool, MergeRequestError> {
+    /// Refresh the mutable fields of a stored merge request without inserting it.
+    ///
+    /// AI fields are never touched, and a stored copy that is newer than `merge_request` is kept.
+    /// Returns whether a stored merge request was updated.
+    pub async fn update_stored_merge_request(
+        &self,
+        merge_request: &MergeRequest,
+    ) -> Result<bool, MergeRequestError> {
         let mut conn = self.context.store.conn_pool.acquire().await?;
 
-        let result: Option<(i32,)> = sqlx::query_as(
+        let result = sqlx::query(&self.context.store.sql(
             r#"
-            SELECT 1 FROM engineering_metrics.merge_requests WHERE mr_id = $1
+            UPDATE engineering_metrics.merge_requests
+            SET mr_title = $2,
+                updated_at = $3,
+                merged_at = $4,
+                merged_by = $5,
+                approved = $6,
+                approved_by = $7,
+                diff_stats_summary = $8,
+                labels = $9
+            WHERE mr_id = $1 AND updated_at <= $3
             "#,
-        )
-        .bind(mr_id)
-        .fetch_optional(&mut *conn)
+        ))
+        .bind(&merge_request.mr_id)
+        .bind(&merge_request.mr_title)
+        .bind(merge_request.updated_at)
+        .bind(merge_request.merged_at)
+        .bind(&merge_request.merged_by)
+        .bind(merge_request.approved)
+        .bind(serde_json::to_value(&merge_request.approved_by)?)
+        .bind(serde_json::to_value(&merge_request.diff_stats_summary)?)
+        .bind(serde_json::to_value(&merge_request.labels)?)
+        .execute(&mut *conn)
         .await?;
 
-        Ok(result.is_some())
+        Ok(result.rows_affected() > 0)
     }
 
+    async fn reconcile_stored_merge_request(
+        &self,
+        merge_request: &MergeRequest,
+        stats: &mut RunGroupStats,
+    ) {
+        let write_started_at = Instant::now();
+        let result = self.update_stored_merge_request(merge_request).await;
+        metrics::observe_db_write("merge_request_reconcile", write_started_at.elapsed());
+        match result {
+            Ok(true) => stats.items_reconciled += 1,
+            // Not stored, or the stored copy is already newer
+            Ok(false) => stats.items_filtered += 1,
+            Err(e) => {
+                warn!(
+                    mr_id = %merge_request.mr_id,
+                    mr_web_url = %merge_request.mr_web_url,
+                    error = %e,
+                    "Failed to reconcile merge request"
+                );
+                stats.items_failed += 1;
+                stats.record_error(format!(
+                    "reconcile failed for {}: {}",
+                    merge_request.mr_id, e
+                ));
+            }
+        }
+    }
+
+    /// Ids among `mr_ids` that are already stored, looked up in one round trip
+    pub async fn existing_merge_request_ids(
+        &self,
+        mr_ids: &[String],
+    ) -> Result<HashSet<String>, MergeRequestError> {
+        if mr_ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let mut conn = self.context.store.conn_pool.acquire().await?;
+
+        let rows = sqlx::query(&self.context.store.sql(
+            r#"
+            SELECT mr_id FROM engineering_metrics.merge_requests WHERE mr_id = ANY($1)
+            "#,
+        ))
+        .bind(mr_ids)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    /// Upsert the merge requests of a page and checkpoint the import at `next_cursor` in one
+    /// transaction, so a page is either stored together with its checkpoint or not at all
+    pub async fn persist_page(
+        &self,
+        merge_requests: &[&MergeRequest],
+        import_progress_handler: &ImportProgressHandler,
+        import_id: Uuid,
+        next_cursor: Option<&str>,
+    ) -> Result<(), MergeRequestError> {
+        let mut transaction = self.context.store.conn_pool.begin().await?;
+
+        self.upsert_merge_requests(&mut transaction, merge_requests)
+            .await?;
+        let items_processed = i32::try_from(merge_requests.len()).map_err(|_| {
+            MergeRequestError::MissingData("Page size does not fit into i32".to_string())
+        })?;
+        import_progress_handler
+            .update_progress_in(&mut transaction, import_id, next_cursor, items_processed)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Upsert many merge requests with a single statement over column arrays
+    async fn upsert_merge_requests(
+        &self,
+        transaction: &mut Transaction<'_, Postgres>,
+        merge_requests: &[&MergeRequest],
+    ) -> Result<(), MergeRequestError> {
+        if merge_requests.is_empty() {
+            return Ok(());
+        }
+
+        // ON CONFLICT may touch each row only once per statement, so the last copy of an id wins
+        let mut latest: HashMap<&str, &MergeRequest> = HashMap::new();
+        for merge_request in merge_requests {
+            latest.insert(&merge_request.mr_id, merge_request);
+        }
+        let merge_requests: Vec<&MergeRequest> = latest.into_values().collect();
+
+        let mut approved_by = Vec::with_capacity(merge_requests.len());
+        let mut diff_stats_summaries = Vec::with_capacity(merge_requests.len());
+        let mut labels = Vec::with_capacity(merge_requests.len());
+        for merge_request in &merge_requests {
+            approved_by.push(serde_json::to_value(&merge_request.approved_by)?);
+            diff_stats_summaries.push(serde_json::to_value(&merge_request.diff_stats_summary)?);
+            labels.push(serde_json::to_value(&merge_request.labels)?);
+        }
+        let column = |field: fn(&MergeRequest) -> String| -> Vec<String> {
+            merge_requests.iter().map(|mr| field(mr)).collect()
+        };
+        let optional_column = |field: fn(&MergeRequest) -> Option<String>| -> Vec<Option<String>> {
+            merge_requests.iter().map(|mr| field(mr)).collect()
+        };
+
+        sqlx::query(
+            &self.context.store.sql(r#"
+            INSERT INTO engineering_metrics.merge_requests (mr_id, mr_iid, mr_title, mr_web_url, project_id, project_name, project_path,
+                created_at, updated_at, merged_at,
+                created_by, merged_by, approved, approved_by, diff_stats_summary, labels, mr_ai_title, mr_ai_summary, mr_ai_model, mr_ai_category,
+                first_approved_at, first_approval_checked_at)
+            SELECT * FROM UNNEST(
+                $1::VARCHAR[], $2::VARCHAR[], $3::VARCHAR[], $4::VARCHAR[], $5::VARCHAR[], $6::VARCHAR[], $7::VARCHAR[],
+                $8::TIMESTAMPTZ[], $9::TIMESTAMPTZ[], $10::TIMESTAMPTZ[],
+                $11::VARCHAR[], $12::VARCHAR[], $13::BOOLEAN[], $14::JSONB[], $15::JSONB[], $16::JSONB[],
+                $17::VARCHAR[], $18::VARCHAR[], $19::VARCHAR[], $20::VARCHAR[],
+                $21::TIMESTAMPTZ[], $22::TIMESTAMPTZ[]
+            )
+            ON CONFLICT (mr_id) DO
+            UPDATE SET
+                mr_iid = EXCLUDED.mr_iid,
+                mr_title = EXCLUDED.mr_title,
+                updated_at = EXCLUDED.updated_at,
+                merged_at = EXCLUDED.merged_at,
+                merged_by = EXCLUDED.merged_by,
+                approved = EXCLUDED.approved,
+                approved_by = EXCLUDED.approved_by,
+                diff_stats_summary = EXCLUDED.diff_stats_summary,
+                labels = EXCLUDED.labels,
+                first_approved_at = COALESCE(EXCLUDED.first_approved_at, engineering_metrics.merge_requests.first_approved_at),
+                first_approval_checked_at = COALESCE(EXCLUDED.first_approval_checked_at, engineering_metrics.merge_requests.first_approval_checked_at),
+                mr_ai_title = COALESCE(EXCLUDED.mr_ai_title, engineering_metrics.merge_requests.mr_ai_title),
+                mr_ai_summary = COALESCE(EXCLUDED.mr_ai_summary, engineering_metrics.merge_requests.mr_ai_summary),
+                mr_ai_model = COALESCE(EXCLUDED.mr_ai_model, engineering_metrics.merge_requests.mr_ai_model),
+                mr_ai_category = COALESCE(EXCLUDED.mr_ai_category, engineering_metrics.merge_requests.mr_ai_category)
+            WHERE engineering_metrics.merge_requests.updated_at <= EXCLUDED.updated_at
+            "#))
+            .bind(column(|mr| mr.mr_id.clone()))
+            .bind(column(|mr| mr.mr_iid.clone()))
+            .bind(column(|mr| mr.mr_title.clone()))
+            .bind(column(|mr| mr.mr_web_url.clone()))
+            .bind(column(|mr| mr.project_id.clone()))
+            .bind(column(|mr| mr.project_name.clone()))
+            .bind(column(|mr| mr.project_path.clone()))
+            .bind(merge_requests.iter().map(|mr| mr.created_at).collect::<Vec<_>>())
+            .bind(merge_requests.iter().map(|mr| mr.updated_at).collect::<Vec<_>>())
+            .bind(merge_requests.iter().map(|mr| mr.merged_at).collect::<Vec<_>>())
+            .bind(column(|mr| mr.created_by.clone()))
+            .bind(optional_column(|mr| mr.merged_by.clone()))
+            .bind(merge_requests.iter().map(|mr| mr.approved).collect::<Vec<_>>())
+            .bind(approved_by)
+            .bind(diff_stats_summaries)
+            .bind(labels)
+            .bind(optional_column(|mr| mr.mr_ai_title.clone()))
+            .bind(optional_column(|mr| mr.mr_ai_summary.clone()))
+            .bind(optional_column(|mr| mr.mr_ai_model.clone()))
+            .bind(optional_column(|mr| mr.mr_ai_category.clone()))
+            .bind(merge_requests.iter().map(|mr| mr.first_approved_at).collect::<Vec<_>>())
+            .bind(merge_requests.iter().map(|mr| mr.first_approval_checked_at).collect::<Vec<_>>())
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Import merged merge requests of a group, accumulating the run statistics into `stats`
     pub async fn import_merge_requests(
         &self,
         group_full_path: &str,
         updated_after: &str,
+        stats: &mut RunGroupStats,
     ) -> Result<(), MergeRequestError> {
         // Parse updated_after timestamp for filtering merged MRs
-        let updated_after_time = OffsetDateTime::parse(updated_after, &Rfc3339)?;
+        let mut updated_after_time = OffsetDateTime::parse(updated_after, &Rfc3339)?;
+        if self.context.reconcile_policy.enabled {
+            // Start before the watermark to catch MRs merged while the previous run was going on
+            updated_after_time -= self.context.reconcile_policy.overlap;
+        }
 
-        // Initialize import progress handler
-        let import_progress_handler = ImportProgressHandler {
-            store: self.context.store.clone(),
-        };
+        let import_progress_handler = self.import_progress_handler();
 
         // Get or resume existing import
         let import_progress = import_progress_handler
-            .get_or_create_import(group_full_path, "merge_requests", updated_after_time)
-            .await
-            .map_err(|e| MergeRequestError::DatabaseError(sqlx::Error::Protocol(e.to_string())))?;
+            .get_or_create_import(group_full_path, IMPORT_TYPE, updated_after_time)
+            .await?;
 
         // When resuming, use the original updated_after from the import_progress record
         // This ensures we continue with the same query parameters
         let effective_updated_after = import_progress.updated_after;
-        let effective_updated_after_str = effective_updated_after
-            .format(&Rfc3339)
-            .map_err(|e| MergeRequestError::MissingData(format!("Failed to format date: {}", e)))?;
+        let effective_updated_after_str = format_timestamp(effective_updated_after)?;
 
+        // Retry merge requests that failed on previous runs before importing new ones
+        let dead_letter_handler = self.dead_letter_handler();
+        self.retry_dead_letters(
+            &self.build_ai_client(),
+            &self.context.ai_model,
+            &dead_letter_handler,
+            group_full_path,
+            stats,
+        )
+        .await;
+
+        let window = ImportWindow {
+            filter: MergeRequestFilter {
+                updated_after: Some(effective_updated_after_str),
+                ..MergeRequestFilter::default()
+            },
+            // Only process MRs that were merged after the effective_updated_after time
+            // This ensures we don't re-process old MRs that were just updated (e.g., commented on)
+            merged_from: effective_updated_after,
+            merged_until: None,
+            reconcile: self.context.reconcile_policy.enabled,
+        };
+        self.import_pages(
+            group_full_path,
+            &import_progress_handler,
+            import_progress,
+            &window,
+            stats,
+        )
+        .await
+    }
+
+    /// Import the merge requests of a group merged within `[since, until)`, one calendar month at a time.
+    ///
+    /// Each month is tracked as its own import, so an interrupted backfill resumes at the month
+    /// and cursor it stopped at, and months that were already completed are skipped.
+    /// The incremental watermark in `collector_runs` is neither read nor advanced.
+    pub async fn backfill_merge_requests(
+        &self,
+        group_full_path: &str,
+        since: Date,
+        until: Date,
+        stats: &mut RunGroupStats,
+    ) -> Result<BackfillSummary, MergeRequestError> {
+        let import_progress_handler = self.import_progress_handler();
+        let mut summary = BackfillSummary::default();
+
+        for (window_start, window_end) in monthly_windows(since, until)? {
+            let import_type = backfill_import_type(window_start, window_end);
+            summary.windows_total += 1;
+
+            let latest_import = import_progress_handler
+                .find_latest_import(group_full_path, &import_type)
+                .await?;
+            if matches!(&latest_import, Some(import) if import.status == ImportStatus::Completed) {
+                info!(
+                    group = group_full_path,
+                    window_start = %window_start,
+                    window_end = %window_end,
+                    "Skipping backfill window: already completed"
+                );
+                summary.windows_skipped += 1;
+                continue;
+            }
+
+            let merged_from = window_start.midnight().assume_utc();
+            let merged_until = window_end.midnight().assume_utc();
+            let import_progress = import_progress_handler
+                .get_or_create_import(group_full_path, &import_type, merged_from)
+                .await?;
+            info!(
+                group = group_full_path,
+                window_start = %window_start,
+                window_end = %window_end,
+                "Backfilling merge requests merged in window"
+            );
+
+            let window = ImportWindow {
+                filter: MergeRequestFilter {
+                    merged_after: Some(format_timestamp(merged_from)?),
+                    merged_before: Some(format_timestamp(merged_until)?),
+                    ..MergeRequestFilter::default()
+                },
+                merged_from,
+                merged_until: Some(merged_until),
+                reconcile: self.context.reconcile_policy.enabled,
+            };
+            self.import_pages(
+                group_full_path,
+                &import_progress_handler,
+                import_progress,
+                &window,
+                stats,
+            )
+            .await?;
+            summary.windows_imported += 1;
+        }
+
+        Ok(summary)
+    }
+
+    /// Re-import merge requests of one project on behalf of a webhook, without tracking an import.
+    ///
+    /// Fetches the merge requests of the project with the given iids, or every merge request of
+    /// the project updated after `updated_after` when no iids are given. Merged ones go through
+    /// the same approval, AI summary and dead-letter handling as the polling import; stored
+    /// copies are refreshed even with upsert disabled.
+    pub async fn refresh_project_merge_requests(
+        &self,
+        group_full_path: &str,
+        project_path: &str,
+        iids: &[String],
+        updated_after: OffsetDateTime,
+        stats: &mut RunGroupStats,
+    ) -> Result<(), MergeRequestError> {
+        let filter = if iids.is_empty() {
+            MergeRequestFilter {
+                updated_after: Some(format_timestamp(updated_after)?),
+                ..MergeRequestFilter::default()
+            }
+        } else {
+            MergeRequestFilter {
+                iids: Some(iids.to_vec()),
+                ..MergeRequestFilter::default()
+            }
+        };
+        let window = ImportWindow {
+            filter,
+            merged_from: OffsetDateTime::UNIX_EPOCH,
+            merged_until: None,
+            reconcile: true,
+        };
+
+        let ai_client = self.build_ai_client();
+        let dead_letter_handler = self.dead_letter_handler();
+        let mut after_pointer_token = None;
+        loop {
+            if self.context.shutdown.is_cancelled() {
+                return Err(MergeRequestError::Interrupted);
+            }
+
+            stats.api_calls += 1;
+            let page = self
+                .fetch_filtered_project_merge_requests(
+                    project_path,
+                    &window.filter,
+                    after_pointer_token,
+                )
+                .await?;
+            let merge_requests = page.merge_requests;
+            stats.items_fetched += merge_requests.len() as i64;
+
+            for mut merge_request in self.select_for_import(merge_requests, &window, stats).await {
+                self.process_merge_request(
+                    &ai_client,
+                    &self.context.ai_model,
+                    &dead_letter_handler,
+                    group_full_path,
+                    &mut merge_request,
+                    stats,
+                )
+                .await;
+            }
+
+            if !page.page_info.has_next_page {
+                return Ok(());
+            }
+            after_pointer_token = page.page_info.end_cursor;
+        }
+    }
+
+    fn import_progress_handler(&self) -> ImportProgressHandler {
+        ImportProgressHandler {
+            store: self.context.store.clone(),
+            run_id: self.context.run_id,
+            policy: self.context.import_progress_policy.clone(),
+        }
+    }
+
+    fn dead_letter_handler(&self) -> DeadLetterHandler {
+        DeadLetterHandler {
+            store: self.context.store.clone(),
+            max_attempts: self.context.dead_letter_max_attempts,
+        }
+    }
+
+    /// Page through the merge requests of one tracked import, checkpointing the cursor after each page
+    #[instrument(
+        name = "import",
+        skip_all,
+        fields(import_id = %import_progress.id, import_type = %import_progress.import_type)
+    )]
+    async fn import_pages(
+        &self,
+        group_full_path: &str,
+        import_progress_handler: &ImportProgressHandler,
+        import_progress: ImportProgress,
+        window: &ImportWindow,
+        stats: &mut RunGroupStats,
+    ) -> Result<(), MergeRequestError> {
         let mut has_more_merge_requests = true;
         let mut after_pointer_token = import_progress.last_cursor.clone();
         let mut total_imported = import_progress.total_processed;
         let mut total_count: Option<i32> = None;
+        let mut resuming_from_cursor = after_pointer_token.is_some();
+        let mut page_number = 0;
 
-        // Get AI configuration from context
-        let ai_base_url = self.context.ai_base_url.clone();
         let ai_model = self.context.ai_model.clone();
-        let ai_api_key = self.context.ai_api_key.clone();
-
-        // Configure GenAI client with custom ServiceTargetResolver
-        let ai_base_url_clone = ai_base_url.clone();
-        let ai_api_key_clone = ai_api_key.clone();
+        let ai_client = self.build_ai_client();
+        let dead_letter_handler = self.dead_letter_handler();
 
-        let target_resolver = ServiceTargetResolver::from_resolver_fn(
-            move |service_target: ServiceTarget| -> Result<ServiceTarget, genai::resolver::Error> {
-                let ServiceTarget { model, .. } = service_target;
-                let endpoint = Endpoint::from_owned(ai_base_url_clone.clone());
-                let auth = AuthData::from_single(ai_api_key_clone.clone());
-                let model = ModelIden::new(AdapterKind::Ollama, model.model_name);
-                Ok(ServiceTarget {
-                    endpoint,
-                    auth,
-                    model,
-                })
-            },
-        );
+        while has_more_merge_requests {
+            // Stop between pages on shutdown; the checkpoint of the last page lets the next run resume
+            if self.context.shutdown.is_cancelled() {
+                if let Err(e) = import_progress_handler
+                    .mark_interrupted(import_progress.id)
+                    .await
+                {
+                    warn!(error = %e, "Failed to mark import as interrupted");
+                }
+                info!(
+                    group = group_full_path,
+                    cursor = ?after_pointer_token,
+                    "Import interrupted by shutdown, progress saved at cursor"
+                );
+                return Err(MergeRequestError::Interrupted);
+            }
 
-        let ai_client = GenAiClient::builder()
-            .with_service_target_resolver(target_resolver)
-            .build();
+            page_number += 1;
+            let page_span = info_span!(
+                "page",
+                page = page_number,
+                cursor = after_pointer_token.as_deref().unwrap_or("")
+            );
 
-        while has_more_merge_requests {
+            stats.api_calls += 1;
             let res = match self
-                .fetch_group_merge_requests(
+                .fetch_filtered_group_merge_requests(
                     group_full_path,
-                    &effective_updated_after_str,
+                    &window.filter,
                     after_pointer_token.clone(),
                 )
+                .instrument(page_span.clone())
                 .await
             {
                 Ok(r) => r,
+                Err(MergeRequestError::GraphQLError(e))
+                    if resuming_from_cursor
+                        && e.is_rejected_cursor()
+                        && self
+                            .context
+                            .import_progress_policy
+                            .restart_on_rejected_cursor =>
+                {
+                    // The saved cursor was rejected (e.g. it expired), start over from the first page
+                    warn!(
+                        group = group_full_path,
+                        cursor = ?after_pointer_token,
+                        error = %e,
+                        "Saved cursor was rejected, restarting import from the first page"
+                    );
+                    import_progress_handler
+                        .restart_from_first_page(import_progress.id)
+                        .await?;
+                    after_pointer_token = None;
+                    total_imported = 0;
+                    resuming_from_cursor = false;
+                    continue;
+                }
                 Err(e) => {
                     // Mark the import as failed but preserve the cursor for resume
                     let _ = import_progress_handler
                         .mark_failed(import_progress.id, &e.to_string())
                         .await;
-                    eprintln!(
-                        "Failed to fetch merge requests for group {}: {}. Progress saved at cursor: {:?}",
-                        group_full_path, e, after_pointer_token
+                    stats.record_error(&e);
+                    warn!(
+                        group = group_full_path,
+                        cursor = ?after_pointer_token,
+                        error = %e,
+                        "Failed to fetch merge requests, progress saved at cursor"
                     );
                     return Err(e);
                 }
             };
 
+            resuming_from_cursor = false;
             let batch_count = res.merge_requests.len();
+            stats.items_fetched += batch_count as i64;
 
             // Capture total count on first fetch (it's the same for all pages)
             if total_count.is_none() {
                 total_count = Some(res.total_count);
-                println!(
-                    "Total merge requests to process for group={}: {}",
-                    group_full_path, res.total_count
+                info!(
+                    group = group_full_path,
+                    total_count = res.total_count,
+                    "Total merge requests to process"
                 );
             }
 
-            println!(
-                "Fetched {} merge requests in this batch for group={}",
-                batch_count, group_full_path
-            );
+            page_span.in_scope(|| {
+                info!(
+                    group = group_full_path,
+                    batch_count, "Fetched merge requests in this batch"
+                )
+            });
 
-            let mut batch_processed = 0;
-            for mut merge_request in res.merge_requests {
-                // Only process MRs that were merged after the effective_updated_after time
-                // This ensures we don't re-process old MRs that were just updated (e.g., commented on)
-                match merge_request.merged_at {
-                    Some(merged_at) if merged_at >= effective_updated_after => {}
-                    _ => continue,
-                };
-
-                // If upsert is disabled, skip existing merge requests
-                if !self.context.upsert_merge_requests {
-                    match self.merge_request_exists(&merge_request.mr_id).await {
-                        Ok(true) => {
-                            println!(
-                                "Skipping existing merge request {} (upsert disabled)",
-                                merge_request.mr_web_url
-                            );
-                            continue;
-                        }
-                        Ok(false) => {} // MR doesn't exist, proceed with ingestion
-                        Err(e) => {
-                            eprintln!(
-                                "Failed to check if merge request {} exists: {}",
-                                merge_request.mr_id, e
-                            );
-                            // Continue with ingestion on error to be safe
-                        }
-                    }
-                }
+            // The page and its checkpoint are stored together, so a resumed import skips none
+            let next_cursor = res.page_info.end_cursor.as_deref();
+            total_imported += self
+                .import_page(
+                    &ai_client,
+                    &ai_model,
+                    &dead_letter_handler,
+                    import_progress_handler,
+                    import_progress.id,
+                    group_full_path,
+                    window,
+                    res.merge_requests,
+                    next_cursor,
+                    stats,
+                )
+                .instrument(page_span.clone())
+                .await;
 
-                // Generate AI summary for merged MRs
-                match self
-                    .generate_ai_summary(&ai_client, &ai_model, &merge_request)
-                    .await
-                {
-                    Ok((title, summary, category)) => {
-                        merge_request.mr_ai_title = Some(title);
-                        merge_request.mr_ai_summary = Some(summary);
-                        merge_request.mr_ai_category = Some(category);
-                        merge_request.mr_ai_model = Some(ai_model.clone());
+            page_span.in_scope(|| {
+                info!(
+                    group = group_full_path,
+                    processed = total_imported,
+                    total = total_count,
+                    "Progress of merge requests processed"
+                )
+            });
+
+            after_pointer_token = res.page_info.end_cursor;
+            has_more_merge_requests = res.page_info.has_next_page;
+        }
+
+        // Mark import as completed
+        if let Err(e) = import_progress_handler
+            .mark_completed(import_progress.id)
+            .await
+        {
+            warn!(error = %e, "Failed to mark import as completed");
+        }
+
+        info!(
+            group = group_full_path,
+            total_imported, "Done importing merge requests"
+        );
+
+        Ok(())
+    }
+
+    /// Import the merge requests of one fetched page and checkpoint the import at `next_cursor`.
+    ///
+    /// Eligible merge requests get their changes and AI summaries concurrently, then the whole
+    /// page is upserted in the transaction that moves the cursor. Returns the number persisted.
+    #[allow(clippy::too_many_arguments)]
+    async fn import_page(
+        &self,
+        ai_client: &GenAiClient,
+        ai_model: &str,
+        dead_letter_handler: &DeadLetterHandler,
+        import_progress_handler: &ImportProgressHandler,
+        import_id: Uuid,
+        group_full_path: &str,
+        window: &ImportWindow,
+        merge_requests: Vec<MergeRequest>,
+        next_cursor: Option<&str>,
+        stats: &mut RunGroupStats,
+    ) -> i32 {
+        let merge_requests = self.select_for_import(merge_requests, window, stats).await;
+
+        let page_concurrency = self.context.max_concurrent_merge_requests.max(1);
+        let summarized: Vec<(MergeRequest, Option<Failure>, RunGroupStats)> =
+            stream::iter(merge_requests)
+                .map(|mut merge_request| async move {
+                    let mut merge_request_stats = RunGroupStats::default();
+                    let merge_request_span = info_span!(
+                        "merge_request",
+                        mr_id = %merge_request.mr_id,
+                        mr_iid = %merge_request.mr_iid
+                    );
+                    let failure = async {
+                        self.record_first_approval(&mut merge_request, &mut merge_request_stats)
+                            .await;
+                        self.summarize_merge_request(
+                            ai_client,
+                            ai_model,
+                            &mut merge_request,
+                            &mut merge_request_stats,
+                        )
+                        .await
                     }
+                    .instrument(merge_request_span)
+                    .await;
+                    (merge_request, failure, merge_request_stats)
+                })
+                .buffer_unordered(page_concurrency)
+                .collect()
+                .await;
+
+        let mut outcomes: Vec<(MergeRequest, Option<Failure>)> =
+            Vec::with_capacity(summarized.len());
+        for (merge_request, failure, merge_request_stats) in summarized {
+            stats.merge(merge_request_stats);
+            outcomes.push((merge_request, failure));
+        }
+
+        let page: Vec<&MergeRequest> = outcomes.iter().map(|(mr, _)| mr).collect();
+        let page_size = page.len();
+        let write_started_at = Instant::now();
+        let page_result = self
+            .persist_page(&page, import_progress_handler, import_id, next_cursor)
+            .instrument(info_span!("persist", merge_requests = page_size))
+            .await;
+        metrics::observe_db_write("merge_request_page", write_started_at.elapsed());
+
+        if let Err(e) = page_result {
+            // A single bad row fails the whole statement, so the rows are stored one by one
+            // and only the failing ones are dead-lettered
+            warn!(
+                error = %e,
+                merge_requests = page_size,
+                "Failed to persist the page in one transaction, persisting merge requests one by one"
+            );
+            let mut persisted = 0;
+            for (merge_request, failure) in &mut outcomes {
+                let write_started_at = Instant::now();
+                let result = self
+                    .persist_merge_request(merge_request)
+                    .instrument(info_span!("persist", mr_id = %merge_request.mr_id))
+                    .await;
+                metrics::observe_db_write("merge_request", write_started_at.elapsed());
+                match result {
+                    Ok(()) => persisted += 1,
                     Err(e) => {
-                        eprintln!(
-                            "Failed to generate AI summary for MR {}: {}",
-                            merge_request.mr_web_url, e
+                        warn!(
+                            mr_web_url = %merge_request.mr_web_url,
+                            error = %e,
+                            "Failed to persist merge request"
                         );
+                        *failure = Some((DeadLetterStage::Persist, e.to_string()));
                     }
                 }
+            }
+            if let Err(e) = import_progress_handler
+                .update_progress(import_id, next_cursor, persisted)
+                .await
+            {
+                warn!(error = %e, "Failed to update import progress");
+            }
+        }
 
-                if let Err(e) = self.persist_merge_request(&merge_request).await {
-                    eprintln!(
-                        "Failed to persist merge request {}: {}",
-                        merge_request.mr_iid, e
-                    );
+        let mut persisted = 0;
+        let mut imported_mr_ids = Vec::new();
+        for (merge_request, failure) in outcomes {
+            if count_outcome(group_full_path, &merge_request, failure.as_ref(), stats) {
+                persisted += 1;
+            }
+            match failure {
+                Some((stage, error)) => {
+                    record_dead_letter(
+                        dead_letter_handler,
+                        group_full_path,
+                        &merge_request,
+                        stage,
+                        &error,
+                    )
+                    .await
                 }
-                batch_processed += 1;
-                total_imported += 1;
+                None => imported_mr_ids.push(merge_request.mr_id),
             }
+        }
+        if let Err(e) = dead_letter_handler.resolve_all(&imported_mr_ids).await {
+            warn!(error = %e, "Failed to resolve dead letters of imported merge requests");
+        }
 
-            // Update progress after each batch - this is our checkpoint
-            let next_cursor = res.page_info.end_cursor.as_deref();
-            if let Err(e) = import_progress_handler
-                .update_progress(import_progress.id, next_cursor, batch_processed)
+        persisted
+    }
+
+    /// Keep the merge requests of a page that are imported.
+    ///
+    /// Those merged outside the window and, with upsert disabled, those already stored are
+    /// dropped; in reconciliation mode their stored copies are refreshed instead.
+    async fn select_for_import(
+        &self,
+        merge_requests: Vec<MergeRequest>,
+        window: &ImportWindow,
+        stats: &mut RunGroupStats,
+    ) -> Vec<MergeRequest> {
+        let mut in_window = Vec::with_capacity(merge_requests.len());
+        for merge_request in merge_requests {
+            // Only process MRs that were merged within the window, so old MRs that were
+            // just updated (e.g., commented on) are not re-processed
+            if window.contains(merge_request.merged_at) {
+                in_window.push(merge_request);
+            } else if window.reconcile && merge_request.merged_at.is_some() {
+                // In reconciliation mode, stored MRs merged before the window still get
+                // their labels and approvals refreshed
+                self.reconcile_stored_merge_request(&merge_request, stats)
+                    .await;
+            } else {
+                stats.items_filtered += 1;
+            }
+        }
+
+        if self.context.upsert_merge_requests {
+            return in_window;
+        }
+
+        // If upsert is disabled, skip existing merge requests
+        let mr_ids: Vec<String> = in_window.iter().map(|mr| mr.mr_id.clone()).collect();
+        let existing = match self.existing_merge_request_ids(&mr_ids).await {
+            Ok(existing) => existing,
+            Err(e) => {
+                warn!(error = %e, "Failed to check which merge requests exist");
+                // Continue with ingestion on error to be safe
+                HashSet::new()
+            }
+        };
+
+        let mut selected = Vec::with_capacity(in_window.len());
+        for merge_request in in_window {
+            if !existing.contains(&merge_request.mr_id) {
+                selected.push(merge_request);
+            } else if window.reconcile {
+                self.reconcile_stored_merge_request(&merge_request, stats)
+                    .await;
+            } else {
+                info!(
+                    mr_id = %merge_request.mr_id,
+                    mr_web_url = %merge_request.mr_web_url,
+                    "Skipping existing merge request (upsert disabled)"
+                );
+                stats.items_skipped_existing += 1;
+            }
+        }
+        selected
+    }
+
+    /// Generate AI summaries for stored merge requests that have none yet, oldest merges first.
+    ///
+    /// Only merge requests of projects imported for `group_full_path` are considered when a group is given.
+    pub async fn backfill_ai_summaries(
+        &self,
+        group_full_path: Option<&str>,
+        limit: i64,
+        stats: &mut RunGroupStats,
+    ) -> Result<(), MergeRequestError> {
+        let candidates = self
+            .fetch_merge_requests_without_summary(group_full_path, limit)
+            .await?;
+        stats.items_fetched += candidates.len() as i64;
+
+        info!(
+            candidates = candidates.len(),
+            "Backfilling AI summaries for merge requests"
+        );
+
+        let ai_model = self.context.ai_model.clone();
+        let ai_client = self.build_ai_client();
+
+        for candidate in candidates {
+            // Summaries are stored one by one, so the remaining candidates are picked up next run
+            if self.context.shutdown.is_cancelled() {
+                return Err(MergeRequestError::Interrupted);
+            }
+
+            let mr_span = info_span!("merge_request", mr_id = %candidate.mr_id);
+            stats.api_calls += 1;
+            let changes = match self
+                .fetch_merge_request_changes(&candidate.project_id, &candidate.mr_iid)
+                .instrument(info_span!(parent: &mr_span, "fetch_changes"))
                 .await
             {
-                eprintln!("Failed to update import progress: {}", e);
+                Ok(changes) => changes,
+                Err(e) => {
+                    warn!(
+                        mr_id = %candidate.mr_id,
+                        mr_web_url = %candidate.mr_web_url,
+                        error = %e,
+                        "Failed to fetch merge request changes"
+                    );
+                    stats.items_failed += 1;
+                    stats.record_error(format!("fetch failed for {}: {}", candidate.mr_id, e));
+                    continue;
+                }
+            };
+
+            let ai_started_at = Instant::now();
+            let ai_result = self
+                .generate_ai_summary(
+                    &ai_client,
+                    &ai_model,
+                    &candidate.mr_title,
+                    None,
+                    &changes,
+                    stats,
+                )
+                .instrument(info_span!(parent: &mr_span, "ai_summary", model = %ai_model))
+                .await;
+            stats.ai_time_ms += ai_started_at.elapsed().as_millis() as i64;
+
+            let (title, summary, category) = match ai_result {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!(
+                        mr_id = %candidate.mr_id,
+                        mr_web_url = %candidate.mr_web_url,
+                        error = %e,
+                        "Failed to generate AI summary"
+                    );
+                    stats.items_failed += 1;
+                    stats.record_error(format!("ai failed for {}: {}", candidate.mr_id, e));
+                    continue;
+                }
+            };
+            stats.items_summarized += 1;
+
+            let write_started_at = Instant::now();
+            let result = self
+                .update_ai_summary(&candidate.mr_id, &title, &summary, &category, &ai_model)
+                .instrument(info_span!(parent: &mr_span, "persist"))
+                .await;
+            metrics::observe_db_write("merge_request_ai_summary", write_started_at.elapsed());
+            match result {
+                Ok(()) => stats.items_persisted += 1,
+                Err(e) => {
+                    warn!(
+                        mr_id = %candidate.mr_id,
+                        mr_web_url = %candidate.mr_web_url,
+                        error = %e,
+                        "Failed to store AI summary"
+                    );
+                    stats.items_failed += 1;
+                    stats.record_error(format!("persist failed for {}: {}", candidate.mr_id, e));
+                }
             }
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_merge_requests_without_summary(
+        &self,
+        group_full_path: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<SummaryCandidate>, MergeRequestError> {
+        let mut conn = self.context.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(&self.context.store.sql(
+            r#"
+            SELECT mr.mr_id, mr.mr_iid, mr.mr_title, mr.mr_web_url, mr.project_id
+            FROM engineering_metrics.merge_requests mr
+            LEFT JOIN engineering_metrics.projects p
+                ON p.p_id = 'gid://gitlab/Project/' || mr.project_id
+            WHERE mr.mr_ai_summary IS NULL
+              AND ($1::VARCHAR IS NULL OR p.group_full_path = $1)
+            ORDER BY mr.merged_at NULLS LAST
+            LIMIT $2
+            "#,
+        ))
+        .bind(group_full_path)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| SummaryCandidate {
+                mr_id: row.get(0),
+                mr_iid: row.get(1),
+                mr_title: row.get(2),
+                mr_web_url: row.get(3),
+                project_id: row.get(4),
+            })
+            .collect())
+    }
 
-            // Display progress with total count if available
-            match total_count {
-                Some(total) => println!(
-                    "Progress: {}/{} merge requests processed for group={}",
-                    total_imported, total, group_full_path
-                ),
-                None => println!(
-                    "Progress: {} merge requests processed for group={}",
-                    total_imported, group_full_path
-                ),
+    async fn update_ai_summary(
+        &self,
+        mr_id: &str,
+        title: &str,
+        summary: &str,
+        category: &str,
+        model: &str,
+    ) -> Result<(), MergeRequestError> {
+        let mut conn = self.context.store.conn_pool.acquire().await?;
+
+        sqlx::query(&self.context.store.sql(
+            r#"
+            UPDATE engineering_metrics.merge_requests
+            SET mr_ai_title = $2,
+                mr_ai_summary = $3,
+                mr_ai_category = $4,
+                mr_ai_model = $5
+            WHERE mr_id = $1
+            "#,
+        ))
+        .bind(mr_id)
+        .bind(title)
+        .bind(summary)
+        .bind(category)
+        .bind(model)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retry the pending dead letters of a group. Successfully imported merge requests are removed
+    /// from the dead-letter table, failures bump their attempt count.
+    async fn retry_dead_letters(
+        &self,
+        ai_client: &GenAiClient,
+        ai_model: &str,
+        dead_letter_handler: &DeadLetterHandler,
+        group_full_path: &str,
+        stats: &mut RunGroupStats,
+    ) {
+        let dead_letters = match dead_letter_handler.fetch_pending(group_full_path).await {
+            Ok(dead_letters) => dead_letters,
+            Err(e) => {
+                warn!(group = group_full_path, error = %e, "Failed to fetch dead letters");
+                return;
             }
+        };
 
-            after_pointer_token = res.page_info.end_cursor;
-            has_more_merge_requests = res.page_info.has_next_page;
+        if dead_letters.is_empty() {
+            return;
         }
 
-        // Mark import as completed
-        if let Err(e) = import_progress_handler
-            .mark_completed(import_progress.id)
+        info!(
+            group = group_full_path,
+            dead_letters = dead_letters.len(),
+            "Retrying failed merge requests"
+        );
+
+        for dead_letter in dead_letters {
+            let mut merge_request = match MergeRequest::deserialize(&dead_letter.payload) {
+                Ok(merge_request) => merge_request,
+                Err(e) => {
+                    warn!(
+                        mr_id = %dead_letter.mr_id,
+                        error = %e,
+                        "Failed to deserialize dead letter"
+                    );
+                    // Count it as a failed attempt so an unreadable payload is eventually parked
+                    if let Err(e) = dead_letter_handler
+                        .record_failure(
+                            &dead_letter.mr_id,
+                            group_full_path,
+                            dead_letter.stage,
+                            &format!("Failed to deserialize dead letter: {}", e),
+                            &dead_letter.payload,
+                        )
+                        .await
+                    {
+                        warn!(error = %e, "Failed to update dead letter for merge request");
+                    }
+                    continue;
+                }
+            };
+
+            self.process_merge_request(
+                ai_client,
+                ai_model,
+                dead_letter_handler,
+                group_full_path,
+                &mut merge_request,
+                stats,
+            )
+            .await;
+        }
+    }
+
+    /// Summarize and persist a single merge request, recording a dead letter for the stage that failed.
+    ///
+    /// A merge request whose summary could not be generated is still persisted without AI fields.
+    /// Returns whether the merge request was persisted.
+    #[instrument(
+        name = "merge_request",
+        skip_all,
+        fields(mr_id = %merge_request.mr_id, mr_iid = %merge_request.mr_iid)
+    )]
+    async fn process_merge_request(
+        &self,
+        ai_client: &GenAiClient,
+        ai_model: &str,
+        dead_letter_handler: &DeadLetterHandler,
+        group_full_path: &str,
+        merge_request: &mut MergeRequest,
+        stats: &mut RunGroupStats,
+    ) -> bool {
+        self.record_first_approval(merge_request, stats).await;
+        let mut failure = self
+            .summarize_merge_request(ai_client, ai_model, merge_request, stats)
+            .await;
+
+        let write_started_at = Instant::now();
+        let persist_result = self
+            .persist_merge_request(merge_request)
+            .instrument(info_span!("persist"))
+            .await;
+        metrics::observe_db_write("merge_request", write_started_at.elapsed());
+        if let Err(e) = persist_result {
+            warn!(
+                mr_web_url = %merge_request.mr_web_url,
+                error = %e,
+                "Failed to persist merge request"
+            );
+            failure = Some((DeadLetterStage::Persist, e.to_string()));
+        }
+
+        let persisted = count_outcome(group_full_path, merge_request, failure.as_ref(), stats);
+        match failure {
+            Some((stage, error)) => {
+                record_dead_letter(
+                    dead_letter_handler,
+                    group_full_path,
+                    merge_request,
+                    stage,
+                    &error,
+                )
+                .await
+            }
+            None => {
+                if let Err(e) = dead_letter_handler.resolve(&merge_request.mr_id).await {
+                    warn!(error = %e, "Failed to update dead letter for merge request");
+                }
+            }
+        }
+
+        persisted
+    }
+
+    /// Fetch the changes of a merge request and fill in its AI fields when summaries are enabled,
+    /// returning the stage that failed
+    async fn summarize_merge_request(
+        &self,
+        ai_client: &GenAiClient,
+        ai_model: &str,
+        merge_request: &mut MergeRequest,
+        stats: &mut RunGroupStats,
+    ) -> Option<Failure> {
+        // Dead letters retried after a persist failure already carry their summary
+        if !self.context.ai_summaries_enabled || merge_request.mr_ai_summary.is_some() {
+            return None;
+        }
+
+        stats.api_calls += 1;
+        let changes = match self
+            .fetch_merge_request_changes(&merge_request.project_id, &merge_request.mr_iid)
+            .instrument(info_span!("fetch_changes"))
+            .await
+        {
+            Ok(changes) => changes,
+            Err(e) => {
+                warn!(
+                    mr_web_url = %merge_request.mr_web_url,
+                    error = %e,
+                    "Failed to fetch merge request changes"
+                );
+                return Some((DeadLetterStage::Fetch, e.to_string()));
+            }
+        };
+
+        let ai_started_at = Instant::now();
+        let ai_result = self
+            .generate_ai_summary(
+                ai_client,
+                ai_model,
+                &merge_request.mr_title,
+                merge_request.mr_description.as_deref(),
+                &changes,
+                stats,
+            )
+            .instrument(info_span!("ai_summary", model = %ai_model))
+            .await;
+        stats.ai_time_ms += ai_started_at.elapsed().as_millis() as i64;
+
+        match ai_result {
+            Ok((title, summary, category)) => {
+                stats.items_summarized += 1;
+                merge_request.mr_ai_title = Some(title);
+                merge_request.mr_ai_summary = Some(summary);
+                merge_request.mr_ai_category = Some(category);
+                merge_request.mr_ai_model = Some(ai_model.to_string());
+                None
+            }
+            Err(e) => {
+                warn!(
+                    mr_web_url = %merge_request.mr_web_url,
+                    error = %e,
+                    "Failed to generate AI summary"
+                );
+                Some((DeadLetterStage::Ai, e.to_string()))
+            }
+        }
+    }
+
+    /// Look up when an approved merge request was first approved, unless its notes were already
+    /// searched. A failed lookup only leaves it empty, as the merge request itself is complete.
+    async fn record_first_approval(
+        &self,
+        merge_request: &mut MergeRequest,
+        stats: &mut RunGroupStats,
+    ) {
+        let has_approvals = merge_request
+            .approved_by
+            .as_ref()
+            .is_some_and(|approved_by| !approved_by.is_empty());
+        if !self.context.fetch_approval_times
+            || !has_approvals
+            || merge_request.first_approved_at.is_some()
+            || merge_request.first_approval_checked_at.is_some()
+        {
+            return;
+        }
+
+        match self.stored_first_approval(&merge_request.mr_id).await {
+            Ok(Some((first_approved_at, checked_at)))
+                if first_approved_at.is_some() || checked_at.is_some() =>
+            {
+                merge_request.first_approved_at = first_approved_at;
+                merge_request.first_approval_checked_at = checked_at;
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!(
+                    mr_web_url = %merge_request.mr_web_url,
+                    error = %e,
+                    "Failed to read the stored first approval of merge request"
+                );
+                return;
+            }
+        }
+
+        match self
+            .fetch_first_approval_at(&merge_request.project_id, &merge_request.mr_iid, stats)
+            .instrument(info_span!("fetch_first_approval"))
             .await
         {
-            eprintln!("Failed to mark import as completed: {}", e);
+            Ok(first_approved_at) => {
+                merge_request.first_approved_at = first_approved_at;
+                merge_request.first_approval_checked_at = Some(OffsetDateTime::now_utc());
+            }
+            Err(e) => warn!(
+                mr_web_url = %merge_request.mr_web_url,
+                error = %e,
+                "Failed to fetch the first approval of merge request"
+            ),
         }
+    }
 
-        println!(
-            "Done importing merge requests data for group={}. Total imported: {}",
-            &group_full_path, total_imported
+    /// The stored first approval of a merge request and when it was looked up
+    async fn stored_first_approval(
+        &self,
+        mr_id: &str,
+    ) -> Result<Option<(Option<OffsetDateTime>, Option<OffsetDateTime>)>, MergeRequestError> {
+        let mut conn = self.context.store.conn_pool.acquire().await?;
+        let row = sqlx::query(&self.context.store.sql(
+            r#"
+            SELECT first_approved_at, first_approval_checked_at
+            FROM engineering_metrics.merge_requests
+            WHERE mr_id = $1
+            "#,
+        ))
+        .bind(mr_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(row.map(|row| (row.get(0), row.get(1))))
+    }
+
+    /// Time of the first approval of a merge request, reading its notes page by page until the
+    /// approval note is found. Gives up after [`MAX_APPROVAL_NOTE_PAGES`] pages.
+    pub async fn fetch_first_approval_at(
+        &self,
+        project_id: &str,
+        merge_request_iid: &str,
+        stats: &mut RunGroupStats,
+    ) -> Result<Option<OffsetDateTime>, MergeRequestError> {
+        let mut page = "1".to_string();
+        for _ in 0..MAX_APPROVAL_NOTE_PAGES {
+            stats.api_calls += 1;
+            let notes = {
+                let _permit = self
+                    .context
+                    .scheduler
+                    .acquire(self.context.gitlab_rest_client.endpoint())
+                    .await?;
+                self.context
+                    .gitlab_rest_client
+                    .fetch_approval_note_page(project_id, merge_request_iid, &page)
+                    .await?
+            };
+            if notes.first_approved_at.is_some() {
+                return Ok(notes.first_approved_at);
+            }
+            match notes.next_page {
+                Some(next_page) => page = next_page,
+                None => return Ok(None),
+            }
+        }
+        Ok(None)
+    }
+
+    async fn fetch_merge_request_changes(
+        &self,
+        project_id: &str,
+        merge_request_iid: &str,
+    ) -> Result<Vec<Change>, MergeRequestError> {
+        let _permit = self
+            .context
+            .scheduler
+            .acquire(self.context.gitlab_rest_client.endpoint())
+            .await?;
+        Ok(self
+            .context
+            .gitlab_rest_client
+            .fetch_merge_request_changes(project_id, merge_request_iid)
+            .await?)
+    }
+
+    fn build_ai_client(&self) -> GenAiClient {
+        // Configure GenAI client with custom ServiceTargetResolver
+        let ai_base_url = self.context.ai_base_url.clone();
+        let ai_api_key = self.context.ai_api_key.clone();
+
+        let target_resolver = ServiceTargetResolver::from_resolver_fn(
+            move |service_target: ServiceTarget| -> Result<ServiceTarget, genai::resolver::Error> {
+                let ServiceTarget { model, .. } = service_target;
+                let endpoint = Endpoint::from_owned(ai_base_url.clone());
+                let auth = AuthData::from_single(ai_api_key.clone());
+                let model = ModelIden::new(AdapterKind::Ollama, model.model_name);
+                Ok(ServiceTarget {
+                    endpoint,
+                    auth,
+                    model,
+                })
+            },
         );
 
-        Ok(())
+        GenAiClient::builder()
+            .with_service_target_resolver(target_resolver)
+            .build()
     }
 
     async fn generate_ai_summary(
         &self,
         ai_client: &GenAiClient,
         ai_model: &str,
-        mr: &MergeRequest,
+        title: &str,
+        description: Option<&str>,
+        changes: &[Change],
+        stats: &mut RunGroupStats,
     ) -> Result<(String, String, String), Box<dyn std::error::Error + Send + Sync>> {
-        let changes = self
-            .context
-            .gitlab_rest_client
-            .fetch_merge_request_changes(&mr.project_id, &mr.mr_iid)
-            .await?;
-
         // Convert changes to string representation, limited to configured max characters
         let mut changes_diff = String::new();
         let max_chars = self.context.ai_max_context_chars;
@@ -516,8 +1765,8 @@ PR Title: {}
 PR Description: {}
 PR Changes:
 {}"#,
-            mr.mr_title,
-            mr.mr_description.as_deref().unwrap_or(""),
+            title,
+            description.unwrap_or(""),
             changes_diff
         );
 
@@ -526,13 +1775,25 @@ PR Changes:
 
         for attempt in 1..=MAX_RETRIES {
             let chat_req = ChatRequest::new(vec![ChatMessage::user(prompt.clone())]);
+            stats.api_calls += 1;
 
-            let response = match ai_client.exec_chat(ai_model, chat_req, None).await {
+            let permit = self
+                .context
+                .scheduler
+                .acquire(&self.context.ai_base_url)
+                .await?;
+            let ai_started_at = Instant::now();
+            let response = ai_client.exec_chat(ai_model, chat_req, None).await;
+            drop(permit);
+            let response = match response {
                 Ok(resp) => resp,
                 Err(e) => {
-                    eprintln!(
-                        "AI request failed (attempt {}/{}): {}",
-                        attempt, MAX_RETRIES, e
+                    metrics::observe_ai_request(ai_model, ai_started_at.elapsed(), Some("request"));
+                    warn!(
+                        attempt,
+                        max_attempts = MAX_RETRIES,
+                        error = %e,
+                        "AI request failed"
                     );
                     last_error = Some(Box::new(e));
                     continue;
@@ -549,14 +1810,23 @@ PR Changes:
                 .trim_end_matches("```")
                 .trim();
 
-            match serde_json::from_str::<AiResponse>(clean_content) {
+            let parsed = serde_json::from_str::<AiResponse>(clean_content);
+            metrics::observe_ai_request(
+                ai_model,
+                ai_started_at.elapsed(),
+                parsed.is_err().then_some("parse"),
+            );
+            match parsed {
                 Ok(ai_resp) => {
                     return Ok((ai_resp.title, ai_resp.summary, ai_resp.category));
                 }
                 Err(e) => {
-                    eprintln!(
-                        "Failed to parse AI response as JSON (attempt {}/{}): {}. Response: {}",
-                        attempt, MAX_RETRIES, e, clean_content
+                    warn!(
+                        attempt,
+                        max_attempts = MAX_RETRIES,
+                        error = %e,
+                        response = clean_content,
+                        "Failed to parse AI response as JSON"
                     );
                     last_error = Some(Box::new(e));
                 }
@@ -570,3 +1840,66 @@ PR Changes:
         }))
     }
 }
+
+/// Stage at which a merge request failed and the error, as recorded in the dead-letter table
+type Failure = (DeadLetterStage, String);
+
+/// Count a processed merge request into `stats` and the metrics, returning whether it was persisted
+fn count_outcome(
+    group_full_path: &str,
+    merge_request: &MergeRequest,
+    failure: Option<&Failure>,
+    stats: &mut RunGroupStats,
+) -> bool {
+    let persisted = !matches!(failure, Some((DeadLetterStage::Persist, _)));
+    if persisted {
+        stats.items_persisted += 1;
+    }
+    let outcome = if failure.is_none() {
+        "persisted"
+    } else {
+        "failed"
+    };
+    metrics::count_merge_request(group_full_path, outcome);
+    if let Some((stage, error)) = failure {
+        stats.items_failed += 1;
+        stats.record_error(format!(
+            "{} failed for {}: {}",
+            stage.as_str(),
+            merge_request.mr_id,
+            error
+        ));
+    }
+    persisted
+}
+
+async fn record_dead_letter(
+    dead_letter_handler: &DeadLetterHandler,
+    group_full_path: &str,
+    merge_request: &MergeRequest,
+    stage: DeadLetterStage,
+    error: &str,
+) {
+    let payload = match serde_json::to_value(merge_request) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!(
+                error = %e,
+                "Failed to serialize merge request for the dead-letter table"
+            );
+            return;
+        }
+    };
+    if let Err(e) = dead_letter_handler
+        .record_failure(
+            &merge_request.mr_id,
+            group_full_path,
+            stage,
+            error,
+            &payload,
+        )
+        .await
+    {
+        warn!(error = %e, "Failed to update dead letter for merge request");
+    }
+}
@@ -1,6 +1,16 @@
 pub mod collector_runs;
 pub mod copilot_collector_runs;
+pub mod copilot_delivery;
 pub mod copilot_metrics;
+pub mod dead_letter;
+pub mod export;
+pub mod flow_metrics;
 pub mod import_progress;
 pub mod merge_request;
+pub mod people;
 pub mod project;
+pub mod query;
+pub mod rollups;
+pub mod run_history;
+pub mod run_lock;
+pub mod webhook;
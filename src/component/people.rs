@@ -0,0 +1,835 @@
+use serde::Deserialize;
+use sqlx::Row;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum PeopleError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("Failed to parse the people directory: {0}")]
+    ParseError(String),
+    #[error("Invalid people directory:\n  - {}", .0.join("\n  - "))]
+    InvalidDirectory(Vec<String>),
+    #[error("No pending match candidate {0}")]
+    UnknownCandidate(Uuid),
+}
+
+/// System an alias of a person belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdentitySource {
+    /// GitLab username, as in `merge_requests.created_by`
+    Gitlab,
+    /// GitHub login, as in `copilot_user_daily_metrics.user_login`
+    Github,
+}
+
+impl IdentitySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdentitySource::Gitlab => "gitlab",
+            IdentitySource::Github => "github",
+        }
+    }
+}
+
+impl FromStr for IdentitySource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gitlab" => Ok(IdentitySource::Gitlab),
+            "github" => Ok(IdentitySource::Github),
+            _ => Err(format!("Unknown identity source: {}", s)),
+        }
+    }
+}
+
+/// File format of a people directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectoryFormat {
+    Yaml,
+    Csv,
+}
+
+impl DirectoryFormat {
+    /// Format of a directory file by its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "yaml" | "yml" => Some(DirectoryFormat::Yaml),
+            "csv" => Some(DirectoryFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for DirectoryFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "yaml" => Ok(DirectoryFormat::Yaml),
+            "csv" => Ok(DirectoryFormat::Csv),
+            _ => Err(format!("Unknown directory format: {}", s)),
+        }
+    }
+}
+
+/// A person as listed in a directory file, with lowercased email and aliases
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DirectoryPerson {
+    pub email: String,
+    pub name: String,
+    pub gitlab: Vec<String>,
+    pub github: Vec<String>,
+    pub teams: Vec<DirectoryMembership>,
+}
+
+/// Membership of a team; both dates are inclusive and an absent date leaves that end open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryMembership {
+    pub team: String,
+    pub valid_from: Option<Date>,
+    pub valid_to: Option<Date>,
+}
+
+#[derive(Deserialize)]
+struct YamlDirectory {
+    people: Vec<YamlPerson>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct YamlPerson {
+    name: String,
+    email: String,
+    #[serde(default)]
+    gitlab: Vec<String>,
+    #[serde(default)]
+    github: Vec<String>,
+    #[serde(default)]
+    teams: Vec<YamlMembership>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct YamlMembership {
+    team: String,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+/// One CSV row per person and team; aliases are separated by `;` or spaces
+#[derive(Deserialize)]
+struct CsvRow {
+    name: String,
+    email: String,
+    #[serde(default)]
+    gitlab: String,
+    #[serde(default)]
+    github: String,
+    #[serde(default)]
+    team: String,
+    #[serde(default)]
+    from: String,
+    #[serde(default)]
+    to: String,
+}
+
+/// Parse a people directory. Entries sharing an email are merged into one person, so a CSV
+/// file lists a person once per team. Every problem of the file is reported at once.
+pub fn parse_directory(
+    content: &str,
+    format: DirectoryFormat,
+) -> Result<Vec<DirectoryPerson>, PeopleError> {
+    let mut directory = DirectoryBuilder::default();
+
+    match format {
+        DirectoryFormat::Yaml => {
+            let parsed: YamlDirectory = serde_yaml::from_str(content)
+                .map_err(|e| PeopleError::ParseError(e.to_string()))?;
+            for (index, person) in parsed.people.into_iter().enumerate() {
+                let entry = format!("person {}", index + 1);
+                let teams = person
+                    .teams
+                    .into_iter()
+                    .map(|membership| (membership.team, membership.from, membership.to))
+                    .collect();
+                directory.add(
+                    &entry,
+                    &person.name,
+                    &person.email,
+                    person.gitlab,
+                    person.github,
+                    teams,
+                );
+            }
+        }
+        DirectoryFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(content.as_bytes());
+            let headers = reader
+                .headers()
+                .map_err(|e| PeopleError::ParseError(e.to_string()))?
+                .clone();
+            for record in reader.records() {
+                let record = record.map_err(|e| PeopleError::ParseError(e.to_string()))?;
+                let row: CsvRow = record
+                    .deserialize(Some(&headers))
+                    .map_err(|e| PeopleError::ParseError(e.to_string()))?;
+                let line = record.position().map_or(0, |position| position.line());
+                let entry = format!("line {}", line);
+                let teams = if row.team.is_empty() && row.from.is_empty() && row.to.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![(row.team, non_empty(row.from), non_empty(row.to))]
+                };
+                directory.add(
+                    &entry,
+                    &row.name,
+                    &row.email,
+                    split_aliases(&row.gitlab),
+                    split_aliases(&row.github),
+                    teams,
+                );
+            }
+        }
+    }
+
+    directory.build()
+}
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+fn split_aliases(aliases: &str) -> Vec<String> {
+    aliases
+        .split(|c: char| c == ';' || c.is_whitespace())
+        .filter(|alias| !alias.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+#[derive(Default)]
+struct DirectoryBuilder {
+    people: Vec<DirectoryPerson>,
+    by_email: HashMap<String, usize>,
+    problems: Vec<String>,
+}
+
+impl DirectoryBuilder {
+    fn add(
+        &mut self,
+        entry: &str,
+        name: &str,
+        email: &str,
+        gitlab: Vec<String>,
+        github: Vec<String>,
+        teams: Vec<(String, Option<String>, Option<String>)>,
+    ) {
+        let email = email.trim().to_lowercase();
+        let name = name.trim();
+        if !email.contains('@') {
+            self.problems
+                .push(format!("{}: '{}' is not an email address", entry, email));
+            return;
+        }
+        if name.is_empty() {
+            self.problems.push(format!("{}: name is required", entry));
+        }
+
+        let index = *self.by_email.entry(email.clone()).or_insert_with(|| {
+            self.people.push(DirectoryPerson {
+                email: email.clone(),
+                ..DirectoryPerson::default()
+            });
+            self.people.len() - 1
+        });
+        let person = &mut self.people[index];
+        if !name.is_empty() {
+            person.name = name.to_string();
+        }
+        for (aliases, new_aliases) in [(&mut person.gitlab, gitlab), (&mut person.github, github)] {
+            for alias in new_aliases {
+                let alias = alias.trim().to_lowercase();
+                if !alias.is_empty() && !aliases.contains(&alias) {
+                    aliases.push(alias);
+                }
+            }
+        }
+        for (team, from, to) in teams {
+            let team = team.trim();
+            let valid_from = parse_date(entry, from.as_deref(), &mut self.problems);
+            let valid_to = parse_date(entry, to.as_deref(), &mut self.problems);
+            if team.is_empty() {
+                self.problems
+                    .push(format!("{}: a team membership needs a team", entry));
+                continue;
+            }
+            if let (Some(valid_from), Some(valid_to)) = (valid_from, valid_to) {
+                if valid_to < valid_from {
+                    self.problems.push(format!(
+                        "{}: membership of {} ends on {} before it starts on {}",
+                        entry, team, valid_to, valid_from
+                    ));
+                }
+            }
+            person.teams.push(DirectoryMembership {
+                team: team.to_string(),
+                valid_from,
+                valid_to,
+            });
+        }
+    }
+
+    fn build(mut self) -> Result<Vec<DirectoryPerson>, PeopleError> {
+        let mut owners: HashMap<(IdentitySource, &str), &str> = HashMap::new();
+        for person in &self.people {
+            let aliases = person
+                .gitlab
+                .iter()
+                .map(|alias| (IdentitySource::Gitlab, alias))
+                .chain(
+                    person
+                        .github
+                        .iter()
+                        .map(|alias| (IdentitySource::Github, alias)),
+                );
+            for (source, alias) in aliases {
+                match owners.insert((source, alias), &person.email) {
+                    Some(owner) if owner != person.email => self.problems.push(format!(
+                        "{} alias {} belongs to both {} and {}",
+                        source.as_str(),
+                        alias,
+                        owner,
+                        person.email
+                    )),
+                    _ => {}
+                }
+            }
+        }
+
+        if self.problems.is_empty() {
+            Ok(self.people)
+        } else {
+            Err(PeopleError::InvalidDirectory(self.problems))
+        }
+    }
+}
+
+fn parse_date(entry: &str, value: Option<&str>, problems: &mut Vec<String>) -> Option<Date> {
+    let value = value?.trim();
+    match Date::parse(value, format_description!("[year]-[month]-[day]")) {
+        Ok(date) => Some(date),
+        Err(e) => {
+            problems.push(format!("{}: invalid date '{}': {}", entry, value, e));
+            None
+        }
+    }
+}
+
+/// A person of the directory with the aliases mapped to them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Person {
+    pub person_id: Uuid,
+    pub email: String,
+    pub display_name: String,
+    pub aliases: Vec<(IdentitySource, String)>,
+}
+
+/// A GitLab author or GitHub Copilot user, by lowercased username or login
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub source: IdentitySource,
+    pub alias: String,
+}
+
+/// Why an identity was proposed as an alias of a person, strongest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchReason {
+    /// The alias is the local part of the person's email
+    Email,
+    /// The alias is one of the person's aliases, in the other source or spelled differently
+    Alias,
+    /// The alias is the person's full name, or first initial and last name
+    Name,
+}
+
+impl MatchReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchReason::Email => "email",
+            MatchReason::Alias => "alias",
+            MatchReason::Name => "name",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchProposal {
+    pub source: IdentitySource,
+    pub alias: String,
+    pub person_id: Uuid,
+    pub reason: MatchReason,
+}
+
+/// Keys shorter than this match too many unrelated people
+const MIN_MATCH_KEY_LEN: usize = 3;
+
+/// Lowercase letters and digits only, so `Jane.Doe`, `jane-doe` and `Jane Doe` compare equal
+fn match_key(value: &str) -> String {
+    value
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// The keys an alias of `person` may equal, with the reason each stands for
+fn person_match_keys(person: &Person) -> Vec<(String, MatchReason)> {
+    let mut keys = Vec::new();
+
+    let local_part = person.email.split('@').next().unwrap_or_default();
+    let local_part = local_part.split('+').next().unwrap_or_default();
+    keys.push((match_key(local_part), MatchReason::Email));
+    for (_, alias) in &person.aliases {
+        keys.push((match_key(alias), MatchReason::Alias));
+    }
+    keys.push((match_key(&person.display_name), MatchReason::Name));
+    let words: Vec<&str> = person.display_name.split_whitespace().collect();
+    if let (Some(first), Some(last)) = (words.first(), words.last()) {
+        if words.len() > 1 {
+            let initial: String = first.chars().take(1).collect();
+            keys.push((
+                match_key(&format!("{}{}", initial, last)),
+                MatchReason::Name,
+            ));
+        }
+    }
+
+    keys.retain(|(key, _)| key.len() >= MIN_MATCH_KEY_LEN);
+    keys
+}
+
+/// Propose people for unmapped identities by comparing the identity with the email, aliases
+/// and name of every person. An identity matching several people gets a proposal for each,
+/// left for the reviewer to pick from.
+pub fn propose_matches(identities: &[Identity], people: &[Person]) -> Vec<MatchProposal> {
+    let keys: Vec<(&Person, Vec<(String, MatchReason)>)> = people
+        .iter()
+        .map(|person| (person, person_match_keys(person)))
+        .collect();
+
+    let mut proposals = Vec::new();
+    for identity in identities {
+        let identity_key = match_key(&identity.alias);
+        if identity_key.len() < MIN_MATCH_KEY_LEN {
+            continue;
+        }
+        for (person, person_keys) in &keys {
+            if let Some((_, reason)) = person_keys.iter().find(|(key, _)| *key == identity_key) {
+                proposals.push(MatchProposal {
+                    source: identity.source,
+                    alias: identity.alias.clone(),
+                    person_id: person.person_id,
+                    reason: *reason,
+                });
+            }
+        }
+    }
+
+    proposals
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateStatus {
+    /// Waiting for review
+    Pending,
+    /// Turned into an alias of the person
+    Accepted,
+    /// Not proposed again by later matching
+    Rejected,
+}
+
+impl CandidateStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CandidateStatus::Pending => "pending",
+            CandidateStatus::Accepted => "accepted",
+            CandidateStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl FromStr for CandidateStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(CandidateStatus::Pending),
+            "accepted" => Ok(CandidateStatus::Accepted),
+            "rejected" => Ok(CandidateStatus::Rejected),
+            _ => Err(format!("Unknown match candidate status: {}", s)),
+        }
+    }
+}
+
+/// A proposed alias of a person in the review queue
+#[derive(Debug, Clone)]
+pub struct AliasCandidate {
+    pub id: Uuid,
+    pub source: String,
+    pub alias: String,
+    pub person_id: Uuid,
+    pub email: String,
+    pub display_name: String,
+    pub reason: String,
+    pub status: String,
+    pub created_at: OffsetDateTime,
+    pub reviewed_at: Option<OffsetDateTime>,
+}
+
+/// Counts of an imported directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImportSummary {
+    pub people: u64,
+    pub aliases: u64,
+    pub memberships: u64,
+}
+
+/// Maps GitLab usernames and GitHub logins to the people and teams of the organisation
+#[derive(Debug, Clone)]
+pub struct PeopleHandler {
+    pub store: Store,
+}
+
+impl PeopleHandler {
+    /// Import a parsed directory in one transaction.
+    ///
+    /// People are matched by email. Their aliases are added, taking over aliases mapped to
+    /// someone else, and their team memberships are replaced by the directory's. People missing
+    /// from the directory and aliases accepted from the review queue are kept.
+    pub async fn import(&self, people: &[DirectoryPerson]) -> Result<ImportSummary, PeopleError> {
+        let mut transaction = self.store.conn_pool.begin().await?;
+        let mut summary = ImportSummary::default();
+
+        for person in people {
+            let person_id: Uuid = sqlx::query_scalar(&self.store.sql(
+                r#"
+                INSERT INTO engineering_metrics.people (person_id, email, display_name)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (email) DO
+                UPDATE SET
+                    display_name = EXCLUDED.display_name,
+                    updated_at = NOW()
+                RETURNING person_id
+                "#,
+            ))
+            .bind(Uuid::now_v7())
+            .bind(&person.email)
+            .bind(&person.name)
+            .fetch_one(&mut *transaction)
+            .await?;
+            summary.people += 1;
+
+            let aliases = person
+                .gitlab
+                .iter()
+                .map(|alias| (IdentitySource::Gitlab, alias))
+                .chain(
+                    person
+                        .github
+                        .iter()
+                        .map(|alias| (IdentitySource::Github, alias)),
+                );
+            for (source, alias) in aliases {
+                sqlx::query(&self.store.sql(
+                    r#"
+                    INSERT INTO engineering_metrics.person_aliases (source, alias, person_id, origin)
+                    VALUES ($1, $2, $3, 'directory')
+                    ON CONFLICT (source, alias) DO
+                    UPDATE SET
+                        person_id = EXCLUDED.person_id,
+                        origin = EXCLUDED.origin
+                    "#,
+                ))
+                .bind(source.as_str())
+                .bind(alias)
+                .bind(person_id)
+                .execute(&mut *transaction)
+                .await?;
+                summary.aliases += 1;
+            }
+
+            sqlx::query(
+                &self
+                    .store
+                    .sql("DELETE FROM engineering_metrics.team_memberships WHERE person_id = $1"),
+            )
+            .bind(person_id)
+            .execute(&mut *transaction)
+            .await?;
+            for membership in &person.teams {
+                let team_id: Uuid = sqlx::query_scalar(&self.store.sql(
+                    r#"
+                    INSERT INTO engineering_metrics.teams (team_id, name)
+                    VALUES ($1, $2)
+                    ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name
+                    RETURNING team_id
+                    "#,
+                ))
+                .bind(Uuid::now_v7())
+                .bind(&membership.team)
+                .fetch_one(&mut *transaction)
+                .await?;
+
+                sqlx::query(&self.store.sql(
+                    r#"
+                    INSERT INTO engineering_metrics.team_memberships
+                        (id, person_id, team_id, valid_from, valid_to)
+                    VALUES ($1, $2, $3, $4, $5)
+                    "#,
+                ))
+                .bind(Uuid::now_v7())
+                .bind(person_id)
+                .bind(team_id)
+                .bind(membership.valid_from)
+                .bind(membership.valid_to)
+                .execute(&mut *transaction)
+                .await?;
+                summary.memberships += 1;
+            }
+        }
+
+        // Identities the directory now maps need no review
+        sqlx::query(&self.store.sql(
+            r#"
+            DELETE FROM engineering_metrics.person_alias_candidates c
+            USING engineering_metrics.person_aliases a
+            WHERE c.status = 'pending' AND a.source = c.source AND a.alias = c.alias
+            "#,
+        ))
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(summary)
+    }
+
+    /// Every person with their aliases
+    pub async fn fetch_people(&self) -> Result<Vec<Person>, PeopleError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT p.person_id, p.email, p.display_name, a.source, a.alias
+            FROM engineering_metrics.people p
+            LEFT JOIN engineering_metrics.person_aliases a ON a.person_id = p.person_id
+            ORDER BY p.email, a.source, a.alias
+            "#,
+        ))
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut people: Vec<Person> = Vec::new();
+        for row in &rows {
+            let person_id: Uuid = row.get(0);
+            if people.last().map(|person| person.person_id) != Some(person_id) {
+                people.push(Person {
+                    person_id,
+                    email: row.get(1),
+                    display_name: row.get(2),
+                    aliases: Vec::new(),
+                });
+            }
+            let source: Option<String> = row.get(3);
+            let alias: Option<String> = row.get(4);
+            if let (Some(source), Some(alias), Some(person)) = (source, alias, people.last_mut()) {
+                if let Ok(source) = source.parse() {
+                    person.aliases.push((source, alias));
+                }
+            }
+        }
+
+        Ok(people)
+    }
+
+    /// GitLab authors and GitHub Copilot users that are not an alias of any person
+    pub async fn fetch_unmapped(&self) -> Result<Vec<Identity>, PeopleError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT source, alias
+            FROM engineering_metrics.unmapped_identities
+            ORDER BY source, alias
+            "#,
+        ))
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let source: String = row.get(0);
+                Some(Identity {
+                    source: source.parse().ok()?,
+                    alias: row.get(1),
+                })
+            })
+            .collect())
+    }
+
+    /// Match the unmapped identities against the directory and queue the proposals for
+    /// review. Returns the number of new candidates; known and rejected ones are skipped.
+    pub async fn match_identities(&self) -> Result<u64, PeopleError> {
+        let identities = self.fetch_unmapped().await?;
+        let people = self.fetch_people().await?;
+        let proposals = propose_matches(&identities, &people);
+
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let mut queued = 0;
+        for proposal in &proposals {
+            let result = sqlx::query(&self.store.sql(
+                r#"
+                INSERT INTO engineering_metrics.person_alias_candidates
+                    (id, source, alias, person_id, reason)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (source, alias, person_id) DO NOTHING
+                "#,
+            ))
+            .bind(Uuid::now_v7())
+            .bind(proposal.source.as_str())
+            .bind(&proposal.alias)
+            .bind(proposal.person_id)
+            .bind(proposal.reason.as_str())
+            .execute(&mut *conn)
+            .await?;
+            queued += result.rows_affected();
+        }
+
+        Ok(queued)
+    }
+
+    /// List match candidates, optionally restricted to one status
+    pub async fn list_candidates(
+        &self,
+        status: Option<CandidateStatus>,
+    ) -> Result<Vec<AliasCandidate>, PeopleError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT c.id, c.source, c.alias, c.person_id, p.email, p.display_name, c.reason,
+                   c.status, c.created_at, c.reviewed_at
+            FROM engineering_metrics.person_alias_candidates c
+            JOIN engineering_metrics.people p ON p.person_id = c.person_id
+            WHERE $1::VARCHAR IS NULL OR c.status = $1
+            ORDER BY c.source, c.alias, p.email
+            "#,
+        ))
+        .bind(status.map(|status| status.as_str()))
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| AliasCandidate {
+                id: row.get(0),
+                source: row.get(1),
+                alias: row.get(2),
+                person_id: row.get(3),
+                email: row.get(4),
+                display_name: row.get(5),
+                reason: row.get(6),
+                status: row.get(7),
+                created_at: row.get(8),
+                reviewed_at: row.get(9),
+            })
+            .collect())
+    }
+
+    /// Map the candidate's identity to its person and reject the other pending candidates
+    /// for the same identity
+    pub async fn accept(&self, id: Uuid) -> Result<(), PeopleError> {
+        let mut transaction = self.store.conn_pool.begin().await?;
+
+        let row = sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.person_alias_candidates
+            SET status = 'accepted',
+                reviewed_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            RETURNING source, alias, person_id
+            "#,
+        ))
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or(PeopleError::UnknownCandidate(id))?;
+        let source: String = row.get(0);
+        let alias: String = row.get(1);
+        let person_id: Uuid = row.get(2);
+
+        sqlx::query(&self.store.sql(
+            r#"
+            INSERT INTO engineering_metrics.person_aliases (source, alias, person_id, origin)
+            VALUES ($1, $2, $3, 'match')
+            ON CONFLICT (source, alias) DO
+            UPDATE SET
+                person_id = EXCLUDED.person_id,
+                origin = EXCLUDED.origin
+            "#,
+        ))
+        .bind(&source)
+        .bind(&alias)
+        .bind(person_id)
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.person_alias_candidates
+            SET status = 'rejected',
+                reviewed_at = NOW()
+            WHERE source = $1 AND alias = $2 AND status = 'pending'
+            "#,
+        ))
+        .bind(&source)
+        .bind(&alias)
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Reject a pending candidate so later matching does not propose it again
+    pub async fn reject(&self, id: Uuid) -> Result<(), PeopleError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let result = sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.person_alias_candidates
+            SET status = 'rejected',
+                reviewed_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            "#,
+        ))
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(PeopleError::UnknownCandidate(id));
+        }
+        Ok(())
+    }
+}
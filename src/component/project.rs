@@ -1,6 +1,15 @@
 use crate::client::gitlab_graphql_client::GitlabGraphQLError;
+use crate::component::import_progress::{ImportProgressError, ImportProgressHandler};
+use crate::component::run_history::RunGroupStats;
 use crate::context::GitlabContext;
+use crate::metrics;
+use crate::scheduler::SchedulerError;
+use std::time::Instant;
 use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::{info, info_span, instrument, warn, Instrument};
+
+pub const IMPORT_TYPE: &str = "projects";
 
 #[derive(Error, Debug)]
 pub enum ProjectError {
@@ -10,8 +19,14 @@ pub enum ProjectError {
     DatabaseError(#[from] sqlx::Error),
     #[error("JSON serialization error: {0}")]
     JsonError(#[from] serde_json::Error),
+    #[error("Import progress error: {0}")]
+    ImportProgressError(#[from] ImportProgressError),
+    #[error("Scheduler error: {0}")]
+    SchedulerError(#[from] SchedulerError),
     #[error("Missing data: {0}")]
     MissingData(String),
+    #[error("Interrupted by shutdown")]
+    Interrupted,
 }
 
 #[derive(Debug, Clone)]
@@ -27,6 +42,7 @@ pub struct Project {
     pub full_path: String,
     pub web_url: String,
     pub topics: Option<Vec<String>>,
+    pub archived: bool,
 }
 
 #[derive(Debug)]
@@ -47,11 +63,17 @@ impl ProjectHandler {
         group_full_path: &str,
         after_pointer_token: Option<String>,
     ) -> Result<ProjectsWithPageInfo, ProjectError> {
-        let group_data = self
-            .context
-            .gitlab_graphql_client
-            .fetch_group_projects(group_full_path, after_pointer_token)
-            .await?;
+        let group_data = {
+            let _permit = self
+                .context
+                .scheduler
+                .acquire(self.context.gitlab_graphql_client.endpoint())
+                .await?;
+            self.context
+                .gitlab_graphql_client
+                .fetch_group_projects(group_full_path, after_pointer_token)
+                .await?
+        };
         // println!("group_data: {:?}", &group_data);
 
         let mut projects: Vec<Project> = Vec::new();
@@ -71,6 +93,7 @@ impl ProjectHandler {
                 full_path: project_ref.full_path.clone(),
                 web_url: project_ref.web_url.clone(),
                 topics: project_ref.topics.as_ref().cloned(),
+                archived: project_ref.archived.unwrap_or(false),
             });
         }
 
@@ -83,63 +106,219 @@ impl ProjectHandler {
         })
     }
 
-    pub async fn persist_project(&self, project: &Project) -> Result<(), ProjectError> {
+    pub async fn persist_project(
+        &self,
+        group_full_path: &str,
+        project: &Project,
+        synced_at: OffsetDateTime,
+    ) -> Result<(), ProjectError> {
         let mut conn = self.context.store.conn_pool.acquire().await?;
         sqlx::query(
-            r#"
-            INSERT INTO engineering_metrics.projects (p_id, p_name, p_path, p_full_path, p_web_url, topics)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            &self.context.store.sql(r#"
+            INSERT INTO engineering_metrics.projects (p_id, p_name, p_path, p_full_path, p_web_url, topics, group_full_path, last_synced_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             ON CONFLICT (p_id) DO 
             UPDATE SET 
                 p_name = $2,
                 p_path = $3,
                 p_full_path = $4,
                 p_web_url = $5,
-                topics = $6
-            "#)
+                topics = $6,
+                group_full_path = $7,
+                last_synced_at = $8,
+                removed_at = NULL
+            "#))
             .bind(&project.id)
             .bind(&project.name)
             .bind(&project.path)
             .bind(&project.full_path)
             .bind(&project.web_url)
             .bind(serde_json::to_value(&project.topics)?)
+            .bind(group_full_path)
+            .bind(synced_at)
         .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
-    pub async fn import_projects(&self, group_full_path: &str) {
+    /// Flag projects of the group that were not seen since `sync_started_at`,
+    /// i.e. projects that were archived, deleted or transferred out of the group.
+    pub async fn mark_removed_projects(
+        &self,
+        group_full_path: &str,
+        sync_started_at: OffsetDateTime,
+    ) -> Result<u64, ProjectError> {
+        let mut conn = self.context.store.conn_pool.acquire().await?;
+        let result = sqlx::query(&self.context.store.sql(
+            r#"
+            UPDATE engineering_metrics.projects
+            SET removed_at = $3
+            WHERE group_full_path = $1
+              AND removed_at IS NULL
+              AND last_synced_at < $2
+            "#,
+        ))
+        .bind(group_full_path)
+        .bind(sync_started_at)
+        .bind(OffsetDateTime::now_utc())
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Import the projects of a group, accumulating the run statistics into `stats`
+    #[instrument(name = "import_projects", skip_all)]
+    pub async fn import_projects(
+        &self,
+        group_full_path: &str,
+        stats: &mut RunGroupStats,
+    ) -> Result<(), ProjectError> {
+        let import_progress_handler = ImportProgressHandler {
+            store: self.context.store.clone(),
+            run_id: self.context.run_id,
+            policy: self.context.import_progress_policy.clone(),
+        };
+
+        // Get or resume existing import
+        let import_progress = import_progress_handler
+            .get_or_create_import(group_full_path, IMPORT_TYPE, OffsetDateTime::now_utc())
+            .await?;
+
         let mut has_more = true;
-        let mut after_pointer_token = Option::None;
+        let mut after_pointer_token = import_progress.last_cursor.clone();
+        let mut total_imported = import_progress.total_processed;
+        let mut resuming_from_cursor = after_pointer_token.is_some();
+        let mut page_number = 0;
 
         while has_more {
+            // Stop between pages on shutdown; the checkpoint of the last page lets the next run resume
+            if self.context.shutdown.is_cancelled() {
+                if let Err(e) = import_progress_handler
+                    .mark_interrupted(import_progress.id)
+                    .await
+                {
+                    warn!(error = %e, "Failed to mark import as interrupted");
+                }
+                info!(
+                    group = group_full_path,
+                    cursor = ?after_pointer_token,
+                    "Project import interrupted by shutdown, progress saved at cursor"
+                );
+                return Err(ProjectError::Interrupted);
+            }
+
+            page_number += 1;
+            let page_span = info_span!(
+                "page",
+                page = page_number,
+                import_id = %import_progress.id
+            );
+
+            stats.api_calls += 1;
             let res = match self
                 .fetch_group_projects(group_full_path, after_pointer_token.to_owned())
+                .instrument(page_span)
                 .await
             {
                 Ok(r) => r,
-                Err(e) => {
-                    eprintln!(
-                        "Failed to fetch projects for group {}: {}",
-                        group_full_path, e
+                Err(ProjectError::GraphQLError(e))
+                    if resuming_from_cursor
+                        && e.is_rejected_cursor()
+                        && self
+                            .context
+                            .import_progress_policy
+                            .restart_on_rejected_cursor =>
+                {
+                    // The saved cursor was rejected (e.g. it expired), start over from the first page
+                    warn!(
+                        group = group_full_path,
+                        cursor = ?after_pointer_token,
+                        error = %e,
+                        "Saved cursor was rejected, restarting import from the first page"
                     );
-                    return;
+                    import_progress_handler
+                        .restart_from_first_page(import_progress.id)
+                        .await?;
+                    after_pointer_token = None;
+                    total_imported = 0;
+                    resuming_from_cursor = false;
+                    continue;
+                }
+                Err(e) => {
+                    // Mark the import as failed but preserve the cursor for resume
+                    let _ = import_progress_handler
+                        .mark_failed(import_progress.id, &e.to_string())
+                        .await;
+                    stats.record_error(&e);
+                    return Err(e);
                 }
             };
 
-            for project in res.projects {
-                if let Err(e) = self.persist_project(&project).await {
-                    eprintln!("Failed to persist project {}: {}", project.id, e);
+            resuming_from_cursor = false;
+            let batch_count = res.projects.len() as i32;
+            stats.items_fetched += i64::from(batch_count);
+            for project in &res.projects {
+                // Archived projects are not synced, so they are flagged as removed below
+                if project.archived {
+                    stats.items_filtered += 1;
+                    continue;
+                }
+                let write_started_at = Instant::now();
+                let result = self
+                    .persist_project(group_full_path, project, OffsetDateTime::now_utc())
+                    .await;
+                metrics::observe_db_write("project", write_started_at.elapsed());
+                if let Err(e) = result {
+                    let _ = import_progress_handler
+                        .mark_failed(import_progress.id, &e.to_string())
+                        .await;
+                    stats.items_failed += 1;
+                    stats.record_error(format!("persist failed for {}: {}", project.id, e));
+                    return Err(e);
                 }
+                stats.items_persisted += 1;
             }
 
+            // Update progress after each page - this is our checkpoint
+            import_progress_handler
+                .update_progress(
+                    import_progress.id,
+                    res.page_info.end_cursor.as_deref(),
+                    batch_count,
+                )
+                .await?;
+            total_imported += batch_count;
+
             after_pointer_token = res.page_info.end_cursor;
             has_more = res.page_info.has_next_page;
         }
-        println!(
-            "Done importing projects data for group={}.",
-            &group_full_path
+
+        // Every project still in the group has been synced since the import started
+        let removed = match self
+            .mark_removed_projects(group_full_path, import_progress.started_at)
+            .await
+        {
+            Ok(removed) => removed,
+            Err(e) => {
+                let _ = import_progress_handler
+                    .mark_failed(import_progress.id, &e.to_string())
+                    .await;
+                stats.record_error(&e);
+                return Err(e);
+            }
+        };
+
+        import_progress_handler
+            .mark_completed(import_progress.id)
+            .await?;
+
+        info!(
+            group = group_full_path,
+            total_imported, removed, "Done importing projects"
         );
+
+        Ok(())
     }
 }
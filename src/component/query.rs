@@ -0,0 +1,307 @@
+use sqlx::Row;
+use thiserror::Error;
+use time::{Date, OffsetDateTime};
+
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum QueryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// A page of a listing; pages start at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl Page {
+    fn offset(&self) -> i64 {
+        i64::from(self.page.saturating_sub(1)) * i64::from(self.per_page)
+    }
+
+    /// One row more than the page holds, to tell whether a next page exists
+    fn limit(&self) -> i64 {
+        i64::from(self.per_page) + 1
+    }
+
+    fn paged<T>(&self, mut items: Vec<T>) -> Paged<T> {
+        let has_next = items.len() > self.per_page as usize;
+        items.truncate(self.per_page as usize);
+        Paged {
+            items,
+            next_page: has_next.then_some(self.page + 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub next_page: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectRecord {
+    pub project_id: String,
+    pub name: String,
+    pub full_path: String,
+    pub web_url: String,
+    pub group_full_path: Option<String>,
+    pub topics: Vec<String>,
+    pub last_synced_at: Option<OffsetDateTime>,
+    pub removed_at: Option<OffsetDateTime>,
+}
+
+/// Filters of the merge request listing; unset filters match everything
+#[derive(Debug, Clone, Default)]
+pub struct MergeRequestFilter {
+    /// GitLab group full path of the project
+    pub group: Option<String>,
+    /// Project full path or numeric project id
+    pub project: Option<String>,
+    pub author: Option<String>,
+    /// Team of the author in the people directory when the merge request was merged
+    pub team: Option<String>,
+    /// AI category
+    pub category: Option<String>,
+    /// First merge day
+    pub since: Option<Date>,
+    /// Day the merge days stop before
+    pub until: Option<Date>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeRequestRecord {
+    pub mr_id: String,
+    pub mr_iid: String,
+    pub title: String,
+    pub web_url: String,
+    pub project_id: String,
+    pub project_full_path: String,
+    pub group_full_path: Option<String>,
+    pub author: String,
+    pub merged_by: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub merged_at: Option<OffsetDateTime>,
+    pub first_approved_at: Option<OffsetDateTime>,
+    pub approved: bool,
+    pub additions: Option<i64>,
+    pub deletions: Option<i64>,
+    pub labels: Vec<String>,
+    pub category: Option<String>,
+}
+
+/// Filters of the Copilot daily metrics listing; unset filters match everything
+#[derive(Debug, Clone, Default)]
+pub struct CopilotDailyFilter {
+    pub org: Option<String>,
+    /// GitHub login, case insensitive
+    pub user: Option<String>,
+    /// Team of the user in the people directory on the report day
+    pub team: Option<String>,
+    pub since: Option<Date>,
+    pub until: Option<Date>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopilotDailyRecord {
+    pub org_slug: String,
+    pub report_day: Date,
+    pub user_login: String,
+    pub user_initiated_interaction_count: i64,
+    pub code_generation_activity_count: i64,
+    pub code_acceptance_activity_count: i64,
+    pub loc_suggested_to_add_sum: i64,
+    pub loc_suggested_to_delete_sum: i64,
+    pub loc_added_sum: i64,
+    pub loc_deleted_sum: i64,
+    pub used_agent: bool,
+    pub used_chat: bool,
+    pub used_cli: bool,
+}
+
+/// Read-only listings of the collected data, in a stable order so pages do not overlap
+#[derive(Debug, Clone)]
+pub struct QueryHandler {
+    pub store: Store,
+}
+
+impl QueryHandler {
+    pub async fn list_projects(
+        &self,
+        group: Option<&str>,
+        page: Page,
+    ) -> Result<Paged<ProjectRecord>, QueryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT p_id, p_name, p_full_path, p_web_url, group_full_path,
+                CASE WHEN jsonb_typeof(topics) = 'array'
+                    THEN ARRAY(SELECT jsonb_array_elements_text(topics))
+                    ELSE '{}'::text[]
+                END,
+                last_synced_at, removed_at
+            FROM engineering_metrics.projects
+            WHERE $1::varchar IS NULL OR group_full_path = $1
+            ORDER BY p_full_path, p_id
+            LIMIT $2 OFFSET $3
+            "#,
+        ))
+        .bind(group)
+        .bind(page.limit())
+        .bind(page.offset())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(page.paged(
+            rows.iter()
+                .map(|row| ProjectRecord {
+                    project_id: row.get(0),
+                    name: row.get(1),
+                    full_path: row.get(2),
+                    web_url: row.get(3),
+                    group_full_path: row.get(4),
+                    topics: row.get(5),
+                    last_synced_at: row.get(6),
+                    removed_at: row.get(7),
+                })
+                .collect(),
+        ))
+    }
+
+    /// Merge requests, most recently merged first and open ones last
+    pub async fn list_merge_requests(
+        &self,
+        filter: &MergeRequestFilter,
+        page: Page,
+    ) -> Result<Paged<MergeRequestRecord>, QueryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT mr.mr_id, mr.mr_iid, mr.mr_title, mr.mr_web_url, mr.project_id,
+                COALESCE(p.p_full_path, mr.project_path), p.group_full_path, mr.created_by,
+                mr.merged_by, mr.created_at, mr.merged_at, mr.first_approved_at, mr.approved,
+                (mr.diff_stats_summary ->> 'additions')::bigint,
+                (mr.diff_stats_summary ->> 'deletions')::bigint,
+                CASE WHEN jsonb_typeof(mr.labels) = 'array'
+                    THEN ARRAY(SELECT jsonb_array_elements_text(mr.labels))
+                    ELSE '{}'::text[]
+                END,
+                mr.mr_ai_category
+            FROM engineering_metrics.merge_requests mr
+            LEFT JOIN engineering_metrics.projects p
+                ON p.p_id = 'gid://gitlab/Project/' || mr.project_id
+            WHERE ($1::varchar IS NULL OR p.group_full_path = $1)
+              AND ($2::varchar IS NULL OR p.p_full_path = $2 OR mr.project_id = $2)
+              AND ($3::varchar IS NULL OR mr.created_by = $3)
+              AND ($4::varchar IS NULL OR EXISTS (
+                  SELECT 1 FROM engineering_metrics.merge_request_teams t
+                  WHERE t.mr_id = mr.mr_id AND t.team = $4
+              ))
+              AND ($5::varchar IS NULL OR mr.mr_ai_category = $5)
+              AND ($6::date IS NULL OR mr.merged_at >= $6::date::timestamp AT TIME ZONE 'UTC')
+              AND ($7::date IS NULL OR mr.merged_at < $7::date::timestamp AT TIME ZONE 'UTC')
+            ORDER BY mr.merged_at DESC NULLS LAST, mr.mr_id
+            LIMIT $8 OFFSET $9
+            "#,
+        ))
+        .bind(&filter.group)
+        .bind(&filter.project)
+        .bind(&filter.author)
+        .bind(&filter.team)
+        .bind(&filter.category)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(page.limit())
+        .bind(page.offset())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(page.paged(
+            rows.iter()
+                .map(|row| MergeRequestRecord {
+                    mr_id: row.get(0),
+                    mr_iid: row.get(1),
+                    title: row.get(2),
+                    web_url: row.get(3),
+                    project_id: row.get(4),
+                    project_full_path: row.get(5),
+                    group_full_path: row.get(6),
+                    author: row.get(7),
+                    merged_by: row.get(8),
+                    created_at: row.get(9),
+                    merged_at: row.get(10),
+                    first_approved_at: row.get(11),
+                    approved: row.get(12),
+                    additions: row.get(13),
+                    deletions: row.get(14),
+                    labels: row.get(15),
+                    category: row.get(16),
+                })
+                .collect(),
+        ))
+    }
+
+    /// Copilot daily metrics per user, most recent report day first
+    pub async fn list_copilot_daily(
+        &self,
+        filter: &CopilotDailyFilter,
+        page: Page,
+    ) -> Result<Paged<CopilotDailyRecord>, QueryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT c.org_slug, c.report_day, c.user_login, c.user_initiated_interaction_count,
+                c.code_generation_activity_count, c.code_acceptance_activity_count,
+                c.loc_suggested_to_add_sum, c.loc_suggested_to_delete_sum, c.loc_added_sum,
+                c.loc_deleted_sum, c.used_agent, c.used_chat, c.used_cli
+            FROM engineering_metrics.copilot_user_daily_metrics c
+            WHERE ($1::varchar IS NULL OR c.org_slug = $1)
+              AND ($2::varchar IS NULL OR lower(c.user_login) = lower($2))
+              AND ($3::varchar IS NULL OR EXISTS (
+                  SELECT 1 FROM engineering_metrics.copilot_user_daily_teams t
+                  WHERE t.metrics_id = c.id AND t.team = $3
+              ))
+              AND ($4::date IS NULL OR c.report_day >= $4)
+              AND ($5::date IS NULL OR c.report_day < $5)
+            ORDER BY c.report_day DESC, c.org_slug, c.user_login
+            LIMIT $6 OFFSET $7
+            "#,
+        ))
+        .bind(&filter.org)
+        .bind(&filter.user)
+        .bind(&filter.team)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(page.limit())
+        .bind(page.offset())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(page.paged(
+            rows.iter()
+                .map(|row| CopilotDailyRecord {
+                    org_slug: row.get(0),
+                    report_day: row.get(1),
+                    user_login: row.get(2),
+                    user_initiated_interaction_count: row.get(3),
+                    code_generation_activity_count: row.get(4),
+                    code_acceptance_activity_count: row.get(5),
+                    loc_suggested_to_add_sum: row.get(6),
+                    loc_suggested_to_delete_sum: row.get(7),
+                    loc_added_sum: row.get(8),
+                    loc_deleted_sum: row.get(9),
+                    used_agent: row.get(10),
+                    used_chat: row.get(11),
+                    used_cli: row.get(12),
+                })
+                .collect(),
+        ))
+    }
+}
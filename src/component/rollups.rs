@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+use tracing::info;
+
+use crate::metrics;
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum RollupError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Materialized reporting rollups and the collector whose data they aggregate
+pub const ROLLUPS: [(&str, &str); 7] = [
+    ("mr_weekly_project_rollup", "gitlab"),
+    ("mr_weekly_author_rollup", "gitlab"),
+    ("mr_weekly_size_rollup", "gitlab"),
+    ("mr_weekly_category_rollup", "gitlab"),
+    ("copilot_daily_org_rollup", "copilot"),
+    ("copilot_daily_ide_rollup", "copilot"),
+    ("copilot_daily_language_rollup", "copilot"),
+];
+
+#[derive(Debug, Clone)]
+pub struct RollupHandler {
+    pub store: Store,
+}
+
+impl RollupHandler {
+    /// Names of the rollups built from the data of `collector`. The AI backfill only changes
+    /// merge request categories, so it refreshes the GitLab rollups.
+    pub fn rollups_of(collector: &str) -> Vec<&'static str> {
+        let source = if collector == "ai" {
+            "gitlab"
+        } else {
+            collector
+        };
+        ROLLUPS
+            .iter()
+            .filter(|(_, rollup_source)| *rollup_source == source)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// Refresh every rollup, returning how long each took
+    pub async fn refresh_all(&self) -> Result<Vec<(&'static str, Duration)>, RollupError> {
+        self.refresh(ROLLUPS.iter().map(|(name, _)| *name)).await
+    }
+
+    /// Refresh the given rollups one after another. A concurrent refresh keeps every rollup
+    /// readable by the dashboards while it is rebuilt.
+    pub async fn refresh(
+        &self,
+        rollups: impl IntoIterator<Item = &'static str>,
+    ) -> Result<Vec<(&'static str, Duration)>, RollupError> {
+        let mut refreshed = Vec::new();
+        for rollup in rollups {
+            let started_at = Instant::now();
+            sqlx::query(&self.store.sql(&format!(
+                "REFRESH MATERIALIZED VIEW CONCURRENTLY engineering_metrics.{}",
+                rollup
+            )))
+            .execute(&self.store.conn_pool)
+            .await?;
+
+            let elapsed = started_at.elapsed();
+            metrics::observe_db_write("rollup_refresh", elapsed);
+            info!(
+                rollup,
+                duration_ms = elapsed.as_millis() as u64,
+                "Refreshed rollup"
+            );
+            refreshed.push((rollup, elapsed));
+        }
+
+        Ok(refreshed)
+    }
+}
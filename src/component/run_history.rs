@@ -0,0 +1,409 @@
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use thiserror::Error;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::store::Store;
+
+/// Maximum number of error messages kept per group so a failing run cannot bloat the history
+const MAX_ERRORS_PER_GROUP: usize = 20;
+
+#[derive(Error, Debug)]
+pub enum RunHistoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Records every collector run with its outcome and per-group statistics.
+///
+/// Unlike `collector_runs`, which only holds the watermark of successful runs,
+/// failed and partial runs are kept here as well.
+#[derive(Debug, Clone)]
+pub struct RunHistoryHandler {
+    pub store: Store,
+}
+
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub id: Uuid,
+    pub collector: String,
+    pub status: RunStatus,
+    pub started_at: OffsetDateTime,
+    pub completed_at: Option<OffsetDateTime>,
+    pub error_summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Running,
+    Succeeded,
+    Failed,
+    /// Stopped on shutdown before every import finished; the next run resumes the imports
+    Interrupted,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunStatus::Running => "running",
+            RunStatus::Succeeded => "succeeded",
+            RunStatus::Failed => "failed",
+            RunStatus::Interrupted => "interrupted",
+        }
+    }
+}
+
+impl FromStr for RunStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "running" => Ok(RunStatus::Running),
+            "succeeded" => Ok(RunStatus::Succeeded),
+            "failed" => Ok(RunStatus::Failed),
+            "interrupted" => Ok(RunStatus::Interrupted),
+            _ => Err(format!("Unknown run status: {}", s)),
+        }
+    }
+}
+
+/// Counters collected while importing one group/org for one import type
+#[derive(Debug, Default, Clone)]
+pub struct RunGroupStats {
+    pub items_fetched: i64,
+    /// Items fetched but not eligible for import, e.g. merge requests that are not merged yet
+    pub items_filtered: i64,
+    pub items_skipped_existing: i64,
+    /// Stored items whose mutable fields were refreshed without re-importing them
+    pub items_reconciled: i64,
+    pub items_summarized: i64,
+    pub items_persisted: i64,
+    pub items_failed: i64,
+    pub ai_time_ms: i64,
+    pub api_calls: i64,
+    pub errors: Vec<String>,
+}
+
+impl RunGroupStats {
+    pub fn record_error(&mut self, error: impl Display) {
+        if self.errors.len() < MAX_ERRORS_PER_GROUP {
+            self.errors.push(error.to_string());
+        }
+    }
+
+    /// Add the counts and errors of `other`, e.g. those of one of several concurrently processed items
+    pub fn merge(&mut self, other: RunGroupStats) {
+        self.items_fetched += other.items_fetched;
+        self.items_filtered += other.items_filtered;
+        self.items_skipped_existing += other.items_skipped_existing;
+        self.items_reconciled += other.items_reconciled;
+        self.items_summarized += other.items_summarized;
+        self.items_persisted += other.items_persisted;
+        self.items_failed += other.items_failed;
+        self.ai_time_ms += other.ai_time_ms;
+        self.api_calls += other.api_calls;
+        for error in other.errors {
+            self.record_error(error);
+        }
+    }
+
+    pub fn error_summary(&self) -> Option<String> {
+        if self.errors.is_empty() {
+            None
+        } else {
+            Some(self.errors.join("; "))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RunGroupStatsRecord {
+    pub run_id: Uuid,
+    pub group_key: String,
+    pub import_type: String,
+    pub stats: RunGroupStats,
+    pub error_summary: Option<String>,
+}
+
+/// Latest run that recorded statistics for a group/org and import type
+#[derive(Debug, Clone)]
+pub struct LatestGroupRun {
+    pub run: Run,
+    pub stats: RunGroupStatsRecord,
+}
+
+impl RunHistoryHandler {
+    /// Record the start of a run for the given collector
+    pub async fn start_run(&self, collector: &str) -> Result<Run, RunHistoryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let id = Uuid::now_v7();
+        let now = OffsetDateTime::now_utc();
+
+        sqlx::query(&self.store.sql(
+            r#"
+            INSERT INTO engineering_metrics.run_history (id, collector, status, started_at)
+            VALUES ($1, $2, 'running', $3)
+            "#,
+        ))
+        .bind(id)
+        .bind(collector)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(Run {
+            id,
+            collector: collector.to_string(),
+            status: RunStatus::Running,
+            started_at: now,
+            completed_at: None,
+            error_summary: None,
+        })
+    }
+
+    /// Record the final status of a run
+    pub async fn complete_run(
+        &self,
+        run_id: Uuid,
+        status: RunStatus,
+        error_summary: Option<&str>,
+    ) -> Result<(), RunHistoryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+
+        sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.run_history
+            SET status = $2,
+                completed_at = $3,
+                error_summary = $4
+            WHERE id = $1
+            "#,
+        ))
+        .bind(run_id)
+        .bind(status.as_str())
+        .bind(OffsetDateTime::now_utc())
+        .bind(error_summary)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store the statistics of one group/org and import type for a run
+    pub async fn persist_group_stats(
+        &self,
+        run_id: Uuid,
+        group_key: &str,
+        import_type: &str,
+        stats: &RunGroupStats,
+    ) -> Result<(), RunHistoryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+
+        sqlx::query(
+            &self.store.sql(r#"
+            INSERT INTO engineering_metrics.run_history_group_stats (run_id, group_key, import_type,
+                items_fetched, items_filtered, items_skipped_existing, items_summarized, items_persisted, items_failed,
+                ai_time_ms, api_calls, error_summary, items_reconciled)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            ON CONFLICT (run_id, group_key, import_type) DO
+            UPDATE SET
+                items_fetched = $4,
+                items_filtered = $5,
+                items_skipped_existing = $6,
+                items_summarized = $7,
+                items_persisted = $8,
+                items_failed = $9,
+                ai_time_ms = $10,
+                api_calls = $11,
+                error_summary = $12,
+                items_reconciled = $13
+            "#),
+        )
+        .bind(run_id)
+        .bind(group_key)
+        .bind(import_type)
+        .bind(stats.items_fetched)
+        .bind(stats.items_filtered)
+        .bind(stats.items_skipped_existing)
+        .bind(stats.items_summarized)
+        .bind(stats.items_persisted)
+        .bind(stats.items_failed)
+        .bind(stats.ai_time_ms)
+        .bind(stats.api_calls)
+        .bind(stats.error_summary())
+        .bind(stats.items_reconciled)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetch the most recent runs, newest first
+    pub async fn fetch_recent_runs(
+        &self,
+        collector: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<Run>, RunHistoryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT id, collector, status, started_at, completed_at, error_summary
+            FROM engineering_metrics.run_history
+            WHERE $1::VARCHAR IS NULL OR collector = $1
+            ORDER BY started_at DESC
+            LIMIT $2
+            "#,
+        ))
+        .bind(collector)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.iter().map(run_from_row).collect())
+    }
+
+    /// Fetch the per-group statistics of a run
+    pub async fn fetch_group_stats(
+        &self,
+        run_id: Uuid,
+    ) -> Result<Vec<RunGroupStatsRecord>, RunHistoryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(
+            &self.store.sql(r#"
+            SELECT run_id, group_key, import_type, items_fetched, items_filtered, items_skipped_existing,
+                   items_summarized, items_persisted, items_failed, ai_time_ms, api_calls, error_summary,
+                   items_reconciled
+            FROM engineering_metrics.run_history_group_stats
+            WHERE run_id = $1
+            ORDER BY group_key, import_type
+            "#),
+        )
+        .bind(run_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| RunGroupStatsRecord {
+                run_id: row.get(0),
+                group_key: row.get(1),
+                import_type: row.get(2),
+                stats: RunGroupStats {
+                    items_fetched: row.get(3),
+                    items_filtered: row.get(4),
+                    items_skipped_existing: row.get(5),
+                    items_reconciled: row.get(12),
+                    items_summarized: row.get(6),
+                    items_persisted: row.get(7),
+                    items_failed: row.get(8),
+                    ai_time_ms: row.get(9),
+                    api_calls: row.get(10),
+                    errors: Vec::new(),
+                },
+                error_summary: row.get(11),
+            })
+            .collect())
+    }
+
+    /// Fetch the most recent run of every collector
+    pub async fn fetch_latest_runs(&self) -> Result<Vec<Run>, RunHistoryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(
+            &self.store.sql(r#"
+            SELECT DISTINCT ON (collector) id, collector, status, started_at, completed_at, error_summary
+            FROM engineering_metrics.run_history
+            ORDER BY collector, started_at DESC
+            "#),
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.iter().map(run_from_row).collect())
+    }
+
+    /// Fetch the statistics of the latest run of every group/org and import type
+    pub async fn fetch_latest_group_runs(&self) -> Result<Vec<LatestGroupRun>, RunHistoryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(
+            &self.store.sql(r#"
+            SELECT DISTINCT ON (s.group_key, s.import_type)
+                   r.id, r.collector, r.status, r.started_at, r.completed_at, r.error_summary,
+                   s.group_key, s.import_type, s.items_fetched, s.items_filtered, s.items_skipped_existing,
+                   s.items_summarized, s.items_persisted, s.items_failed, s.ai_time_ms, s.api_calls,
+                   s.error_summary, s.items_reconciled
+            FROM engineering_metrics.run_history_group_stats s
+            JOIN engineering_metrics.run_history r ON r.id = s.run_id
+            ORDER BY s.group_key, s.import_type, r.started_at DESC
+            "#),
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let run = run_from_row(row);
+                LatestGroupRun {
+                    stats: RunGroupStatsRecord {
+                        run_id: run.id,
+                        group_key: row.get(6),
+                        import_type: row.get(7),
+                        stats: RunGroupStats {
+                            items_fetched: row.get(8),
+                            items_filtered: row.get(9),
+                            items_skipped_existing: row.get(10),
+                            items_reconciled: row.get(17),
+                            items_summarized: row.get(11),
+                            items_persisted: row.get(12),
+                            items_failed: row.get(13),
+                            ai_time_ms: row.get(14),
+                            api_calls: row.get(15),
+                            errors: Vec::new(),
+                        },
+                        error_summary: row.get(16),
+                    },
+                    run,
+                }
+            })
+            .collect())
+    }
+
+    /// Time of the last activity of each collector: the completion of its latest run, or its
+    /// start while it is still running
+    pub async fn fetch_last_activity(
+        &self,
+        collectors: &[String],
+    ) -> Result<HashMap<String, OffsetDateTime>, RunHistoryError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT collector, MAX(COALESCE(completed_at, started_at))
+            FROM engineering_metrics.run_history
+            WHERE collector = ANY($1)
+            GROUP BY collector
+            "#,
+        ))
+        .bind(collectors)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+}
+
+/// Map the `id, collector, status, started_at, completed_at, error_summary` columns of a run
+fn run_from_row(row: &PgRow) -> Run {
+    let status_str: String = row.get(2);
+    Run {
+        id: row.get(0),
+        collector: row.get(1),
+        status: RunStatus::from_str(&status_str).unwrap_or(RunStatus::Failed),
+        started_at: row.get(3),
+        completed_at: row.get(4),
+        error_summary: row.get(5),
+    }
+}
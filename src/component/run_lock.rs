@@ -0,0 +1,247 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use sqlx::Row;
+use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::task::{JoinError, JoinHandle};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum RunLockError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("Run lock {0} lost: lease was taken over by another instance")]
+    LeaseLost(String),
+}
+
+/// Lease-row based lock that prevents two collector instances from importing
+/// the same group/org at the same time.
+///
+/// A lease expires after `lease_seconds` unless it is renewed, so a crashed run
+/// never blocks subsequent runs for longer than one lease period.
+#[derive(Debug, Clone)]
+pub struct RunLockHandler {
+    pub store: Store,
+    pub holder_name: String,
+    pub lease_seconds: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RunLease {
+    pub lock_key: String,
+    pub holder_id: Uuid,
+    pub expires_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct RunLockHolder {
+    pub holder_id: Uuid,
+    pub holder_name: String,
+    pub acquired_at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+}
+
+impl RunLockHandler {
+    /// Try to acquire the lease for `lock_key`. Returns `None` when another holder owns an unexpired lease.
+    pub async fn try_acquire(&self, lock_key: &str) -> Result<Option<RunLease>, RunLockError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let holder_id = Uuid::now_v7();
+
+        // An existing row is only taken over once its lease has expired
+        let row = sqlx::query(
+            &self.store.sql(r#"
+            INSERT INTO engineering_metrics.run_locks (lock_key, holder_id, holder_name, acquired_at, expires_at)
+            VALUES ($1, $2, $3, NOW(), NOW() + INTERVAL '1 second' * $4)
+            ON CONFLICT (lock_key) DO
+            UPDATE SET
+                holder_id = EXCLUDED.holder_id,
+                holder_name = EXCLUDED.holder_name,
+                acquired_at = EXCLUDED.acquired_at,
+                expires_at = EXCLUDED.expires_at
+            WHERE engineering_metrics.run_locks.expires_at < NOW()
+            RETURNING expires_at
+            "#),
+        )
+        .bind(lock_key)
+        .bind(holder_id)
+        .bind(&self.holder_name)
+        .bind(self.lease_seconds)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(row.map(|row| RunLease {
+            lock_key: lock_key.to_string(),
+            holder_id,
+            expires_at: row.get(0),
+        }))
+    }
+
+    /// Extend the lease. Returns `false` when the lease has been lost to another holder.
+    pub async fn renew(&self, lease: &mut RunLease) -> Result<bool, RunLockError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let row = sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.run_locks
+            SET expires_at = NOW() + INTERVAL '1 second' * $3
+            WHERE lock_key = $1 AND holder_id = $2
+            RETURNING expires_at
+            "#,
+        ))
+        .bind(&lease.lock_key)
+        .bind(lease.holder_id)
+        .bind(self.lease_seconds)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        match row {
+            Some(row) => {
+                lease.expires_at = row.get(0);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Release the lease if it is still held by this holder
+    pub async fn release(&self, lease: &RunLease) -> Result<(), RunLockError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        sqlx::query(&self.store.sql(
+            r#"
+            DELETE FROM engineering_metrics.run_locks
+            WHERE lock_key = $1 AND holder_id = $2
+            "#,
+        ))
+        .bind(&lease.lock_key)
+        .bind(lease.holder_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetch the current holder of `lock_key`, including holders whose lease has already expired
+    pub async fn fetch_holder(
+        &self,
+        lock_key: &str,
+    ) -> Result<Option<RunLockHolder>, RunLockError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let row = sqlx::query(&self.store.sql(
+            r#"
+            SELECT holder_id, holder_name, acquired_at, expires_at
+            FROM engineering_metrics.run_locks
+            WHERE lock_key = $1
+            "#,
+        ))
+        .bind(lock_key)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(row.map(|row| RunLockHolder {
+            holder_id: row.get(0),
+            holder_name: row.get(1),
+            acquired_at: row.get(2),
+            expires_at: row.get(3),
+        }))
+    }
+
+    /// Run `task` while holding the lease for `lock_key`, renewing it in the background.
+    ///
+    /// Returns `None` without running `task` when another instance holds the lease, and
+    /// stops `task` with [`RunLockError::LeaseLost`] when the lease is taken over mid-run.
+    /// Work that `task` spawns must use [`LockedTask`] to be stopped as well.
+    pub async fn run_exclusive<F, T>(
+        &self,
+        lock_key: &str,
+        task: F,
+    ) -> Result<Option<T>, RunLockError>
+    where
+        F: Future<Output = T>,
+    {
+        let mut lease = match self.try_acquire(lock_key).await? {
+            Some(lease) => lease,
+            None => {
+                match self.fetch_holder(lock_key).await? {
+                    Some(holder) => info!(
+                        lock = lock_key,
+                        holder = %holder.holder_name,
+                        acquired_at = %holder.acquired_at,
+                        expires_at = %holder.expires_at,
+                        "Skipping run: lock held by another instance"
+                    ),
+                    None => info!(
+                        lock = lock_key,
+                        "Skipping run: lock held by another instance"
+                    ),
+                }
+                return Ok(None);
+            }
+        };
+
+        // Renew well before expiry so a slow renewal does not let another instance in
+        let renewal_period = Duration::from_secs((self.lease_seconds / 3).max(1) as u64);
+        let mut renewal = tokio::time::interval(renewal_period);
+        renewal.tick().await;
+
+        tokio::pin!(task);
+        let output = loop {
+            tokio::select! {
+                output = &mut task => break output,
+                _ = renewal.tick() => {
+                    match self.renew(&mut lease).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            // Dropping `task` stops it, including work it spawned as a `LockedTask`;
+                            // the lease belongs to the new holder now
+                            warn!(
+                                lock = lock_key,
+                                "Lost run lock: lease was taken over by another instance"
+                            );
+                            return Err(RunLockError::LeaseLost(lock_key.to_string()));
+                        }
+                        Err(e) => warn!(lock = lock_key, error = %e, "Failed to renew run lock"),
+                    }
+                }
+            }
+        };
+
+        self.release(&lease).await?;
+
+        Ok(Some(output))
+    }
+}
+
+/// Task spawned by the work of [`RunLockHandler::run_exclusive`].
+///
+/// Unlike a plain [`JoinHandle`], dropping it aborts the task, so spawned work stops together
+/// with the locked future when the lease is lost.
+#[derive(Debug)]
+pub struct LockedTask<T>(JoinHandle<T>);
+
+impl<T: Send + 'static> LockedTask<T> {
+    pub fn spawn<F>(future: F) -> Self
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        LockedTask(tokio::spawn(future))
+    }
+}
+
+impl<T> Future for LockedTask<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
+impl<T> Drop for LockedTask<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
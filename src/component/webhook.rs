@@ -0,0 +1,321 @@
+use serde_json::Value;
+use sqlx::Row;
+use std::str::FromStr;
+use thiserror::Error;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum WebhookError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("Invalid webhook payload: {0}")]
+    InvalidPayload(String),
+}
+
+/// GitLab webhook events that lead to a refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    MergeRequest,
+    Pipeline,
+    Deployment,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::MergeRequest => "merge_request",
+            WebhookEvent::Pipeline => "pipeline",
+            WebhookEvent::Deployment => "deployment",
+        }
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "merge_request" => Ok(WebhookEvent::MergeRequest),
+            "pipeline" => Ok(WebhookEvent::Pipeline),
+            "deployment" => Ok(WebhookEvent::Deployment),
+            _ => Err(format!("Unknown webhook event: {}", s)),
+        }
+    }
+}
+
+/// What a webhook event asks to re-import
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshTarget {
+    pub event: WebhookEvent,
+    /// Numeric GitLab project id, as stored in `merge_requests.project_id`
+    pub project_id: String,
+    /// Full path of the project, e.g. `platform/backend/api`
+    pub project_path: String,
+    /// `None` refreshes the recently updated merge requests of the project
+    pub mr_iid: Option<String>,
+}
+
+/// Read the refresh target of a GitLab webhook payload.
+///
+/// Merge request events and pipelines of a merge request refresh that merge request; deployments
+/// refresh the project. Returns `None` for events that do not affect merge requests, e.g. push or
+/// issue events, and for pipelines without a merge request, which run on every branch push.
+pub fn refresh_target(payload: &Value) -> Result<Option<RefreshTarget>, WebhookError> {
+    let event = match payload.get("object_kind").and_then(Value::as_str) {
+        Some(kind) => match kind.parse::<WebhookEvent>() {
+            Ok(event) => event,
+            Err(_) => return Ok(None),
+        },
+        None => return Err(missing("object_kind")),
+    };
+
+    let project = payload.get("project");
+    let project_id =
+        identifier(project.and_then(|project| project.get("id"))).ok_or(missing("project.id"))?;
+    let project_path = project
+        .and_then(|project| project.get("path_with_namespace"))
+        .and_then(Value::as_str)
+        .ok_or(missing("project.path_with_namespace"))?
+        .to_string();
+    let mr_iid = match event {
+        WebhookEvent::MergeRequest => Some(
+            identifier(
+                payload
+                    .get("object_attributes")
+                    .and_then(|attributes| attributes.get("iid")),
+            )
+            .ok_or(missing("object_attributes.iid"))?,
+        ),
+        WebhookEvent::Pipeline => match identifier(
+            payload
+                .get("merge_request")
+                .and_then(|merge_request| merge_request.get("iid")),
+        ) {
+            Some(iid) => Some(iid),
+            None => return Ok(None),
+        },
+        WebhookEvent::Deployment => None,
+    };
+
+    Ok(Some(RefreshTarget {
+        event,
+        project_id,
+        project_path,
+        mr_iid,
+    }))
+}
+
+fn missing(field: &str) -> WebhookError {
+    WebhookError::InvalidPayload(format!("{} is missing", field))
+}
+
+/// GitLab sends ids as numbers; strings are accepted as well
+fn identifier(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::Number(number) => Some(number.to_string()),
+        Value::String(text) if !text.is_empty() => Some(text.clone()),
+        _ => None,
+    }
+}
+
+/// The configured group a project belongs to: the longest group full path the project path is nested in
+pub fn group_for_project<'a>(groups: &'a [String], project_path: &str) -> Option<&'a str> {
+    groups
+        .iter()
+        .map(String::as_str)
+        .filter(|group| {
+            project_path
+                .strip_prefix(group)
+                .is_some_and(|rest| rest.starts_with('/'))
+        })
+        .max_by_key(|group| group.len())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshStatus {
+    /// Refreshed by the next pass of the daemon
+    Pending,
+    /// Failed too often; only retried again once a new event arrives for its target
+    Parked,
+}
+
+impl RefreshStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefreshStatus::Pending => "pending",
+            RefreshStatus::Parked => "parked",
+        }
+    }
+}
+
+impl FromStr for RefreshStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(RefreshStatus::Pending),
+            "parked" => Ok(RefreshStatus::Parked),
+            _ => Err(format!("Unknown refresh status: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RefreshRequest {
+    pub id: Uuid,
+    pub group_full_path: String,
+    pub project_id: String,
+    pub project_path: String,
+    pub mr_iid: Option<String>,
+    /// Latest event received for the target
+    pub event: WebhookEvent,
+    pub status: RefreshStatus,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub received_at: OffsetDateTime,
+}
+
+/// Queue of merge requests and projects reported by GitLab webhooks, drained by the daemon
+#[derive(Debug, Clone)]
+pub struct WebhookQueueHandler {
+    pub store: Store,
+}
+
+impl WebhookQueueHandler {
+    /// Queue a refresh, merging it into the request already waiting for the same target
+    pub async fn enqueue(
+        &self,
+        group_full_path: &str,
+        target: &RefreshTarget,
+    ) -> Result<(), WebhookError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+
+        sqlx::query(&self.store.sql(
+            r#"
+            INSERT INTO engineering_metrics.webhook_refresh_queue
+                (id, group_full_path, project_id, project_path, mr_iid, event, received_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (project_id, mr_iid) DO
+            UPDATE SET
+                group_full_path = EXCLUDED.group_full_path,
+                project_path = EXCLUDED.project_path,
+                event = EXCLUDED.event,
+                status = 'pending',
+                attempts = 0,
+                received_at = EXCLUDED.received_at
+            "#,
+        ))
+        .bind(Uuid::now_v7())
+        .bind(group_full_path)
+        .bind(&target.project_id)
+        .bind(&target.project_path)
+        .bind(&target.mr_iid)
+        .bind(target.event.as_str())
+        .bind(OffsetDateTime::now_utc())
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Oldest pending requests first
+    pub async fn fetch_pending(&self, limit: i64) -> Result<Vec<RefreshRequest>, WebhookError> {
+        self.fetch(Some(RefreshStatus::Pending), limit).await
+    }
+
+    /// List queued requests, optionally restricted to one status
+    pub async fn list(
+        &self,
+        status: Option<RefreshStatus>,
+    ) -> Result<Vec<RefreshRequest>, WebhookError> {
+        self.fetch(status, i64::MAX).await
+    }
+
+    async fn fetch(
+        &self,
+        status: Option<RefreshStatus>,
+        limit: i64,
+    ) -> Result<Vec<RefreshRequest>, WebhookError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        let rows = sqlx::query(&self.store.sql(
+            r#"
+            SELECT id, group_full_path, project_id, project_path, mr_iid, event, status,
+                   attempts, last_error, received_at
+            FROM engineering_metrics.webhook_refresh_queue
+            WHERE $1::VARCHAR IS NULL OR status = $1
+            ORDER BY received_at, id
+            LIMIT $2
+            "#,
+        ))
+        .bind(status.map(|status| status.as_str()))
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let event: String = row.get(5);
+                let status: String = row.get(6);
+                Ok(RefreshRequest {
+                    id: row.get(0),
+                    group_full_path: row.get(1),
+                    project_id: row.get(2),
+                    project_path: row.get(3),
+                    mr_iid: row.get(4),
+                    event: event.parse().map_err(WebhookError::InvalidPayload)?,
+                    status: status.parse().map_err(WebhookError::InvalidPayload)?,
+                    attempts: row.get(7),
+                    last_error: row.get(8),
+                    received_at: row.get(9),
+                })
+            })
+            .collect()
+    }
+
+    /// Remove a refreshed request, unless another event for its target arrived in the meantime
+    pub async fn complete(&self, request: &RefreshRequest) -> Result<(), WebhookError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        sqlx::query(&self.store.sql(
+            r#"
+            DELETE FROM engineering_metrics.webhook_refresh_queue
+            WHERE id = $1 AND received_at = $2
+            "#,
+        ))
+        .bind(request.id)
+        .bind(request.received_at)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed refresh, parking the request once it failed `max_attempts` times
+    pub async fn record_failure(
+        &self,
+        request: &RefreshRequest,
+        error_message: &str,
+        max_attempts: i32,
+    ) -> Result<(), WebhookError> {
+        let mut conn = self.store.conn_pool.acquire().await?;
+        sqlx::query(&self.store.sql(
+            r#"
+            UPDATE engineering_metrics.webhook_refresh_queue
+            SET attempts = attempts + 1,
+                status = CASE WHEN attempts + 1 >= $4 THEN 'parked' ELSE 'pending' END,
+                last_error = $3
+            WHERE id = $1 AND received_at = $2
+            "#,
+        ))
+        .bind(request.id)
+        .bind(request.received_at)
+        .bind(error_message)
+        .bind(max_attempts)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1575 @@
+use serde::Deserialize;
+use sqlx::postgres::PgSslMode;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+
+use crate::component::import_progress::ImportProgressPolicy;
+use crate::component::merge_request::ReconcilePolicy;
+use crate::schedule::CronSchedule;
+use crate::scheduler::WorkScheduler;
+use crate::store::{is_valid_schema_name, StoreOptions, DEFAULT_SCHEMA};
+
+/// Config file that is loaded when no path is given and the file exists in the working directory
+pub const DEFAULT_CONFIG_PATH: &str = "collector.toml";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path}: {source}")]
+    ReadError {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse config file {path}: {source}")]
+    ParseError {
+        path: String,
+        source: toml::de::Error,
+    },
+    #[error("Invalid configuration:\n  - {}", .0.join("\n  - "))]
+    Invalid(Vec<String>),
+}
+
+/// Collector configuration.
+///
+/// Values are layered: built-in defaults, then the TOML file, then environment variables,
+/// then command line flags. Nothing is validated while layering; the `*_settings` methods
+/// validate the sections a command needs and report every problem at once.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub gitlab: GitlabConfig,
+    pub ai: AiConfig,
+    pub copilot: CopilotConfig,
+    pub import_progress: ImportProgressConfig,
+    pub run_lock: RunLockConfig,
+    pub reporting: ReportingConfig,
+    pub export: ExportConfig,
+    pub api: ApiConfig,
+    pub webhook: WebhookConfig,
+    pub concurrency: ConcurrencyConfig,
+    pub daemon: DaemonConfig,
+    pub metrics: MetricsConfig,
+    pub logging: LoggingConfig,
+    /// Problems found while layering, e.g. environment variables that do not parse
+    #[serde(skip)]
+    pub load_problems: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: Option<String>,
+    pub url_file: Option<String>,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_seconds: u64,
+    /// Idle connections above `min_connections` are closed after this long; 0 keeps them open
+    pub idle_timeout_seconds: u64,
+    /// Server-side limit of every statement; 0 disables it
+    pub statement_timeout_seconds: u64,
+    /// Shown in `pg_stat_activity`; empty leaves it unset
+    pub application_name: String,
+    /// Further attempts at the initial connection while the database is unreachable or starting
+    pub connect_retries: u32,
+    /// `disable`, `allow`, `prefer`, `require`, `verify-ca` or `verify-full`; overrides the URL's `sslmode`
+    pub ssl_mode: Option<String>,
+    pub ssl_root_cert: Option<String>,
+    pub ssl_client_cert: Option<String>,
+    pub ssl_client_key: Option<String>,
+    /// Schema holding the collector's tables; give every tenant sharing a database its own
+    pub schema: String,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: None,
+            url_file: None,
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_seconds: 30,
+            idle_timeout_seconds: 600,
+            statement_timeout_seconds: 0,
+            application_name: env!("CARGO_PKG_NAME").to_string(),
+            connect_retries: 10,
+            ssl_mode: None,
+            ssl_root_cert: None,
+            ssl_client_cert: None,
+            ssl_client_key: None,
+            schema: DEFAULT_SCHEMA.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitlabConfig {
+    pub rest_endpoint: Option<String>,
+    pub graphql_endpoint: Option<String>,
+    pub api_token: Option<String>,
+    pub api_token_file: Option<String>,
+    pub groups: Vec<GroupConfig>,
+    pub upsert_merge_requests: bool,
+    /// RFC 3339 timestamp to start from when no successful run has been recorded yet
+    pub initial_ingestion_date: Option<String>,
+    pub dead_letter_max_attempts: i32,
+    /// Refresh labels and approvals of stored merge requests instead of skipping them
+    pub reconcile: bool,
+    /// Hours before the watermark at which a reconciling sync starts
+    pub reconcile_overlap_hours: i64,
+    /// Fetch when approved merge requests were first approved, one REST request each
+    pub fetch_approval_times: bool,
+}
+
+impl Default for GitlabConfig {
+    fn default() -> Self {
+        GitlabConfig {
+            rest_endpoint: None,
+            graphql_endpoint: None,
+            api_token: None,
+            api_token_file: None,
+            groups: Vec::new(),
+            upsert_merge_requests: false,
+            initial_ingestion_date: None,
+            dead_letter_max_attempts: 5,
+            reconcile: false,
+            reconcile_overlap_hours: 24,
+            fetch_approval_times: true,
+        }
+    }
+}
+
+/// Settings of one GitLab group; unset values fall back to the global settings
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct GroupConfig {
+    pub full_path: String,
+    pub upsert_merge_requests: Option<bool>,
+    /// Generate AI summaries for the merge requests of this group
+    pub ai_summaries: Option<bool>,
+}
+
+impl GroupConfig {
+    pub fn new(full_path: &str) -> Self {
+        GroupConfig {
+            full_path: full_path.to_string(),
+            ..GroupConfig::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AiConfig {
+    pub enabled: bool,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+    pub api_key: Option<String>,
+    pub api_key_file: Option<String>,
+    pub max_context_chars: usize,
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        AiConfig {
+            enabled: true,
+            base_url: None,
+            model: None,
+            api_key: None,
+            api_key_file: None,
+            max_context_chars: 10000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CopilotConfig {
+    pub api_token: Option<String>,
+    pub api_token_file: Option<String>,
+    pub api_endpoint: String,
+    pub api_version: String,
+    pub orgs: Vec<String>,
+    pub report_lag_days: i64,
+    /// RFC 3339 timestamp to start from for orgs without a successful run
+    pub initial_ingestion_date: Option<String>,
+}
+
+impl Default for CopilotConfig {
+    fn default() -> Self {
+        CopilotConfig {
+            api_token: None,
+            api_token_file: None,
+            api_endpoint: "https://api.github.com".to_string(),
+            api_version: "2026-03-10".to_string(),
+            orgs: Vec::new(),
+            report_lag_days: 2,
+            initial_ingestion_date: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImportProgressConfig {
+    pub retention_days: i32,
+    pub stale_after_hours: i64,
+    pub restart_on_rejected_cursor: bool,
+}
+
+impl Default for ImportProgressConfig {
+    fn default() -> Self {
+        ImportProgressConfig {
+            retention_days: 30,
+            stale_after_hours: 72,
+            restart_on_rejected_cursor: true,
+        }
+    }
+}
+
+impl ImportProgressConfig {
+    pub fn policy(&self) -> ImportProgressPolicy {
+        ImportProgressPolicy {
+            retention_days: self.retention_days,
+            stale_after: Duration::hours(self.stale_after_hours),
+            restart_on_rejected_cursor: self.restart_on_rejected_cursor,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunLockConfig {
+    pub lease_seconds: i64,
+}
+
+impl Default for RunLockConfig {
+    fn default() -> Self {
+        RunLockConfig { lease_seconds: 900 }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportingConfig {
+    /// Refresh the materialized rollups and flow metrics fed by a collector after each of its runs
+    pub refresh_after_runs: bool,
+    /// Lengths in days of the windows flow metrics are computed over
+    pub flow_metric_windows_days: Vec<i32>,
+}
+
+impl Default for ReportingConfig {
+    fn default() -> Self {
+        ReportingConfig {
+            refresh_after_runs: true,
+            flow_metric_windows_days: vec![7, 30, 90],
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    /// Key of the HMAC that turns usernames into pseudonyms; keep it to get the same pseudonyms
+    /// across exports
+    pub pseudonymization_key: Option<String>,
+    pub pseudonymization_key_file: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Address the `api` command serves the read-only REST API on
+    pub listen_address: String,
+    /// API keys accepted by the REST API, separated by commas or newlines so keys can be rotated
+    pub keys: Option<String>,
+    pub keys_file: Option<String>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig {
+            listen_address: "0.0.0.0:8080".to_string(),
+            keys: None,
+            keys_file: None,
+        }
+    }
+}
+
+/// GitLab webhook receiver of the daemon; it is enabled by setting a secret token
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Secret token configured on the GitLab webhooks, sent in the `X-Gitlab-Token` header
+    pub secret_token: Option<String>,
+    pub secret_token_file: Option<String>,
+    /// Seconds between two passes over the queued refreshes
+    pub refresh_interval_seconds: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            secret_token: None,
+            secret_token_file: None,
+            refresh_interval_seconds: 30,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConcurrencyConfig {
+    /// GitLab groups imported at the same time
+    pub max_concurrent_groups: usize,
+    /// Copilot orgs imported at the same time
+    pub max_concurrent_orgs: usize,
+    /// Merge requests of one page whose changes, AI summary and write run at the same time
+    pub max_concurrent_merge_requests: usize,
+    /// API requests in flight across all groups and orgs of the process
+    pub max_concurrent_requests: usize,
+    /// API requests in flight to one host (GitLab, GitHub, the AI endpoint)
+    pub max_concurrent_requests_per_host: usize,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        ConcurrencyConfig {
+            max_concurrent_groups: 4,
+            max_concurrent_orgs: 4,
+            max_concurrent_merge_requests: 4,
+            max_concurrent_requests: 16,
+            max_concurrent_requests_per_host: 8,
+        }
+    }
+}
+
+impl ConcurrencyConfig {
+    /// Scheduler shared by every import of a command, or of all jobs of the daemon
+    pub fn scheduler(&self) -> WorkScheduler {
+        WorkScheduler::new(
+            self.max_concurrent_requests,
+            self.max_concurrent_requests_per_host,
+        )
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Address the daemon serves `/metrics` and the health endpoints on; an empty address disables the HTTP server
+    pub listen_address: String,
+    /// File the metrics are written to after a one-shot command, for the node_exporter textfile collector
+    pub textfile: Option<PathBuf>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            listen_address: "0.0.0.0:9090".to_string(),
+            textfile: None,
+        }
+    }
+}
+
+/// Output format of the log lines written to stderr
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, carrying the fields of the enclosing spans
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("expected 'text' or 'json'".to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    /// Level filter in `RUST_LOG` syntax, e.g. `info` or `info,engineering_metrics_data_collector=debug`
+    pub level: String,
+    /// OTLP/HTTP endpoint the tracing spans are exported to; spans are not exported when unset
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            format: LogFormat::Text,
+            level: "info".to_string(),
+            otlp_endpoint: None,
+            service_name: env!("CARGO_PKG_NAME").to_string(),
+        }
+    }
+}
+
+/// Cron schedules of the daemon jobs; a job without a schedule does not run in daemon mode
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    pub gitlab_sync: Option<String>,
+    pub copilot_sync: Option<String>,
+    pub ai_backfill: Option<String>,
+    /// Maximum number of merge requests summarized by one scheduled AI backfill
+    pub ai_backfill_limit: i64,
+    /// `/readyz` fails once a scheduled job has shown no activity for longer than this; it has to
+    /// exceed the longest schedule interval plus one run, the default fits daily schedules
+    pub max_run_age_hours: i64,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        DaemonConfig {
+            gitlab_sync: None,
+            copilot_sync: None,
+            ai_backfill: None,
+            ai_backfill_limit: 500,
+            max_run_age_hours: 26,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DatabaseSettings {
+    pub url: String,
+    pub options: StoreOptions,
+}
+
+#[derive(Debug, Clone)]
+pub struct GitlabApiSettings {
+    pub rest_endpoint: String,
+    pub graphql_endpoint: String,
+    pub api_token: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AiSettings {
+    pub base_url: String,
+    pub model: String,
+    pub api_key: String,
+    pub max_context_chars: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct GitlabSyncSettings {
+    pub api: GitlabApiSettings,
+    pub groups: Vec<GroupConfig>,
+    pub upsert_merge_requests: bool,
+    pub initial_ingestion_date: Option<String>,
+    pub dead_letter_max_attempts: i32,
+    pub reconcile_policy: ReconcilePolicy,
+    pub fetch_approval_times: bool,
+    /// Whether AI summaries are generated unless a group overrides it
+    pub ai_enabled: bool,
+    /// Only resolved when at least one group generates AI summaries
+    pub ai: Option<AiSettings>,
+}
+
+impl GitlabSyncSettings {
+    pub fn upsert_merge_requests_for(&self, group: &GroupConfig) -> bool {
+        group
+            .upsert_merge_requests
+            .unwrap_or(self.upsert_merge_requests)
+    }
+
+    pub fn ai_summaries_for(&self, group: &GroupConfig) -> bool {
+        group.ai_summaries.unwrap_or(self.ai_enabled)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CopilotSettings {
+    pub api_token: String,
+    pub api_endpoint: String,
+    pub api_version: String,
+    pub orgs: Vec<String>,
+    pub report_lag_days: i64,
+    pub initial_ingestion_date: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiSettings {
+    pub listen_address: SocketAddr,
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookSettings {
+    pub secret_token: String,
+    pub refresh_interval: std::time::Duration,
+    /// Settings of the scheduled GitLab sync; events of projects outside its groups are ignored
+    pub gitlab: GitlabSyncSettings,
+}
+
+/// A daemon job together with the settings it runs with
+#[derive(Debug, Clone)]
+pub struct ScheduledJob<T> {
+    pub schedule: CronSchedule,
+    pub settings: T,
+}
+
+#[derive(Debug, Clone)]
+pub struct DaemonSettings {
+    pub gitlab_sync: Option<ScheduledJob<GitlabSyncSettings>>,
+    pub copilot_sync: Option<ScheduledJob<CopilotSettings>>,
+    pub ai_backfill: Option<ScheduledJob<(GitlabApiSettings, AiSettings)>>,
+    pub ai_backfill_limit: i64,
+    pub max_run_age: Duration,
+    /// Address of the HTTP server, `None` when it is disabled
+    pub listen_address: Option<SocketAddr>,
+    /// GitLab webhook receiver, `None` when no secret token is set
+    pub webhook: Option<WebhookSettings>,
+}
+
+impl Config {
+    /// Load the config file and apply the environment on top of it.
+    ///
+    /// Without an explicit path, `collector.toml` is loaded if it exists; otherwise only
+    /// defaults and the environment are used.
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Config::default(),
+        };
+        config.apply_env(|name| std::env::var(name).ok());
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::ReadError {
+            path: path.display().to_string(),
+            source,
+        })?;
+
+        toml::from_str(&content).map_err(|source| ConfigError::ParseError {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    /// Override values with the environment variables returned by `lookup`. Empty values are ignored.
+    pub fn apply_env<F>(&mut self, lookup: F)
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let get = |name: &str| lookup(name).filter(|value| !value.trim().is_empty());
+        let problems = &mut self.load_problems;
+
+        if let Some(url) = get("DATABASE_URL") {
+            set_secret(&mut self.database.url, &mut self.database.url_file, url);
+        }
+        if let Some(file) = get("DATABASE_URL_FILE") {
+            set_secret_file(&mut self.database.url, &mut self.database.url_file, file);
+        }
+        parse_env(
+            get("DATABASE_MAX_CONNECTIONS"),
+            "DATABASE_MAX_CONNECTIONS",
+            &mut self.database.max_connections,
+            problems,
+        );
+        parse_env(
+            get("DATABASE_MIN_CONNECTIONS"),
+            "DATABASE_MIN_CONNECTIONS",
+            &mut self.database.min_connections,
+            problems,
+        );
+        parse_env(
+            get("DATABASE_ACQUIRE_TIMEOUT_SECONDS"),
+            "DATABASE_ACQUIRE_TIMEOUT_SECONDS",
+            &mut self.database.acquire_timeout_seconds,
+            problems,
+        );
+        parse_env(
+            get("DATABASE_IDLE_TIMEOUT_SECONDS"),
+            "DATABASE_IDLE_TIMEOUT_SECONDS",
+            &mut self.database.idle_timeout_seconds,
+            problems,
+        );
+        parse_env(
+            get("DATABASE_STATEMENT_TIMEOUT_SECONDS"),
+            "DATABASE_STATEMENT_TIMEOUT_SECONDS",
+            &mut self.database.statement_timeout_seconds,
+            problems,
+        );
+        if let Some(application_name) = get("DATABASE_APPLICATION_NAME") {
+            self.database.application_name = application_name;
+        }
+        parse_env(
+            get("DATABASE_CONNECT_RETRIES"),
+            "DATABASE_CONNECT_RETRIES",
+            &mut self.database.connect_retries,
+            problems,
+        );
+        if let Some(ssl_mode) = get("DATABASE_SSL_MODE") {
+            self.database.ssl_mode = Some(ssl_mode);
+        }
+        if let Some(ssl_root_cert) = get("DATABASE_SSL_ROOT_CERT") {
+            self.database.ssl_root_cert = Some(ssl_root_cert);
+        }
+        if let Some(ssl_client_cert) = get("DATABASE_SSL_CLIENT_CERT") {
+            self.database.ssl_client_cert = Some(ssl_client_cert);
+        }
+        if let Some(ssl_client_key) = get("DATABASE_SSL_CLIENT_KEY") {
+            self.database.ssl_client_key = Some(ssl_client_key);
+        }
+        if let Some(schema) = get("DATABASE_SCHEMA") {
+            self.database.schema = schema;
+        }
+
+        if let Some(rest_endpoint) = get("GITLAB_REST_ENDPOINT") {
+            self.gitlab.rest_endpoint = Some(rest_endpoint);
+        }
+        if let Some(graphql_endpoint) = get("GITLAB_GRAPHQL_ENDPOINT") {
+            self.gitlab.graphql_endpoint = Some(graphql_endpoint);
+        }
+        if let Some(token) = get("GITLAB_API_TOKEN") {
+            set_secret(
+                &mut self.gitlab.api_token,
+                &mut self.gitlab.api_token_file,
+                token,
+            );
+        }
+        if let Some(file) = get("GITLAB_API_TOKEN_FILE") {
+            set_secret_file(
+                &mut self.gitlab.api_token,
+                &mut self.gitlab.api_token_file,
+                file,
+            );
+        }
+        if let Some(group_list) = get("GITLAB_FULL_PATH_GROUP_LIST") {
+            self.gitlab.set_group_paths(&split_list(&group_list));
+        }
+        parse_env(
+            get("UPSERT_MERGE_REQUESTS"),
+            "UPSERT_MERGE_REQUESTS",
+            &mut self.gitlab.upsert_merge_requests,
+            problems,
+        );
+        if let Some(initial_ingestion_date) = get("INITIAL_INGESTION_DATE") {
+            self.gitlab.initial_ingestion_date = Some(initial_ingestion_date);
+        }
+        parse_env(
+            get("DEAD_LETTER_MAX_ATTEMPTS"),
+            "DEAD_LETTER_MAX_ATTEMPTS",
+            &mut self.gitlab.dead_letter_max_attempts,
+            problems,
+        );
+        parse_env(
+            get("RECONCILE_MERGE_REQUESTS"),
+            "RECONCILE_MERGE_REQUESTS",
+            &mut self.gitlab.reconcile,
+            problems,
+        );
+        parse_env(
+            get("RECONCILE_OVERLAP_HOURS"),
+            "RECONCILE_OVERLAP_HOURS",
+            &mut self.gitlab.reconcile_overlap_hours,
+            problems,
+        );
+        parse_env(
+            get("FETCH_APPROVAL_TIMES"),
+            "FETCH_APPROVAL_TIMES",
+            &mut self.gitlab.fetch_approval_times,
+            problems,
+        );
+
+        parse_env(
+            get("AI_ENABLED"),
+            "AI_ENABLED",
+            &mut self.ai.enabled,
+            problems,
+        );
+        if let Some(base_url) = get("AI_BASE_URL") {
+            self.ai.base_url = Some(base_url);
+        }
+        if let Some(model) = get("AI_MODEL") {
+            self.ai.model = Some(model);
+        }
+        if let Some(api_key) = get("AI_API_KEY") {
+            set_secret(&mut self.ai.api_key, &mut self.ai.api_key_file, api_key);
+        }
+        if let Some(file) = get("AI_API_KEY_FILE") {
+            set_secret_file(&mut self.ai.api_key, &mut self.ai.api_key_file, file);
+        }
+        parse_env(
+            get("AI_MAX_CONTEXT_CHARS"),
+            "AI_MAX_CONTEXT_CHARS",
+            &mut self.ai.max_context_chars,
+            problems,
+        );
+
+        if let Some(token) = get("GITHUB_API_TOKEN") {
+            set_secret(
+                &mut self.copilot.api_token,
+                &mut self.copilot.api_token_file,
+                token,
+            );
+        }
+        if let Some(file) = get("GITHUB_API_TOKEN_FILE") {
+            set_secret_file(
+                &mut self.copilot.api_token,
+                &mut self.copilot.api_token_file,
+                file,
+            );
+        }
+        if let Some(api_endpoint) = get("GITHUB_API_ENDPOINT") {
+            self.copilot.api_endpoint = api_endpoint;
+        }
+        if let Some(api_version) = get("GITHUB_API_VERSION") {
+            self.copilot.api_version = api_version;
+        }
+        if let Some(org_list) = get("GITHUB_COPILOT_ORG_LIST") {
+            self.copilot.orgs = split_list(&org_list);
+        }
+        parse_env(
+            get("COPILOT_REPORT_LAG_DAYS"),
+            "COPILOT_REPORT_LAG_DAYS",
+            &mut self.copilot.report_lag_days,
+            problems,
+        );
+        if let Some(initial_ingestion_date) = get("COPILOT_INITIAL_INGESTION_DATE") {
+            self.copilot.initial_ingestion_date = Some(initial_ingestion_date);
+        }
+
+        parse_env(
+            get("IMPORT_PROGRESS_RETENTION_DAYS"),
+            "IMPORT_PROGRESS_RETENTION_DAYS",
+            &mut self.import_progress.retention_days,
+            problems,
+        );
+        parse_env(
+            get("IMPORT_STALE_AFTER_HOURS"),
+            "IMPORT_STALE_AFTER_HOURS",
+            &mut self.import_progress.stale_after_hours,
+            problems,
+        );
+        parse_env(
+            get("RESTART_ON_REJECTED_CURSOR"),
+            "RESTART_ON_REJECTED_CURSOR",
+            &mut self.import_progress.restart_on_rejected_cursor,
+            problems,
+        );
+        parse_env(
+            get("RUN_LOCK_LEASE_SECONDS"),
+            "RUN_LOCK_LEASE_SECONDS",
+            &mut self.run_lock.lease_seconds,
+            problems,
+        );
+        parse_env(
+            get("REFRESH_ROLLUPS_AFTER_RUNS"),
+            "REFRESH_ROLLUPS_AFTER_RUNS",
+            &mut self.reporting.refresh_after_runs,
+            problems,
+        );
+        if let Some(windows) = get("FLOW_METRIC_WINDOWS_DAYS") {
+            match parse_window_days(&windows) {
+                Ok(windows) => self.reporting.flow_metric_windows_days = windows,
+                Err(e) => problems.push(format!("FLOW_METRIC_WINDOWS_DAYS: {}", e)),
+            }
+        }
+        if let Some(key) = get("EXPORT_PSEUDONYMIZATION_KEY") {
+            set_secret(
+                &mut self.export.pseudonymization_key,
+                &mut self.export.pseudonymization_key_file,
+                key,
+            );
+        }
+        if let Some(file) = get("EXPORT_PSEUDONYMIZATION_KEY_FILE") {
+            set_secret_file(
+                &mut self.export.pseudonymization_key,
+                &mut self.export.pseudonymization_key_file,
+                file,
+            );
+        }
+        if let Some(listen_address) = get("API_LISTEN_ADDRESS") {
+            self.api.listen_address = listen_address;
+        }
+        if let Some(keys) = get("API_KEYS") {
+            set_secret(&mut self.api.keys, &mut self.api.keys_file, keys);
+        }
+        if let Some(file) = get("API_KEYS_FILE") {
+            set_secret_file(&mut self.api.keys, &mut self.api.keys_file, file);
+        }
+        if let Some(token) = get("WEBHOOK_SECRET_TOKEN") {
+            set_secret(
+                &mut self.webhook.secret_token,
+                &mut self.webhook.secret_token_file,
+                token,
+            );
+        }
+        if let Some(file) = get("WEBHOOK_SECRET_TOKEN_FILE") {
+            set_secret_file(
+                &mut self.webhook.secret_token,
+                &mut self.webhook.secret_token_file,
+                file,
+            );
+        }
+        parse_env(
+            get("WEBHOOK_REFRESH_INTERVAL_SECONDS"),
+            "WEBHOOK_REFRESH_INTERVAL_SECONDS",
+            &mut self.webhook.refresh_interval_seconds,
+            problems,
+        );
+        parse_env(
+            get("MAX_CONCURRENT_GROUPS"),
+            "MAX_CONCURRENT_GROUPS",
+            &mut self.concurrency.max_concurrent_groups,
+            problems,
+        );
+        parse_env(
+            get("MAX_CONCURRENT_ORGS"),
+            "MAX_CONCURRENT_ORGS",
+            &mut self.concurrency.max_concurrent_orgs,
+            problems,
+        );
+        parse_env(
+            get("MAX_CONCURRENT_MERGE_REQUESTS"),
+            "MAX_CONCURRENT_MERGE_REQUESTS",
+            &mut self.concurrency.max_concurrent_merge_requests,
+            problems,
+        );
+        parse_env(
+            get("MAX_CONCURRENT_REQUESTS"),
+            "MAX_CONCURRENT_REQUESTS",
+            &mut self.concurrency.max_concurrent_requests,
+            problems,
+        );
+        parse_env(
+            get("MAX_CONCURRENT_REQUESTS_PER_HOST"),
+            "MAX_CONCURRENT_REQUESTS_PER_HOST",
+            &mut self.concurrency.max_concurrent_requests_per_host,
+            problems,
+        );
+        for (name, schedule) in [
+            ("DAEMON_GITLAB_SYNC_SCHEDULE", &mut self.daemon.gitlab_sync),
+            (
+                "DAEMON_COPILOT_SYNC_SCHEDULE",
+                &mut self.daemon.copilot_sync,
+            ),
+            ("DAEMON_AI_BACKFILL_SCHEDULE", &mut self.daemon.ai_backfill),
+        ] {
+            if let Some(value) = get(name) {
+                *schedule = Some(value);
+            }
+        }
+        parse_env(
+            get("DAEMON_AI_BACKFILL_LIMIT"),
+            "DAEMON_AI_BACKFILL_LIMIT",
+            &mut self.daemon.ai_backfill_limit,
+            problems,
+        );
+        parse_env(
+            get("DAEMON_MAX_RUN_AGE_HOURS"),
+            "DAEMON_MAX_RUN_AGE_HOURS",
+            &mut self.daemon.max_run_age_hours,
+            problems,
+        );
+
+        if let Some(listen_address) = lookup("METRICS_LISTEN_ADDRESS") {
+            // An empty value disables the server, so it is not filtered out like other variables
+            self.metrics.listen_address = listen_address;
+        }
+        if let Some(textfile) = get("METRICS_TEXTFILE") {
+            self.metrics.textfile = Some(PathBuf::from(textfile));
+        }
+
+        parse_env(
+            get("LOG_FORMAT"),
+            "LOG_FORMAT",
+            &mut self.logging.format,
+            problems,
+        );
+        if let Some(level) = get("LOG_LEVEL") {
+            self.logging.level = level;
+        }
+        if let Some(endpoint) = get("OTEL_EXPORTER_OTLP_ENDPOINT") {
+            self.logging.otlp_endpoint = Some(endpoint);
+        }
+        if let Some(service_name) = get("OTEL_SERVICE_NAME") {
+            self.logging.service_name = service_name;
+        }
+    }
+
+    /// Validate every configured section and return all problems found.
+    ///
+    /// The GitLab and Copilot sections are only checked when groups or orgs are configured.
+    pub fn check(&self) -> Vec<String> {
+        let mut problems = self.load_problems.clone();
+        self.resolve_database(&mut problems);
+        self.check_shared(&mut problems);
+
+        if !self.gitlab.groups.is_empty() {
+            self.resolve_gitlab_sync(&mut problems);
+        }
+        if !self.copilot.orgs.is_empty() {
+            self.resolve_copilot(&mut problems);
+        }
+        for (schedule, name) in [
+            (&self.daemon.gitlab_sync, "daemon.gitlab_sync"),
+            (&self.daemon.copilot_sync, "daemon.copilot_sync"),
+            (&self.daemon.ai_backfill, "daemon.ai_backfill"),
+        ] {
+            parse_schedule(schedule, name, &mut problems);
+        }
+        self.resolve_listen_address(&mut problems);
+        if let Err(e) = crate::telemetry::parse_level(&self.logging.level) {
+            problems.push(format!("logging.level: {}", e));
+        }
+        if self.gitlab.groups.is_empty() && self.copilot.orgs.is_empty() {
+            problems.push(
+                "neither gitlab.groups nor copilot.orgs is configured, there is nothing to sync"
+                    .to_string(),
+            );
+        }
+
+        problems
+    }
+
+    pub fn database_settings(&self) -> Result<DatabaseSettings, ConfigError> {
+        self.settings(|config, problems| config.resolve_database(problems))
+    }
+
+    pub fn gitlab_sync_settings(&self) -> Result<GitlabSyncSettings, ConfigError> {
+        self.settings(|config, problems| {
+            config.check_shared(problems);
+            config.resolve_gitlab_sync(problems)
+        })
+    }
+
+    /// GitLab API and AI settings, without requiring any group to be configured
+    pub fn ai_backfill_settings(&self) -> Result<(GitlabApiSettings, AiSettings), ConfigError> {
+        self.settings(|config, problems| {
+            let api = config.resolve_gitlab_api(problems);
+            let ai = config.resolve_ai(problems);
+            api.zip(ai)
+        })
+    }
+
+    pub fn copilot_settings(&self) -> Result<CopilotSettings, ConfigError> {
+        self.settings(|config, problems| {
+            config.check_shared(problems);
+            config.resolve_copilot(problems)
+        })
+    }
+
+    /// Settings of every scheduled daemon job, validating only the sections those jobs need
+    pub fn daemon_settings(&self) -> Result<DaemonSettings, ConfigError> {
+        self.settings(|config, problems| {
+            config.check_shared(problems);
+            config.resolve_daemon(problems)
+        })
+    }
+
+    /// Key to pseudonymize exported usernames with
+    pub fn export_pseudonymization_key(&self) -> Result<String, ConfigError> {
+        self.settings(|config, problems| {
+            let key = resolve_secret(
+                &config.export.pseudonymization_key,
+                &config.export.pseudonymization_key_file,
+                "export.pseudonymization_key",
+                problems,
+            )?;
+            if key.len() < 16 {
+                problems.push(
+                    "export.pseudonymization_key must be at least 16 characters long".to_string(),
+                );
+            }
+            Some(key)
+        })
+    }
+
+    /// Listen address and accepted keys of the REST API
+    pub fn api_settings(&self) -> Result<ApiSettings, ConfigError> {
+        self.settings(|config, problems| {
+            let listen_address = match config.api.listen_address.trim().parse() {
+                Ok(address) => Some(address),
+                Err(_) => {
+                    problems.push(format!(
+                        "api.listen_address must be a socket address like 0.0.0.0:8080, got '{}'",
+                        config.api.listen_address
+                    ));
+                    None
+                }
+            };
+            let keys: Vec<String> = resolve_secret(
+                &config.api.keys,
+                &config.api.keys_file,
+                "api.keys",
+                problems,
+            )?
+            .split([',', '\n'])
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect();
+            if keys.iter().any(|key| key.len() < 16) {
+                problems.push("api.keys must each be at least 16 characters long".to_string());
+            }
+
+            listen_address.map(|listen_address| ApiSettings {
+                listen_address,
+                keys,
+            })
+        })
+    }
+
+    fn settings<T, F>(&self, resolve: F) -> Result<T, ConfigError>
+    where
+        F: FnOnce(&Config, &mut Vec<String>) -> Option<T>,
+    {
+        let mut problems = self.load_problems.clone();
+        match resolve(self, &mut problems) {
+            Some(settings) if problems.is_empty() => Ok(settings),
+            _ => Err(ConfigError::Invalid(problems)),
+        }
+    }
+
+    fn resolve_daemon(&self, problems: &mut Vec<String>) -> Option<DaemonSettings> {
+        let daemon = &self.daemon;
+        if daemon.gitlab_sync.is_none()
+            && daemon.copilot_sync.is_none()
+            && daemon.ai_backfill.is_none()
+        {
+            problems.push(
+                "no daemon job is scheduled; set daemon.gitlab_sync, daemon.copilot_sync or daemon.ai_backfill"
+                    .to_string(),
+            );
+            return None;
+        }
+        if daemon.ai_backfill_limit < 1 {
+            problems.push("daemon.ai_backfill_limit must be at least 1".to_string());
+        }
+        if daemon.max_run_age_hours < 1 {
+            problems.push("daemon.max_run_age_hours must be at least 1".to_string());
+        }
+
+        let problems_before = problems.len();
+        let gitlab_sync = parse_schedule(&daemon.gitlab_sync, "daemon.gitlab_sync", problems)
+            .and_then(|schedule| {
+                self.resolve_gitlab_sync(problems)
+                    .map(|settings| ScheduledJob { schedule, settings })
+            });
+        let copilot_sync = parse_schedule(&daemon.copilot_sync, "daemon.copilot_sync", problems)
+            .and_then(|schedule| {
+                self.resolve_copilot(problems)
+                    .map(|settings| ScheduledJob { schedule, settings })
+            });
+        let ai_backfill = parse_schedule(&daemon.ai_backfill, "daemon.ai_backfill", problems)
+            .and_then(|schedule| {
+                let api = self.resolve_gitlab_api(problems);
+                let ai = self.resolve_ai(problems);
+                api.zip(ai)
+                    .map(|settings| ScheduledJob { schedule, settings })
+            });
+        if problems.len() > problems_before {
+            // Jobs share the GitLab and AI sections, report each problem once
+            let mut seen = std::collections::HashSet::new();
+            let new_problems = problems.split_off(problems_before);
+            problems.extend(
+                new_problems
+                    .into_iter()
+                    .filter(|problem| seen.insert(problem.clone())),
+            );
+            return None;
+        }
+
+        let listen_address = self.resolve_listen_address(problems)?;
+        let webhook = self.resolve_webhook(gitlab_sync.as_ref(), listen_address, problems)?;
+        Some(DaemonSettings {
+            gitlab_sync,
+            copilot_sync,
+            ai_backfill,
+            ai_backfill_limit: daemon.ai_backfill_limit,
+            max_run_age: Duration::hours(daemon.max_run_age_hours),
+            listen_address,
+            webhook,
+        })
+    }
+
+    /// `Some(None)` when no secret token is set, `None` when the receiver cannot be enabled
+    fn resolve_webhook(
+        &self,
+        gitlab_sync: Option<&ScheduledJob<GitlabSyncSettings>>,
+        listen_address: Option<SocketAddr>,
+        problems: &mut Vec<String>,
+    ) -> Option<Option<WebhookSettings>> {
+        let webhook = &self.webhook;
+        if webhook.secret_token.is_none() && webhook.secret_token_file.is_none() {
+            return Some(None);
+        }
+
+        let secret_token = resolve_secret(
+            &webhook.secret_token,
+            &webhook.secret_token_file,
+            "webhook.secret_token",
+            problems,
+        );
+        if secret_token.as_ref().is_some_and(|token| token.len() < 16) {
+            problems.push("webhook.secret_token must be at least 16 characters long".to_string());
+        }
+        if webhook.refresh_interval_seconds < 1 {
+            problems.push("webhook.refresh_interval_seconds must be at least 1".to_string());
+        }
+        if listen_address.is_none() {
+            problems.push(
+                "webhook.secret_token needs the HTTP server; set metrics.listen_address"
+                    .to_string(),
+            );
+        }
+        // The scheduled sync catches up on events that were missed or failed to refresh
+        let Some(gitlab_sync) = gitlab_sync else {
+            problems.push("webhook.secret_token needs daemon.gitlab_sync".to_string());
+            return None;
+        };
+
+        Some(Some(WebhookSettings {
+            secret_token: secret_token?,
+            refresh_interval: std::time::Duration::from_secs(webhook.refresh_interval_seconds),
+            gitlab: gitlab_sync.settings.clone(),
+        }))
+    }
+
+    /// `Some(None)` when the HTTP server is disabled, `None` when the address is invalid
+    fn resolve_listen_address(&self, problems: &mut Vec<String>) -> Option<Option<SocketAddr>> {
+        let listen_address = self.metrics.listen_address.trim();
+        if listen_address.is_empty() {
+            return Some(None);
+        }
+        match listen_address.parse() {
+            Ok(address) => Some(Some(address)),
+            Err(_) => {
+                problems.push(format!(
+                    "metrics.listen_address must be a socket address like 0.0.0.0:9090, got '{}'",
+                    listen_address
+                ));
+                None
+            }
+        }
+    }
+
+    fn resolve_database(&self, problems: &mut Vec<String>) -> Option<DatabaseSettings> {
+        let url = resolve_secret(
+            &self.database.url,
+            &self.database.url_file,
+            "database.url",
+            problems,
+        );
+        if let Some(url) = &url {
+            if !url.starts_with("postgres://") && !url.starts_with("postgresql://") {
+                problems.push("database.url must start with postgres:// or postgresql://".into());
+            }
+        }
+        let database = &self.database;
+        if database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+        if database.min_connections > database.max_connections {
+            problems.push(
+                "database.min_connections must not exceed database.max_connections".to_string(),
+            );
+        }
+        if database.acquire_timeout_seconds == 0 {
+            problems.push("database.acquire_timeout_seconds must be at least 1".to_string());
+        }
+        let ssl_mode = match database.ssl_mode.as_deref().map(PgSslMode::from_str) {
+            Some(Ok(ssl_mode)) => Some(ssl_mode),
+            Some(Err(_)) => {
+                problems.push(
+                    "database.ssl_mode must be one of disable, allow, prefer, require, verify-ca, verify-full"
+                        .to_string(),
+                );
+                None
+            }
+            None => None,
+        };
+        for (path, name) in [
+            (&database.ssl_root_cert, "database.ssl_root_cert"),
+            (&database.ssl_client_cert, "database.ssl_client_cert"),
+            (&database.ssl_client_key, "database.ssl_client_key"),
+        ] {
+            if let Some(path) = path {
+                if !Path::new(path).is_file() {
+                    problems.push(format!("{}: file {} does not exist", name, path));
+                }
+            }
+        }
+        if database.ssl_client_cert.is_some() != database.ssl_client_key.is_some() {
+            problems.push(
+                "database.ssl_client_cert and database.ssl_client_key must be set together"
+                    .to_string(),
+            );
+        }
+        if !is_valid_schema_name(&database.schema) {
+            problems.push(format!(
+                "database.schema '{}' must be a lowercase identifier (letters, digits, _) of at most 63 characters, not starting with pg_",
+                database.schema
+            ));
+        }
+
+        let seconds = |seconds: u64| (seconds > 0).then(|| std::time::Duration::from_secs(seconds));
+        url.map(|url| DatabaseSettings {
+            url,
+            options: StoreOptions {
+                max_connections: database.max_connections,
+                min_connections: database.min_connections,
+                acquire_timeout: std::time::Duration::from_secs(database.acquire_timeout_seconds),
+                idle_timeout: seconds(database.idle_timeout_seconds),
+                statement_timeout: seconds(database.statement_timeout_seconds),
+                application_name: Some(database.application_name.trim().to_string())
+                    .filter(|name| !name.is_empty()),
+                ssl_mode,
+                ssl_root_cert: database.ssl_root_cert.clone(),
+                ssl_client_cert: database.ssl_client_cert.clone(),
+                ssl_client_key: database.ssl_client_key.clone(),
+                connect_retries: database.connect_retries,
+                schema: database.schema.clone(),
+                ..StoreOptions::default()
+            },
+        })
+    }
+
+    fn check_shared(&self, problems: &mut Vec<String>) {
+        if self.import_progress.retention_days < 1 {
+            problems.push("import_progress.retention_days must be at least 1".to_string());
+        }
+        if self.import_progress.stale_after_hours < 1 {
+            problems.push("import_progress.stale_after_hours must be at least 1".to_string());
+        }
+        if self.run_lock.lease_seconds < 1 {
+            problems.push("run_lock.lease_seconds must be at least 1".to_string());
+        }
+        if self
+            .reporting
+            .flow_metric_windows_days
+            .iter()
+            .any(|days| !(1..=3650).contains(days))
+        {
+            problems.push(
+                "reporting.flow_metric_windows_days must be between 1 and 3650 days".to_string(),
+            );
+        }
+        for (value, name) in [
+            (
+                self.concurrency.max_concurrent_groups,
+                "concurrency.max_concurrent_groups",
+            ),
+            (
+                self.concurrency.max_concurrent_orgs,
+                "concurrency.max_concurrent_orgs",
+            ),
+            (
+                self.concurrency.max_concurrent_merge_requests,
+                "concurrency.max_concurrent_merge_requests",
+            ),
+            (
+                self.concurrency.max_concurrent_requests,
+                "concurrency.max_concurrent_requests",
+            ),
+            (
+                self.concurrency.max_concurrent_requests_per_host,
+                "concurrency.max_concurrent_requests_per_host",
+            ),
+        ] {
+            if value == 0 {
+                problems.push(format!("{} must be at least 1", name));
+            }
+        }
+    }
+
+    fn resolve_gitlab_api(&self, problems: &mut Vec<String>) -> Option<GitlabApiSettings> {
+        let rest_endpoint =
+            required_url(&self.gitlab.rest_endpoint, "gitlab.rest_endpoint", problems);
+        let graphql_endpoint = required_url(
+            &self.gitlab.graphql_endpoint,
+            "gitlab.graphql_endpoint",
+            problems,
+        );
+        let api_token = resolve_secret(
+            &self.gitlab.api_token,
+            &self.gitlab.api_token_file,
+            "gitlab.api_token",
+            problems,
+        );
+
+        match (rest_endpoint, graphql_endpoint, api_token) {
+            (Some(rest_endpoint), Some(graphql_endpoint), Some(api_token)) => {
+                Some(GitlabApiSettings {
+                    rest_endpoint,
+                    graphql_endpoint,
+                    api_token,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn resolve_gitlab_sync(&self, problems: &mut Vec<String>) -> Option<GitlabSyncSettings> {
+        let api = self.resolve_gitlab_api(problems);
+
+        if self.gitlab.groups.is_empty() {
+            problems.push("gitlab.groups must contain at least one group".to_string());
+        }
+        let mut seen = HashSet::new();
+        for group in &self.gitlab.groups {
+            if group.full_path.trim().is_empty() {
+                problems.push("gitlab.groups contains a group with an empty full_path".to_string());
+            } else if !seen.insert(group.full_path.as_str()) {
+                problems.push(format!(
+                    "gitlab.groups contains {} more than once",
+                    group.full_path
+                ));
+            }
+        }
+        check_timestamp(
+            &self.gitlab.initial_ingestion_date,
+            "gitlab.initial_ingestion_date",
+            problems,
+        );
+        if self.gitlab.dead_letter_max_attempts < 1 {
+            problems.push("gitlab.dead_letter_max_attempts must be at least 1".to_string());
+        }
+        if self.gitlab.reconcile_overlap_hours < 0 {
+            problems.push("gitlab.reconcile_overlap_hours must not be negative".to_string());
+        }
+
+        let ai_needed = self
+            .gitlab
+            .groups
+            .iter()
+            .any(|group| group.ai_summaries.unwrap_or(self.ai.enabled));
+        let ai = if ai_needed {
+            Some(self.resolve_ai(problems)?)
+        } else {
+            None
+        };
+
+        api.map(|api| GitlabSyncSettings {
+            api,
+            groups: self.gitlab.groups.clone(),
+            upsert_merge_requests: self.gitlab.upsert_merge_requests,
+            fetch_approval_times: self.gitlab.fetch_approval_times,
+            initial_ingestion_date: self.gitlab.initial_ingestion_date.clone(),
+            dead_letter_max_attempts: self.gitlab.dead_letter_max_attempts,
+            reconcile_policy: ReconcilePolicy {
+                enabled: self.gitlab.reconcile,
+                overlap: Duration::hours(self.gitlab.reconcile_overlap_hours),
+            },
+            ai_enabled: self.ai.enabled,
+            ai,
+        })
+    }
+
+    fn resolve_ai(&self, problems: &mut Vec<String>) -> Option<AiSettings> {
+        let base_url = required_url(&self.ai.base_url, "ai.base_url", problems);
+        let model = required(&self.ai.model, "ai.model", problems);
+        let api_key = resolve_secret(
+            &self.ai.api_key,
+            &self.ai.api_key_file,
+            "ai.api_key",
+            problems,
+        );
+        if self.ai.max_context_chars == 0 {
+            problems.push("ai.max_context_chars must be at least 1".to_string());
+        }
+
+        match (base_url, model, api_key) {
+            (Some(base_url), Some(model), Some(api_key)) => Some(AiSettings {
+                base_url,
+                model,
+                api_key,
+                max_context_chars: self.ai.max_context_chars,
+            }),
+            _ => None,
+        }
+    }
+
+    fn resolve_copilot(&self, problems: &mut Vec<String>) -> Option<CopilotSettings> {
+        let api_token = resolve_secret(
+            &self.copilot.api_token,
+            &self.copilot.api_token_file,
+            "copilot.api_token",
+            problems,
+        );
+        check_url(&self.copilot.api_endpoint, "copilot.api_endpoint", problems);
+        if self.copilot.orgs.is_empty() {
+            problems.push("copilot.orgs must contain at least one org".to_string());
+        }
+        if self.copilot.report_lag_days < 0 {
+            problems.push("copilot.report_lag_days must not be negative".to_string());
+        }
+        check_timestamp(
+            &self.copilot.initial_ingestion_date,
+            "copilot.initial_ingestion_date",
+            problems,
+        );
+
+        api_token.map(|api_token| CopilotSettings {
+            api_token,
+            api_endpoint: self.copilot.api_endpoint.clone(),
+            api_version: self.copilot.api_version.clone(),
+            orgs: self.copilot.orgs.clone(),
+            report_lag_days: self.copilot.report_lag_days,
+            initial_ingestion_date: self.copilot.initial_ingestion_date.clone(),
+        })
+    }
+}
+
+impl GitlabConfig {
+    /// Replace the group list, keeping the per-group settings of groups that stay configured
+    pub fn set_group_paths(&mut self, full_paths: &[String]) {
+        self.groups = full_paths
+            .iter()
+            .map(|full_path| {
+                self.groups
+                    .iter()
+                    .find(|group| &group.full_path == full_path)
+                    .cloned()
+                    .unwrap_or_else(|| GroupConfig::new(full_path))
+            })
+            .collect();
+    }
+}
+
+/// Set an inline secret, replacing a secret file configured in an earlier layer
+pub fn set_secret(value: &mut Option<String>, file: &mut Option<String>, secret: String) {
+    *value = Some(secret);
+    *file = None;
+}
+
+/// Set a secret file, replacing an inline secret configured in an earlier layer
+pub fn set_secret_file(value: &mut Option<String>, file: &mut Option<String>, path: String) {
+    *value = None;
+    *file = Some(path);
+}
+
+pub fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Parse a comma separated list of window lengths in days, e.g. `7,30,90`
+pub fn parse_window_days(list: &str) -> Result<Vec<i32>, String> {
+    split_list(list)
+        .iter()
+        .map(|days| {
+            days.parse()
+                .map_err(|e| format!("invalid window '{}': {}", days, e))
+        })
+        .collect()
+}
+
+fn parse_env<T>(value: Option<String>, name: &str, target: &mut T, problems: &mut Vec<String>)
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(value) = value {
+        match value.trim().parse() {
+            Ok(parsed) => *target = parsed,
+            Err(e) => problems.push(format!("{}: invalid value '{}': {}", name, value, e)),
+        }
+    }
+}
+
+fn parse_schedule(
+    value: &Option<String>,
+    name: &str,
+    problems: &mut Vec<String>,
+) -> Option<CronSchedule> {
+    match CronSchedule::parse(value.as_deref()?) {
+        Ok(schedule) => Some(schedule),
+        Err(e) => {
+            problems.push(format!("{}: {}", name, e));
+            None
+        }
+    }
+}
+
+fn required(value: &Option<String>, name: &str, problems: &mut Vec<String>) -> Option<String> {
+    match value.as_deref().map(str::trim) {
+        Some(value) if !value.is_empty() => Some(value.to_string()),
+        _ => {
+            problems.push(format!("{} is required", name));
+            None
+        }
+    }
+}
+
+fn required_url(value: &Option<String>, name: &str, problems: &mut Vec<String>) -> Option<String> {
+    let value = required(value, name, problems)?;
+    check_url(&value, name, problems);
+    Some(value)
+}
+
+fn check_url(value: &str, name: &str, problems: &mut Vec<String>) {
+    if !value.starts_with("http://") && !value.starts_with("https://") {
+        problems.push(format!(
+            "{} must be an http:// or https:// URL, got '{}'",
+            name, value
+        ));
+    }
+}
+
+fn check_timestamp(value: &Option<String>, name: &str, problems: &mut Vec<String>) {
+    if let Some(value) = value {
+        if let Err(e) = OffsetDateTime::parse(value, &Rfc3339) {
+            problems.push(format!(
+                "{} must be an RFC 3339 timestamp, got '{}': {}",
+                name, value, e
+            ));
+        }
+    }
+}
+
+/// Resolve a secret given either inline or as a path to a file holding it, e.g. a mounted container secret
+fn resolve_secret(
+    value: &Option<String>,
+    file: &Option<String>,
+    name: &str,
+    problems: &mut Vec<String>,
+) -> Option<String> {
+    match (value, file) {
+        (Some(_), Some(_)) => {
+            problems.push(format!("set either {} or {}_file, not both", name, name));
+            None
+        }
+        (None, Some(path)) => match std::fs::read_to_string(path) {
+            Ok(secret) if !secret.trim().is_empty() => Some(secret.trim().to_string()),
+            Ok(_) => {
+                problems.push(format!("{}_file {} is empty", name, path));
+                None
+            }
+            Err(e) => {
+                problems.push(format!("{}_file {} cannot be read: {}", name, path, e));
+                None
+            }
+        },
+        (value, None) => required(value, name, problems),
+    }
+}
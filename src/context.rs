@@ -1,8 +1,13 @@
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
 use crate::{
     client::{
         copilot_usage_metrics_client::CopilotUsageMetricsClient,
         gitlab_graphql_client::GitlabGraphQLClient, gitlab_rest_client::GitlabRestClient,
     },
+    component::{import_progress::ImportProgressPolicy, merge_request::ReconcilePolicy},
+    scheduler::WorkScheduler,
     store::Store,
 };
 
@@ -15,7 +20,22 @@ pub struct GitlabContext {
     pub ai_model: String,
     pub ai_api_key: String,
     pub ai_max_context_chars: usize,
+    /// Whether merge requests get an AI summary; when disabled the summary stays empty
+    pub ai_summaries_enabled: bool,
     pub upsert_merge_requests: bool,
+    /// Whether the first approval time of approved merge requests is fetched, one request each
+    pub fetch_approval_times: bool,
+    pub reconcile_policy: ReconcilePolicy,
+    pub dead_letter_max_attempts: i32,
+    pub import_progress_policy: ImportProgressPolicy,
+    /// Merge requests of one page processed at the same time
+    pub max_concurrent_merge_requests: usize,
+    /// Bounds the API requests shared with every other import of the process
+    pub scheduler: WorkScheduler,
+    /// Cancelled on shutdown; imports stop after checkpointing the unit in progress
+    pub shutdown: CancellationToken,
+    /// Run recorded in `run_history` that this context belongs to
+    pub run_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone)]
@@ -24,4 +44,11 @@ pub struct CopilotContext {
     pub copilot_usage_metrics_client: CopilotUsageMetricsClient,
     pub github_api_version: String,
     pub report_lag_days: i64,
+    pub import_progress_policy: ImportProgressPolicy,
+    /// Bounds the API requests shared with every other import of the process
+    pub scheduler: WorkScheduler,
+    /// Cancelled on shutdown; imports stop after checkpointing the unit in progress
+    pub shutdown: CancellationToken,
+    /// Run recorded in `run_history` that this context belongs to
+    pub run_id: Option<Uuid>,
 }
@@ -0,0 +1,7 @@
+use time::macros::format_description;
+use time::Date;
+
+/// Parse a `YYYY-MM-DD` day, as taken by the command line and the API
+pub fn parse_day(value: &str) -> Result<Date, String> {
+    Date::parse(value, format_description!("[year]-[month]-[day]")).map_err(|e| e.to_string())
+}
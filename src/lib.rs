@@ -14,3 +14,44 @@ pub mod client;
 pub mod component;
 
 pub mod context;
+
+/// Defines the collector configuration.
+///
+/// The configuration is layered from a TOML file, the environment and command line flags.
+pub mod config;
+
+/// Defines the date parsing shared by the command line and the API.
+pub mod dates;
+
+/// Defines the cron schedules of the daemon jobs.
+pub mod schedule;
+
+/// Defines the work scheduler that bounds concurrent API requests.
+///
+/// The limits apply to the whole process and to each API host.
+pub mod scheduler;
+
+/// Defines the Prometheus metrics of the collector.
+///
+/// The metrics are served by the daemon on `/metrics` or written to a textfile after one-shot runs.
+pub mod metrics;
+
+/// Defines the structured logging and tracing of the collector.
+///
+/// Log lines are written as text or JSON; spans can additionally be exported over OTLP.
+pub mod telemetry;
+
+/// Defines the HTTP server of the daemon.
+///
+/// The server exposes the metrics, liveness, readiness and a JSON status summary.
+pub mod server;
+
+/// Defines the read-only REST API over the collected data.
+///
+/// The API serves paginated listings and flow metrics to clients holding an API key.
+pub mod api;
+
+/// Defines the command line interface.
+///
+/// The commands wire the components together for syncs, maintenance and status reporting.
+pub mod cli;
@@ -0,0 +1,243 @@
+use std::path::Path;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use thiserror::Error;
+use time::OffsetDateTime;
+
+#[derive(Error, Debug)]
+pub enum MetricsError {
+    #[error("Failed to encode metrics: {0}")]
+    EncodeError(#[from] prometheus::Error),
+    #[error("Failed to write metrics textfile {path}: {source}")]
+    WriteError {
+        path: String,
+        source: std::io::Error,
+    },
+}
+
+/// Prometheus metrics of the collector, shared by every job of the process
+struct Metrics {
+    registry: Registry,
+    /// Requests to the GitLab, GitHub and AI APIs by endpoint and HTTP status (`error` when no response arrived)
+    api_requests: IntCounterVec,
+    api_request_duration: HistogramVec,
+    /// Merge requests handled per group, by outcome (`persisted`, `failed`)
+    merge_requests_processed: IntCounterVec,
+    ai_request_duration: HistogramVec,
+    /// Failed AI summary attempts, by reason (`request`, `parse`)
+    ai_failures: IntCounterVec,
+    copilot_days_advanced: IntCounterVec,
+    /// GitLab webhooks received, by event (`other` for unsupported, `unknown` for unreadable ones)
+    /// and outcome (`queued`, `ignored`, `rejected`)
+    webhook_events: IntCounterVec,
+    db_write_duration: HistogramVec,
+    /// Unix time of the last successful import per collector and group/org
+    last_success: GaugeVec,
+}
+
+static METRICS: LazyLock<Result<Metrics, prometheus::Error>> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("collector".to_string()), None)?;
+        let metrics = Metrics {
+            api_requests: IntCounterVec::new(
+                Opts::new("api_requests_total", "API requests by endpoint and status"),
+                &["api", "endpoint", "status"],
+            )?,
+            api_request_duration: HistogramVec::new(
+                HistogramOpts::new("api_request_duration_seconds", "API request latency"),
+                &["api", "endpoint"],
+            )?,
+            merge_requests_processed: IntCounterVec::new(
+                Opts::new(
+                    "merge_requests_processed_total",
+                    "Merge requests processed per group",
+                ),
+                &["group", "outcome"],
+            )?,
+            ai_request_duration: HistogramVec::new(
+                HistogramOpts::new("ai_request_duration_seconds", "AI summary request latency")
+                    .buckets(vec![0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 40.0, 80.0]),
+                &["model"],
+            )?,
+            ai_failures: IntCounterVec::new(
+                Opts::new("ai_failures_total", "Failed AI summary attempts"),
+                &["model", "reason"],
+            )?,
+            copilot_days_advanced: IntCounterVec::new(
+                Opts::new(
+                    "copilot_days_advanced_total",
+                    "Copilot report days imported per org",
+                ),
+                &["org"],
+            )?,
+            webhook_events: IntCounterVec::new(
+                Opts::new("webhook_events_total", "GitLab webhooks received"),
+                &["event", "outcome"],
+            )?,
+            db_write_duration: HistogramVec::new(
+                HistogramOpts::new("db_write_duration_seconds", "Database write latency").buckets(
+                    vec![0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5],
+                ),
+                &["operation"],
+            )?,
+            last_success: GaugeVec::new(
+                Opts::new(
+                    "last_success_timestamp_seconds",
+                    "Unix time of the last successful import per group or org",
+                ),
+                &["collector", "key"],
+            )?,
+            registry,
+        };
+
+        metrics
+            .registry
+            .register(Box::new(metrics.api_requests.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.api_request_duration.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.merge_requests_processed.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.ai_request_duration.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.ai_failures.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.copilot_days_advanced.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.webhook_events.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.db_write_duration.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.last_success.clone()))?;
+
+        Ok(metrics)
+    }
+}
+
+/// Recording is skipped if the metrics could not be registered; `render` reports that error
+fn with_metrics<F: FnOnce(&Metrics)>(record: F) {
+    if let Ok(metrics) = METRICS.as_ref() {
+        record(metrics);
+    }
+}
+
+pub fn observe_api_request(api: &str, endpoint: &str, status: &str, elapsed: Duration) {
+    with_metrics(|metrics| {
+        metrics
+            .api_requests
+            .with_label_values(&[api, endpoint, status])
+            .inc();
+        metrics
+            .api_request_duration
+            .with_label_values(&[api, endpoint])
+            .observe(elapsed.as_secs_f64());
+    });
+}
+
+pub fn observe_ai_request(model: &str, elapsed: Duration, failure_reason: Option<&str>) {
+    with_metrics(|metrics| {
+        metrics
+            .ai_request_duration
+            .with_label_values(&[model])
+            .observe(elapsed.as_secs_f64());
+        if let Some(reason) = failure_reason {
+            metrics
+                .ai_failures
+                .with_label_values(&[model, reason])
+                .inc();
+        }
+    });
+}
+
+pub fn observe_db_write(operation: &str, elapsed: Duration) {
+    with_metrics(|metrics| {
+        metrics
+            .db_write_duration
+            .with_label_values(&[operation])
+            .observe(elapsed.as_secs_f64());
+    });
+}
+
+pub fn count_merge_request(group_full_path: &str, outcome: &str) {
+    with_metrics(|metrics| {
+        metrics
+            .merge_requests_processed
+            .with_label_values(&[group_full_path, outcome])
+            .inc();
+    });
+}
+
+pub fn count_copilot_day(org_slug: &str) {
+    with_metrics(|metrics| {
+        metrics
+            .copilot_days_advanced
+            .with_label_values(&[org_slug])
+            .inc();
+    });
+}
+
+pub fn count_webhook_event(event: &str, outcome: &str) {
+    with_metrics(|metrics| {
+        metrics
+            .webhook_events
+            .with_label_values(&[event, outcome])
+            .inc();
+    });
+}
+
+/// Record a successful import of a group (`gitlab`) or an org (`copilot`) for alerting on stale data
+pub fn record_success(collector: &str, key: &str) {
+    with_metrics(|metrics| {
+        metrics
+            .last_success
+            .with_label_values(&[collector, key])
+            .set(OffsetDateTime::now_utc().unix_timestamp() as f64);
+    });
+}
+
+/// All metrics in the Prometheus text exposition format
+pub fn render() -> Result<String, MetricsError> {
+    let metrics = METRICS
+        .as_ref()
+        .map_err(|e| MetricsError::EncodeError(prometheus::Error::Msg(e.to_string())))?;
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&metrics.registry.gather(), &mut buffer)?;
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// Write all metrics to `path` for the node_exporter textfile collector.
+///
+/// The file is written next to its destination and renamed, so the exporter never reads a partial file.
+pub fn write_textfile(path: &Path) -> Result<(), MetricsError> {
+    let write_error = |source| MetricsError::WriteError {
+        path: path.display().to_string(),
+        source,
+    };
+    let temp_path = path.with_extension("prom.tmp");
+    std::fs::write(&temp_path, render()?).map_err(write_error)?;
+    std::fs::rename(&temp_path, path).map_err(write_error)
+}
+
+/// Status label of an API response, `error` when the request failed before a response arrived
+pub fn status_label(result: &Result<reqwest::Response, reqwest::Error>) -> String {
+    match result {
+        Ok(response) => response.status().as_u16().to_string(),
+        Err(e) => e
+            .status()
+            .map_or_else(|| "error".to_string(), |status| status.as_u16().to_string()),
+    }
+}
@@ -0,0 +1,56 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use thiserror::Error;
+use time::OffsetDateTime;
+
+#[derive(Error, Debug)]
+pub enum ScheduleError {
+    #[error("Invalid cron expression '{expression}': {reason}")]
+    InvalidExpression { expression: String, reason: String },
+}
+
+/// Cron schedule of a daemon job, evaluated in UTC.
+///
+/// Accepts standard five-field expressions (`minute hour day-of-month month day-of-week`) as well
+/// as the six- and seven-field forms with a leading seconds and a trailing year field.
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    expression: String,
+    schedule: Schedule,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, ScheduleError> {
+        let expression = expression.trim();
+        // The cron crate always expects a seconds field
+        let normalized = if expression.split_whitespace().count() == 5 {
+            format!("0 {}", expression)
+        } else {
+            expression.to_string()
+        };
+
+        let schedule =
+            Schedule::from_str(&normalized).map_err(|e| ScheduleError::InvalidExpression {
+                expression: expression.to_string(),
+                reason: e.to_string(),
+            })?;
+
+        Ok(CronSchedule {
+            expression: expression.to_string(),
+            schedule,
+        })
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// First fire time strictly after `after`, or `None` when the schedule never fires again
+    pub fn next_after(&self, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        let after = DateTime::<Utc>::from_timestamp(after.unix_timestamp(), after.nanosecond())?;
+        let next = self.schedule.after(&after).next()?;
+        OffsetDateTime::from_unix_timestamp(next.timestamp()).ok()
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use thiserror::Error;
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+
+#[derive(Error, Debug)]
+pub enum SchedulerError {
+    #[error("Work scheduler closed: {0}")]
+    Closed(#[from] AcquireError),
+}
+
+/// Bounds the concurrent API requests of every import in the process, in total and per host.
+///
+/// One scheduler is shared by all groups, orgs and merge requests of a command, so raising the
+/// group or merge request concurrency never floods a single API beyond its host limit.
+#[derive(Debug, Clone)]
+pub struct WorkScheduler {
+    global: Arc<Semaphore>,
+    max_concurrent_per_host: usize,
+    hosts: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+/// Held while a request runs; dropping it frees the slot for the next request
+#[derive(Debug)]
+pub struct WorkPermit {
+    _host: OwnedSemaphorePermit,
+    _global: OwnedSemaphorePermit,
+}
+
+impl Default for WorkScheduler {
+    fn default() -> Self {
+        WorkScheduler::new(16, 8)
+    }
+}
+
+impl WorkScheduler {
+    pub fn new(max_concurrent: usize, max_concurrent_per_host: usize) -> Self {
+        WorkScheduler {
+            global: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            max_concurrent_per_host: max_concurrent_per_host.max(1),
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Wait for a free slot for a request to `url`.
+    ///
+    /// The host slot is taken first, so a request waiting for a busy host does not hold a
+    /// global slot that a request to another host could use.
+    pub async fn acquire(&self, url: &str) -> Result<WorkPermit, SchedulerError> {
+        let host = self.host_semaphore(url);
+        let host_permit = host.acquire_owned().await?;
+        let global_permit = self.global.clone().acquire_owned().await?;
+
+        Ok(WorkPermit {
+            _host: host_permit,
+            _global: global_permit,
+        })
+    }
+
+    /// Number of requests to `url`'s host that can start right now
+    pub fn available_for(&self, url: &str) -> usize {
+        self.host_semaphore(url)
+            .available_permits()
+            .min(self.global.available_permits())
+    }
+
+    fn host_semaphore(&self, url: &str) -> Arc<Semaphore> {
+        let host = host_key(url);
+        let mut hosts = self.hosts.lock().unwrap_or_else(PoisonError::into_inner);
+        hosts
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_concurrent_per_host)))
+            .clone()
+    }
+}
+
+/// Host and port of `url`, or the whole value when it is not an absolute URL
+fn host_key(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => match (parsed.host_str(), parsed.port_or_known_default()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => url.to_string(),
+        },
+        Err(_) => url.to_string(),
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use serde::Serialize;
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::api::constant_time_eq;
+use crate::component::import_progress::{
+    ImportProgressError, ImportProgressHandler, ImportProgressPolicy, ImportStatus,
+};
+use crate::component::run_history::{RunHistoryError, RunHistoryHandler};
+use crate::component::webhook::{self, WebhookQueueHandler};
+use crate::metrics;
+use crate::store::Store;
+
+#[derive(Error, Debug)]
+pub enum ServerError {
+    #[error("Run history error: {0}")]
+    RunHistoryError(#[from] RunHistoryError),
+    #[error("Import progress error: {0}")]
+    ImportProgressError(#[from] ImportProgressError),
+}
+
+/// State shared by the health and status endpoints
+#[derive(Debug, Clone)]
+pub struct ServerState {
+    pub store: Store,
+    /// Collectors of the scheduled jobs whose runs `/readyz` watches
+    pub collectors: Vec<String>,
+    /// Longest time a watched collector may go without run activity before `/readyz` fails
+    pub max_run_age: Duration,
+    pub import_progress_policy: ImportProgressPolicy,
+    /// Start of the process; a collector without any run counts as active since then
+    pub started_at: OffsetDateTime,
+    /// Serves `POST /webhooks/gitlab` when set
+    pub webhook: Option<WebhookReceiver>,
+}
+
+/// Accepts GitLab webhooks and queues the merge request refreshes they ask for
+#[derive(Debug, Clone)]
+pub struct WebhookReceiver {
+    /// Expected in the `X-Gitlab-Token` header
+    pub secret_token: String,
+    /// Full paths of the synced groups; events of projects outside them are ignored
+    pub groups: Vec<String>,
+}
+
+pub fn router(state: ServerState) -> Router {
+    let mut router = Router::new()
+        .route("/metrics", get(render_metrics))
+        .route("/healthz", get(health))
+        .route("/readyz", get(readiness))
+        .route("/status", get(status));
+    if state.webhook.is_some() {
+        router = router.route("/webhooks/gitlab", post(gitlab_webhook));
+    }
+    router.with_state(state)
+}
+
+/// Serve the HTTP endpoints on `listener` until `shutdown` is cancelled
+pub async fn serve(
+    listener: TcpListener,
+    state: ServerState,
+    shutdown: CancellationToken,
+) -> std::io::Result<()> {
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async move { shutdown.cancelled().await })
+        .await
+}
+
+async fn render_metrics() -> Response {
+    match metrics::render() {
+        Ok(body) => ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// The process is alive as long as it answers
+async fn health() -> &'static str {
+    "ok"
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    ready: bool,
+    checks: Vec<Check>,
+}
+
+#[derive(Serialize)]
+struct Check {
+    name: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, detail: Option<String>) -> Self {
+        Check {
+            name: name.into(),
+            ok: detail.is_none(),
+            detail,
+        }
+    }
+}
+
+/// Ready when the database answers, every embedded migration is applied and no watched
+/// collector has been without run activity for longer than `max_run_age`
+async fn readiness(State(state): State<ServerState>) -> Response {
+    let checks = readiness_checks(&state).await;
+    let ready = checks.iter().all(|check| check.ok);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    json_response(status, &Readiness { ready, checks })
+}
+
+async fn readiness_checks(state: &ServerState) -> Vec<Check> {
+    if let Err(e) = state.store.ping().await {
+        // Nothing else can be checked without the database
+        return vec![Check::new("database", Some(e.to_string()))];
+    }
+    let mut checks = vec![Check::new("database", None)];
+
+    checks.push(Check::new(
+        "migrations",
+        match state.store.pending_migrations().await {
+            Ok(pending) if pending.is_empty() => None,
+            Ok(pending) => Some(format!(
+                "pending migrations: {}",
+                pending
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            Err(e) => Some(e.to_string()),
+        },
+    ));
+
+    let run_history_handler = RunHistoryHandler {
+        store: state.store.clone(),
+    };
+    match run_history_handler
+        .fetch_last_activity(&state.collectors)
+        .await
+    {
+        Ok(last_activity) => {
+            let now = OffsetDateTime::now_utc();
+            for collector in &state.collectors {
+                checks.push(Check::new(
+                    format!("run:{}", collector),
+                    stale_run_detail(state, &last_activity, collector, now),
+                ));
+            }
+        }
+        Err(e) => checks.push(Check::new("runs", Some(e.to_string()))),
+    }
+
+    checks
+}
+
+fn stale_run_detail(
+    state: &ServerState,
+    last_activity: &HashMap<String, OffsetDateTime>,
+    collector: &str,
+    now: OffsetDateTime,
+) -> Option<String> {
+    let since = last_activity
+        .get(collector)
+        .map_or(state.started_at, |activity| {
+            (*activity).max(state.started_at)
+        });
+    (now - since > state.max_run_age).then(|| {
+        format!(
+            "no run activity since {}, more than {} hours ago",
+            timestamp(since),
+            state.max_run_age.whole_hours()
+        )
+    })
+}
+
+#[derive(Serialize)]
+struct Status {
+    runs: Vec<RunStatusBody>,
+    groups: Vec<GroupStatusBody>,
+    imports: Vec<ImportStatusBody>,
+}
+
+#[derive(Serialize)]
+struct RunStatusBody {
+    id: Uuid,
+    collector: String,
+    status: &'static str,
+    started_at: String,
+    completed_at: Option<String>,
+    error_summary: Option<String>,
+}
+
+#[derive(Serialize)]
+struct GroupStatusBody {
+    group_key: String,
+    import_type: String,
+    run_id: Uuid,
+    collector: String,
+    status: &'static str,
+    started_at: String,
+    items_fetched: i64,
+    items_persisted: i64,
+    items_failed: i64,
+    error_summary: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ImportStatusBody {
+    id: Uuid,
+    group_full_path: String,
+    import_type: String,
+    status: &'static str,
+    total_processed: i32,
+    last_cursor: Option<String>,
+    started_at: String,
+    last_activity_at: String,
+    error_message: Option<String>,
+    stale: bool,
+}
+
+/// Latest run per collector and per group/org, and every unfinished import
+async fn status(State(state): State<ServerState>) -> Response {
+    match collect_status(&state).await {
+        Ok(status) => json_response(StatusCode::OK, &status),
+        Err(e) => json_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            &serde_json::json!({ "error": e.to_string() }),
+        ),
+    }
+}
+
+async fn collect_status(state: &ServerState) -> Result<Status, ServerError> {
+    let run_history_handler = RunHistoryHandler {
+        store: state.store.clone(),
+    };
+    let runs = run_history_handler
+        .fetch_latest_runs()
+        .await?
+        .into_iter()
+        .map(|run| RunStatusBody {
+            id: run.id,
+            collector: run.collector,
+            status: run.status.as_str(),
+            started_at: timestamp(run.started_at),
+            completed_at: run.completed_at.map(timestamp),
+            error_summary: run.error_summary,
+        })
+        .collect();
+    let groups = run_history_handler
+        .fetch_latest_group_runs()
+        .await?
+        .into_iter()
+        .map(|group_run| GroupStatusBody {
+            group_key: group_run.stats.group_key,
+            import_type: group_run.stats.import_type,
+            run_id: group_run.run.id,
+            collector: group_run.run.collector,
+            status: group_run.run.status.as_str(),
+            started_at: timestamp(group_run.run.started_at),
+            items_fetched: group_run.stats.stats.items_fetched,
+            items_persisted: group_run.stats.stats.items_persisted,
+            items_failed: group_run.stats.stats.items_failed,
+            error_summary: group_run.stats.error_summary,
+        })
+        .collect();
+
+    let import_progress_handler = ImportProgressHandler {
+        store: state.store.clone(),
+        run_id: None,
+        policy: state.import_progress_policy.clone(),
+    };
+    let now = OffsetDateTime::now_utc();
+    let imports = import_progress_handler
+        .list_imports(&[
+            ImportStatus::InProgress,
+            ImportStatus::Interrupted,
+            ImportStatus::Failed,
+        ])
+        .await?
+        .into_iter()
+        .map(|import| ImportStatusBody {
+            stale: import_progress_handler.is_stale(&import, now),
+            id: import.id,
+            group_full_path: import.group_full_path,
+            import_type: import.import_type,
+            status: import.status.as_str(),
+            total_processed: import.total_processed,
+            last_cursor: import.last_cursor,
+            started_at: timestamp(import.started_at),
+            last_activity_at: timestamp(import.last_activity_at),
+            error_message: import.error_message,
+        })
+        .collect();
+
+    Ok(Status {
+        runs,
+        groups,
+        imports,
+    })
+}
+
+#[derive(Serialize)]
+struct WebhookAccepted {
+    queued: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct WebhookRejected {
+    error: String,
+}
+
+/// Queue the merge request or project a GitLab event affects; the daemon refreshes it shortly after.
+///
+/// Unsupported events and projects outside the synced groups are acknowledged without queueing,
+/// so GitLab does not disable the webhook for failing.
+async fn gitlab_webhook(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(receiver) = state.webhook.as_ref() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let token = headers
+        .get("x-gitlab-token")
+        .and_then(|value| value.to_str().ok());
+    if !token.is_some_and(|token| constant_time_eq(&receiver.secret_token, token)) {
+        metrics::count_webhook_event("unknown", "rejected");
+        return webhook_error(StatusCode::UNAUTHORIZED, "Missing or invalid webhook token");
+    }
+
+    let target = match serde_json::from_slice(&body)
+        .map_err(|e| webhook::WebhookError::InvalidPayload(e.to_string()))
+        .and_then(|payload| webhook::refresh_target(&payload))
+    {
+        Ok(Some(target)) => target,
+        Ok(None) => return webhook_ignored("other", "event does not affect merge requests"),
+        Err(e) => {
+            metrics::count_webhook_event("unknown", "rejected");
+            return webhook_error(StatusCode::BAD_REQUEST, &e.to_string());
+        }
+    };
+    let Some(group_full_path) = webhook::group_for_project(&receiver.groups, &target.project_path)
+    else {
+        return webhook_ignored(target.event.as_str(), "project is not in a synced group");
+    };
+
+    let queue = WebhookQueueHandler {
+        store: state.store.clone(),
+    };
+    match queue.enqueue(group_full_path, &target).await {
+        Ok(()) => {
+            metrics::count_webhook_event(target.event.as_str(), "queued");
+            json_response(
+                StatusCode::ACCEPTED,
+                &WebhookAccepted {
+                    queued: true,
+                    reason: None,
+                },
+            )
+        }
+        Err(e) => webhook_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+fn webhook_ignored(event: &str, reason: &str) -> Response {
+    metrics::count_webhook_event(event, "ignored");
+    json_response(
+        StatusCode::OK,
+        &WebhookAccepted {
+            queued: false,
+            reason: Some(reason.to_string()),
+        },
+    )
+}
+
+fn webhook_error(status: StatusCode, error: &str) -> Response {
+    json_response(
+        status,
+        &WebhookRejected {
+            error: error.to_string(),
+        },
+    )
+}
+
+pub(crate) fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response {
+    match serde_json::to_string(body) {
+        Ok(body) => (status, [(header::CONTENT_TYPE, "application/json")], body).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+pub(crate) fn timestamp(value: OffsetDateTime) -> String {
+    value.format(&Rfc3339).unwrap_or_else(|_| value.to_string())
+}
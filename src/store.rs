@@ -1,26 +1,329 @@
-use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::borrow::Cow;
+use std::str::FromStr;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use sqlx::error::BoxDynError;
+use sqlx::migrate::{Migration, MigrationSource, Migrator};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions, PgSslMode};
+use sqlx::Connection;
+use thiserror::Error;
+use tracing::{info, warn};
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("Invalid database URL: {0}")]
+    InvalidUrl(sqlx::Error),
+    #[error("Couldn't establish DB connection after {attempts} attempts: {source}")]
+    ConnectError { attempts: u32, source: sqlx::Error },
+    #[error("Invalid schema name '{0}': expected a lowercase identifier of at most 63 characters")]
+    InvalidSchema(String),
+    #[error("Couldn't read the migration history: {0}")]
+    MigrationHistoryError(sqlx::Error),
+    #[error("Database schema is not up to date ({0} pending migrations); run `migrate` first")]
+    PendingMigrations(usize),
+}
+
+/// Schema the migrations and queries are written against
+pub const DEFAULT_SCHEMA: &str = "engineering_metrics";
+
+/// Pool, session and TLS settings of the database connection
+#[derive(Debug, Clone)]
+pub struct StoreOptions {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// Longest wait for a free connection before a query fails
+    pub acquire_timeout: Duration,
+    /// Idle connections above `min_connections` are closed after this long
+    pub idle_timeout: Option<Duration>,
+    /// Server-side limit of every statement of the pool's sessions
+    pub statement_timeout: Option<Duration>,
+    /// Shown in `pg_stat_activity`, so the collector's sessions can be told apart
+    pub application_name: Option<String>,
+    /// Overrides the `sslmode` of the URL
+    pub ssl_mode: Option<PgSslMode>,
+    pub ssl_root_cert: Option<String>,
+    pub ssl_client_cert: Option<String>,
+    pub ssl_client_key: Option<String>,
+    /// Further attempts at the initial connection, e.g. while the database is starting. Every
+    /// attempt itself keeps trying for up to `acquire_timeout`.
+    pub connect_retries: u32,
+    /// Wait before the first retry; it doubles with every retry up to `max_retry_backoff`
+    pub retry_backoff: Duration,
+    pub max_retry_backoff: Duration,
+    /// Schema holding the collector's tables, so several tenants can share one database
+    pub schema: String,
+}
+
+impl Default for StoreOptions {
+    fn default() -> Self {
+        StoreOptions {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+            statement_timeout: None,
+            application_name: None,
+            ssl_mode: None,
+            ssl_root_cert: None,
+            ssl_client_cert: None,
+            ssl_client_key: None,
+            connect_retries: 0,
+            retry_backoff: Duration::from_secs(1),
+            max_retry_backoff: Duration::from_secs(30),
+            schema: DEFAULT_SCHEMA.to_string(),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Store {
     pub conn_pool: PgPool,
+    pub schema: String,
 }
 
 impl Store {
-    pub async fn new(db_url: &str) -> Self {
-        let db_pool = match PgPoolOptions::new()
-            .max_connections(10)
-            .connect(db_url)
-            .await
-        {
-            Ok(pool) => pool,
-            Err(e) => panic!("Couldn't establish DB connection:[], {}", e),
-        };
+    pub async fn new(db_url: &str) -> Result<Self, StoreError> {
+        Self::connect(db_url, &StoreOptions::default()).await
+    }
+
+    /// Open the connection pool, retrying the first connection with backoff while the database
+    /// is unreachable or still starting up
+    pub async fn connect(db_url: &str, options: &StoreOptions) -> Result<Self, StoreError> {
+        if !is_valid_schema_name(&options.schema) {
+            return Err(StoreError::InvalidSchema(options.schema.clone()));
+        }
+        let connect_options = connect_options(db_url, options)?;
+        let pool_options = PgPoolOptions::new()
+            .max_connections(options.max_connections)
+            .min_connections(options.min_connections)
+            .acquire_timeout(options.acquire_timeout)
+            .idle_timeout(options.idle_timeout);
 
-        Store { conn_pool: db_pool }
+        let mut backoff = options.retry_backoff;
+        let mut attempt = 1;
+        loop {
+            match pool_options
+                .clone()
+                .connect_with(connect_options.clone())
+                .await
+            {
+                Ok(pool) => {
+                    if attempt > 1 {
+                        info!(attempt, "Connected to the database");
+                    }
+                    return Ok(Store {
+                        conn_pool: pool,
+                        schema: options.schema.clone(),
+                    });
+                }
+                Err(e) if attempt <= options.connect_retries && is_transient(&e) => {
+                    warn!(
+                        attempt,
+                        max_attempts = options.connect_retries + 1,
+                        retry_in_ms = backoff.as_millis() as u64,
+                        error = %e,
+                        "Database is not reachable yet, retrying"
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(options.max_retry_backoff);
+                    attempt += 1;
+                }
+                Err(source) => {
+                    return Err(StoreError::ConnectError {
+                        attempts: attempt,
+                        source,
+                    })
+                }
+            }
+        }
     }
 
+    /// Apply the pending migrations to the configured schema.
+    ///
+    /// The default schema keeps its migration history in `public._sqlx_migrations`; any other
+    /// schema gets its own history table, so every tenant is migrated independently.
     pub async fn migrate(&self) -> Result<(), sqlx::Error> {
-        sqlx::migrate!("./migrations").run(&self.conn_pool).await?;
+        if self.schema == DEFAULT_SCHEMA {
+            sqlx::migrate!("./migrations").run(&self.conn_pool).await?;
+            return Ok(());
+        }
+
+        let migrator = Migrator::new(SchemaMigrations::new(&self.schema)).await?;
+        // The search path is changed for this session only, so it is kept out of the pool
+        let mut conn = self.conn_pool.acquire().await?.detach();
+        sqlx::query(&format!("CREATE SCHEMA IF NOT EXISTS {}", self.schema))
+            .execute(&mut conn)
+            .await?;
+        sqlx::query(&format!("SET search_path TO {}", self.schema))
+            .execute(&mut conn)
+            .await?;
+        let result = migrator.run(&mut conn).await;
+        conn.close().await?;
+        Ok(result?)
+    }
+
+    /// Point a query written against the default schema at the configured one
+    pub fn sql<'q>(&self, query: &'q str) -> Cow<'q, str> {
+        if self.schema == DEFAULT_SCHEMA {
+            Cow::Borrowed(query)
+        } else {
+            Cow::Owned(rename_schema(query, &self.schema))
+        }
+    }
+
+    /// Check that a connection can be acquired and answers a query
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("SELECT 1").execute(&self.conn_pool).await?;
         Ok(())
     }
+
+    /// Check that every migration is applied, without changing the database, so read-only
+    /// commands can run with a role that may not create or alter tables
+    pub async fn check_migrated(&self) -> Result<(), StoreError> {
+        let pending = self
+            .pending_migrations()
+            .await
+            .map_err(StoreError::MigrationHistoryError)?;
+        if !pending.is_empty() {
+            return Err(StoreError::PendingMigrations(pending.len()));
+        }
+        Ok(())
+    }
+
+    /// Versions of the migrations embedded in the binary that are not applied to the database
+    pub async fn pending_migrations(&self) -> Result<Vec<i64>, sqlx::Error> {
+        let history = if self.schema == DEFAULT_SCHEMA {
+            "_sqlx_migrations".to_string()
+        } else {
+            format!("{}._sqlx_migrations", self.schema)
+        };
+        // The history table only exists once migrations were applied
+        let has_history: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+            .bind(&history)
+            .fetch_one(&self.conn_pool)
+            .await?;
+        let applied: Vec<i64> = if has_history {
+            sqlx::query_scalar(&format!("SELECT version FROM {} WHERE success", history))
+                .fetch_all(&self.conn_pool)
+                .await?
+        } else {
+            Vec::new()
+        };
+
+        Ok(sqlx::migrate!("./migrations")
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| migration.version)
+            .filter(|version| !applied.contains(version))
+            .collect())
+    }
+}
+
+/// Whether `schema` can be used unquoted in SQL: a lowercase identifier that Postgres neither
+/// folds nor reserves for its own schemas
+pub fn is_valid_schema_name(schema: &str) -> bool {
+    let mut chars = schema.chars();
+    let starts_well = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_');
+    starts_well
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && schema.len() <= 63
+        && !schema.starts_with("pg_")
+}
+
+/// Replace the default schema name wherever it stands as a whole identifier
+fn rename_schema(sql: &str, schema: &str) -> String {
+    let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut renamed = String::with_capacity(sql.len());
+    let mut rest = sql;
+    while let Some(position) = rest.find(DEFAULT_SCHEMA) {
+        renamed.push_str(&rest[..position]);
+        let after = &rest[position + DEFAULT_SCHEMA.len()..];
+        if renamed.ends_with(is_identifier) || after.starts_with(is_identifier) {
+            renamed.push_str(DEFAULT_SCHEMA);
+        } else {
+            renamed.push_str(schema);
+        }
+        rest = after;
+    }
+    renamed.push_str(rest);
+    renamed
+}
+
+/// The embedded migrations, rewritten for another schema. The schema is created before they run,
+/// so the first migration must not fail on it already existing.
+#[derive(Debug)]
+struct SchemaMigrations(Vec<Migration>);
+
+impl SchemaMigrations {
+    fn new(schema: &str) -> Self {
+        let migrations = sqlx::migrate!("./migrations")
+            .iter()
+            .map(|migration| {
+                let sql = migration.sql.replace(
+                    &format!("CREATE SCHEMA {}", DEFAULT_SCHEMA),
+                    &format!("CREATE SCHEMA IF NOT EXISTS {}", DEFAULT_SCHEMA),
+                );
+                Migration::new(
+                    migration.version,
+                    migration.description.clone(),
+                    migration.migration_type,
+                    Cow::Owned(rename_schema(&sql, schema)),
+                    migration.no_tx,
+                )
+            })
+            .collect();
+        SchemaMigrations(migrations)
+    }
+}
+
+impl<'s> MigrationSource<'s> for SchemaMigrations {
+    fn resolve(self) -> BoxFuture<'s, Result<Vec<Migration>, BoxDynError>> {
+        Box::pin(async move { Ok(self.0) })
+    }
+}
+
+fn connect_options(db_url: &str, options: &StoreOptions) -> Result<PgConnectOptions, StoreError> {
+    let mut connect_options = PgConnectOptions::from_str(db_url).map_err(StoreError::InvalidUrl)?;
+
+    if let Some(application_name) = &options.application_name {
+        connect_options = connect_options.application_name(application_name);
+    }
+    if let Some(statement_timeout) = options.statement_timeout {
+        connect_options = connect_options.options([(
+            "statement_timeout",
+            format!("{}ms", statement_timeout.as_millis()),
+        )]);
+    }
+    if let Some(ssl_mode) = options.ssl_mode {
+        connect_options = connect_options.ssl_mode(ssl_mode);
+    }
+    if let Some(ssl_root_cert) = &options.ssl_root_cert {
+        connect_options = connect_options.ssl_root_cert(ssl_root_cert.as_str());
+    }
+    if let Some(ssl_client_cert) = &options.ssl_client_cert {
+        connect_options = connect_options.ssl_client_cert(ssl_client_cert.as_str());
+    }
+    if let Some(ssl_client_key) = &options.ssl_client_key {
+        connect_options = connect_options.ssl_client_key(ssl_client_key.as_str());
+    }
+
+    Ok(connect_options)
+}
+
+/// Errors worth another connection attempt: the server is down, restarting or not accepting
+/// connections yet. Wrong credentials or a missing database fail at once.
+fn is_transient(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut => true,
+        sqlx::Error::Database(e) => matches!(
+            e.code().as_deref(),
+            // cannot_connect_now (starting up / shutting down), too_many_connections,
+            // admin_shutdown, crash_shutdown
+            Some("57P03" | "53300" | "57P01" | "57P02")
+        ),
+        _ => false,
+    }
 }
@@ -0,0 +1,105 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use thiserror::Error;
+use tracing::{warn, Subscriber};
+use tracing_subscriber::filter::ParseError;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::{SubscriberInitExt, TryInitError};
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{LogFormat, LoggingConfig};
+
+#[derive(Error, Debug)]
+pub enum TelemetryError {
+    #[error("Invalid log level: {0}")]
+    InvalidLevel(#[from] ParseError),
+    #[error("Failed to build the OTLP span exporter: {0}")]
+    ExporterError(#[from] ExporterBuildError),
+    #[error("Failed to install the tracing subscriber: {0}")]
+    InitError(#[from] TryInitError),
+}
+
+/// Handle of the installed subscriber; `shutdown` flushes the spans that are not exported yet
+pub struct Telemetry {
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+/// Parse a level filter in `RUST_LOG` syntax
+pub fn parse_level(level: &str) -> Result<EnvFilter, TelemetryError> {
+    Ok(EnvFilter::try_new(level.trim())?)
+}
+
+/// Log line layer writing to `writer` in the configured format.
+///
+/// JSON lines carry the fields of the current span and of every enclosing span, so a line
+/// of a merge request stage can be traced back to its run, group and page.
+pub fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
+
+/// Install the global subscriber: level filter, log lines on stderr and, when an endpoint
+/// is configured, span export over OTLP/HTTP.
+pub fn init(config: &LoggingConfig) -> Result<Telemetry, TelemetryError> {
+    let filter = parse_level(&config.level)?;
+    let tracer_provider = match &config.otlp_endpoint {
+        Some(endpoint) => {
+            let exporter = SpanExporter::builder()
+                .with_http()
+                .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+                .build()?;
+            Some(
+                SdkTracerProvider::builder()
+                    .with_batch_exporter(exporter)
+                    .with_resource(
+                        Resource::builder()
+                            .with_service_name(config.service_name.clone())
+                            .build(),
+                    )
+                    .build(),
+            )
+        }
+        None => None,
+    };
+    let otel_layer = tracer_provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(config.service_name.clone()))
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer(config.format, std::io::stderr))
+        .with(otel_layer)
+        .try_init()?;
+
+    Ok(Telemetry { tracer_provider })
+}
+
+impl Telemetry {
+    /// Export the remaining spans; the exporter blocks, so it runs off the async workers
+    pub async fn shutdown(self) {
+        let Some(provider) = self.tracer_provider else {
+            return;
+        };
+        match tokio::task::spawn_blocking(move || provider.shutdown()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!(error = %e, "Failed to export the remaining spans"),
+            Err(e) => warn!(error = %e, "Span export task failed to join"),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use engineering_metrics_data_collector::metrics;
+
+#[test]
+fn should_render_recorded_metrics_in_the_prometheus_text_format() {
+    metrics::observe_api_request(
+        "gitlab_rest",
+        "merge_request_changes",
+        "200",
+        Duration::from_millis(120),
+    );
+    metrics::count_merge_request("render-group", "persisted");
+    metrics::record_success("gitlab", "render-group");
+
+    let rendered = metrics::render().unwrap();
+
+    assert!(rendered.contains(
+        r#"collector_api_requests_total{api="gitlab_rest",endpoint="merge_request_changes",status="200"}"#
+    ));
+    assert!(rendered.contains("collector_api_request_duration_seconds_bucket"));
+    assert!(rendered.contains(
+        r#"collector_merge_requests_processed_total{group="render-group",outcome="persisted"} 1"#
+    ));
+    assert!(rendered.contains(
+        r#"collector_last_success_timestamp_seconds{collector="gitlab",key="render-group"}"#
+    ));
+}
+
+#[test]
+fn should_write_metrics_to_a_textfile() {
+    metrics::count_copilot_day("textfile-org");
+    let path = std::env::temp_dir().join(format!("{}-collector.prom", std::process::id()));
+
+    metrics::write_textfile(&path).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains(r#"collector_copilot_days_advanced_total{org="textfile-org"} 1"#));
+    assert!(!path.with_extension("prom.tmp").exists());
+}
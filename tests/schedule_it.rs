@@ -0,0 +1,37 @@
+use engineering_metrics_data_collector::schedule::CronSchedule;
+use time::macros::datetime;
+
+#[test]
+fn should_fire_a_five_field_expression_on_the_minute() {
+    let schedule = CronSchedule::parse("*/30 * * * *").unwrap();
+
+    let next = schedule.next_after(datetime!(2026-10-19 10:05:42 UTC));
+
+    assert_eq!(next, Some(datetime!(2026-10-19 10:30:00 UTC)));
+    assert_eq!(schedule.expression(), "*/30 * * * *");
+}
+
+#[test]
+fn should_fire_strictly_after_the_given_time() {
+    let schedule = CronSchedule::parse("0 2 * * *").unwrap();
+
+    let next = schedule.next_after(datetime!(2026-10-19 02:00:00 UTC));
+
+    assert_eq!(next, Some(datetime!(2026-10-20 02:00:00 UTC)));
+}
+
+#[test]
+fn should_accept_expressions_with_seconds() {
+    let schedule = CronSchedule::parse("15 0 6 * * Mon").unwrap();
+
+    // 2026-10-19 is a Monday
+    let next = schedule.next_after(datetime!(2026-10-19 07:00:00 UTC));
+
+    assert_eq!(next, Some(datetime!(2026-10-26 06:00:15 UTC)));
+}
+
+#[test]
+fn should_reject_invalid_expressions() {
+    assert!(CronSchedule::parse("every hour").is_err());
+    assert!(CronSchedule::parse("61 * * * *").is_err());
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use engineering_metrics_data_collector::scheduler::WorkScheduler;
+use tokio::time::timeout;
+
+const GITLAB: &str = "https://gitlab.com/api/graphql";
+const GITLAB_REST: &str = "https://gitlab.com/api/v4";
+const GITHUB: &str = "https://api.github.com";
+
+#[tokio::test]
+async fn should_limit_concurrent_requests_per_host() {
+    let scheduler = WorkScheduler::new(10, 2);
+
+    let first = scheduler.acquire(GITLAB).await.unwrap();
+    let _second = scheduler.acquire(GITLAB_REST).await.unwrap();
+    assert_eq!(scheduler.available_for(GITLAB), 0);
+
+    // The GraphQL and REST endpoints share the host, while another host is unaffected
+    assert!(
+        timeout(Duration::from_millis(50), scheduler.acquire(GITLAB))
+            .await
+            .is_err()
+    );
+    assert_eq!(scheduler.available_for(GITHUB), 2);
+    let _github = scheduler.acquire(GITHUB).await.unwrap();
+
+    drop(first);
+    assert_eq!(scheduler.available_for(GITLAB), 1);
+    assert!(
+        timeout(Duration::from_millis(50), scheduler.acquire(GITLAB))
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn should_limit_concurrent_requests_across_hosts() {
+    let scheduler = WorkScheduler::new(2, 2);
+
+    let gitlab = scheduler.acquire(GITLAB).await.unwrap();
+    let _github = scheduler.acquire(GITHUB).await.unwrap();
+
+    assert_eq!(scheduler.available_for("http://localhost:11434"), 0);
+    assert!(timeout(
+        Duration::from_millis(50),
+        scheduler.acquire("http://localhost:11434")
+    )
+    .await
+    .is_err());
+
+    drop(gitlab);
+    assert!(timeout(
+        Duration::from_millis(50),
+        scheduler.acquire("http://localhost:11434")
+    )
+    .await
+    .is_ok());
+}
+
+#[tokio::test]
+async fn should_share_limits_between_clones() {
+    let scheduler = WorkScheduler::new(4, 1);
+    let clone = scheduler.clone();
+
+    let _permit = scheduler.acquire(GITHUB).await.unwrap();
+
+    assert_eq!(clone.available_for(GITHUB), 0);
+}
@@ -0,0 +1,90 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use engineering_metrics_data_collector::config::LogFormat;
+use engineering_metrics_data_collector::telemetry;
+use tracing::{info, info_span};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::Registry;
+
+/// Log lines written by the layer under test
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'w> MakeWriter<'w> for Buffer {
+    type Writer = Buffer;
+
+    fn make_writer(&'w self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+impl Buffer {
+    fn lines(&self) -> Vec<serde_json::Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+#[test]
+fn should_write_json_lines_with_the_fields_of_every_enclosing_span() {
+    let buffer = Buffer::default();
+    let subscriber =
+        Registry::default().with(telemetry::fmt_layer(LogFormat::Json, buffer.clone()));
+
+    tracing::subscriber::with_default(subscriber, || {
+        let run = info_span!("run", collector = "gitlab", run_id = 7);
+        let _run = run.enter();
+        let group = info_span!("group", group = "platform/backend");
+        let _group = group.enter();
+        let import = info_span!("import", import_id = 42);
+        let _import = import.enter();
+        let merge_request = info_span!("merge_request", mr_id = 1234, mr_iid = 56);
+        let _merge_request = merge_request.enter();
+        let stage = info_span!("fetch_changes");
+        let _stage = stage.enter();
+        info!(elapsed_ms = 1500, "Fetched merge request changes");
+    });
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 1);
+    let line = &lines[0];
+    assert_eq!(line["level"], "INFO");
+    assert_eq!(line["message"], "Fetched merge request changes");
+    assert_eq!(line["elapsed_ms"], 1500);
+    assert_eq!(line["span"]["name"], "fetch_changes");
+    let spans: Vec<&str> = line["spans"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|span| span["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        spans,
+        ["run", "group", "import", "merge_request", "fetch_changes"]
+    );
+    assert_eq!(line["spans"][1]["group"], "platform/backend");
+    assert_eq!(line["spans"][2]["import_id"], 42);
+    assert_eq!(line["spans"][3]["mr_id"], 1234);
+}
+
+#[test]
+fn should_reject_an_invalid_log_level() {
+    assert!(telemetry::parse_level("info,engineering_metrics_data_collector=debug").is_ok());
+    assert!(telemetry::parse_level("info,[").is_err());
+}